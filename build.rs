@@ -53,6 +53,7 @@ fn main() {
                 "EGL_KHR_create_context",
                 "EGL_EXT_create_context_robustness",
                 "EGL_KHR_create_context_no_error",
+                "EGL_KHR_context_flush_control",
                 "EGL_KHR_platform_x11",
                 "EGL_KHR_platform_android",
                 "EGL_KHR_platform_wayland",
@@ -112,6 +113,7 @@ fn main() {
                 "EGL_KHR_create_context",
                 "EGL_EXT_create_context_robustness",
                 "EGL_KHR_create_context_no_error",
+                "EGL_KHR_context_flush_control",
                 "EGL_KHR_platform_x11",
                 "EGL_KHR_platform_android",
                 "EGL_KHR_platform_wayland",
@@ -121,6 +123,19 @@ fn main() {
                 "EGL_MESA_platform_gbm",
                 "EGL_EXT_platform_wayland",
                 "EGL_EXT_platform_device",
+                "EGL_EXT_device_base",
+                "EGL_EXT_device_query",
+                "EGL_EXT_device_enumeration",
+                "EGL_EXT_device_drm",
+                "EGL_EXT_output_base",
+                "EGL_EXT_output_drm",
+                "EGL_KHR_image_base",
+                "EGL_EXT_image_dma_buf_import",
+                "EGL_EXT_image_dma_buf_import_modifiers",
+                "EGL_KHR_gl_texture_2D_image",
+                "EGL_MESA_image_dma_buf_export",
+                "EGL_KHR_swap_buffers_with_damage",
+                "EGL_EXT_swap_buffers_with_damage",
             ],
         )
         .write_bindings(gl_generator::StructGenerator, &mut file)
@@ -138,6 +153,7 @@ fn main() {
                 "EGL_KHR_create_context",
                 "EGL_EXT_create_context_robustness",
                 "EGL_KHR_create_context_no_error",
+                "EGL_KHR_context_flush_control",
                 "EGL_KHR_platform_x11",
                 "EGL_KHR_platform_android",
                 "EGL_KHR_platform_wayland",
@@ -147,6 +163,7 @@ fn main() {
                 "EGL_MESA_platform_gbm",
                 "EGL_EXT_platform_wayland",
                 "EGL_EXT_platform_device",
+                "EGL_ANDROID_recordable",
             ],
         )
         .write_bindings(gl_generator::StaticStructGenerator, &mut file)
@@ -164,6 +181,7 @@ fn main() {
                 "EGL_KHR_create_context",
                 "EGL_EXT_create_context_robustness",
                 "EGL_KHR_create_context_no_error",
+                "EGL_KHR_context_flush_control",
                 "EGL_KHR_platform_x11",
                 "EGL_KHR_platform_android",
                 "EGL_KHR_platform_wayland",
@@ -17,11 +17,17 @@ use PixelFormatRequirements;
 use api::egl;
 use api::egl::Context as EglContext;
 use std::cell::Cell;
+use std::fmt;
 use std::sync::Arc;
 use winit::os::android::EventsLoopExt;
 
 mod ffi;
 
+#[inline]
+pub fn has_current_context() -> bool {
+    egl::has_current_context()
+}
+
 struct AndroidContext {
     egl_context: EglContext,
     stopped: Option<Cell<bool>>,
@@ -51,6 +57,12 @@ impl android_glue::SyncEventHandler for AndroidSyncEventHandler {
     }
 }
 
+impl fmt::Debug for Context {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.egl_context.fmt(f)
+    }
+}
+
 impl Context {
     #[inline]
     pub fn new(
@@ -127,6 +139,19 @@ impl Context {
         unimplemented!()
     }
 
+    #[inline]
+    pub unsafe fn new_raw_egl(
+        display: egl::ffi::EGLDisplay,
+        context: egl::ffi::EGLContext,
+        config: egl::ffi::egl::types::EGLConfig,
+    ) -> Result<Self, CreationError> {
+        let context = EglContext::from_raw(display, context, config)?;
+        Ok(Context(Arc::new(AndroidContext {
+            egl_context: context,
+            stopped: None,
+        })))
+    }
+
     #[inline]
     pub unsafe fn make_current(&self) -> Result<(), ContextError> {
         if let Some(ref stopped) = self.0.stopped {
@@ -141,6 +166,18 @@ impl Context {
     #[inline]
     pub fn resize(&self, _: u32, _: u32) {}
 
+    #[inline]
+    pub fn reinitialize(&mut self) -> Result<(), ContextError> {
+        match Arc::get_mut(&mut self.0) {
+            Some(ctx) => ctx.egl_context.reinitialize(),
+            // the event handlers of windowed contexts hold the context too
+            None => Err(ContextError::OsError(
+                "Cannot reinitialize a windowed context on Android"
+                    .to_string(),
+            )),
+        }
+    }
+
     #[inline]
     pub fn is_current(&self) -> bool {
         self.0.egl_context.is_current()
@@ -166,6 +203,11 @@ impl Context {
         self.0.egl_context.get_api()
     }
 
+    #[inline]
+    pub fn framebuffer_size(&self) -> Option<(u32, u32)> {
+        self.0.egl_context.surface_size()
+    }
+
     #[inline]
     pub fn get_pixel_format(&self) -> PixelFormat {
         self.0.egl_context.get_pixel_format()
@@ -180,4 +222,14 @@ impl Context {
     pub unsafe fn get_egl_display(&self) -> egl::ffi::EGLDisplay {
         self.0.egl_context.get_egl_display()
     }
+
+    #[inline]
+    pub unsafe fn get_egl_surface(&self) -> egl::ffi::egl::types::EGLSurface {
+        self.0.egl_context.get_egl_surface()
+    }
+
+    #[inline]
+    pub unsafe fn get_egl_config(&self) -> egl::ffi::egl::types::EGLConfig {
+        self.0.egl_context.get_egl_config()
+    }
 }
@@ -115,11 +115,12 @@ impl Context {
             let width = (self.libcaca.caca_get_canvas_width)(canvas);
             let height = (self.libcaca.caca_get_canvas_height)(canvas);
 
+            let row_width = self.opengl.get_dimensions().0 as usize;
             let buffer = self
                 .opengl
                 .get_framebuffer()
-                .chunks(self.opengl.get_dimensions().0 as usize)
-                .flat_map(|i| i.iter().cloned())
+                .chunks(self.opengl.get_row_length() as usize)
+                .flat_map(|i| i[..row_width].iter().cloned())
                 .rev()
                 .collect::<Vec<u32>>();
 
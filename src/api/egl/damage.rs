@@ -0,0 +1,281 @@
+#![cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+
+use std::cmp;
+
+/// A rectangle of a surface, in pixels.
+///
+/// Like everywhere in EGL and OpenGL, the origin is the bottom-left corner
+/// of the surface and `y` goes up, unlike with most windowing systems.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    /// Creates the rectangle whose bottom-left corner is at `(x, y)`.
+    #[inline]
+    pub fn from_xywh(x: i32, y: i32, width: u32, height: u32) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Returns true if the rectangle has no area.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// Returns the part of the rectangle that is within `other`, or `None`
+    /// if they don't overlap.
+    pub fn intersect(&self, other: &Rect) -> Option<Rect> {
+        let left = cmp::max(self.x, other.x) as i64;
+        let bottom = cmp::max(self.y, other.y) as i64;
+        let right = cmp::min(self.right(), other.right());
+        let top = cmp::min(self.top(), other.top());
+        if right <= left || top <= bottom {
+            return None;
+        }
+        Some(Rect::from_extents(left, bottom, right, top))
+    }
+
+    /// Returns the smallest rectangle that contains both rectangles.
+    ///
+    /// Empty rectangles are ignored, and the size saturates instead of
+    /// overflowing.
+    pub fn union(&self, other: &Rect) -> Rect {
+        if other.is_empty() {
+            return *self;
+        }
+        if self.is_empty() {
+            return *other;
+        }
+        let left = cmp::min(self.x, other.x) as i64;
+        let bottom = cmp::min(self.y, other.y) as i64;
+        let right = cmp::max(self.right(), other.right());
+        let top = cmp::max(self.top(), other.top());
+        Rect::from_extents(left, bottom, right, top)
+    }
+
+    /// Returns true if the rectangles overlap or share part of an edge, in
+    /// which case their union covers little more than both of them.
+    fn touches(&self, other: &Rect) -> bool {
+        let left = cmp::max(self.x, other.x) as i64;
+        let bottom = cmp::max(self.y, other.y) as i64;
+        let right = cmp::min(self.right(), other.right());
+        let top = cmp::min(self.top(), other.top());
+        // touching corners only would make a much larger union
+        right >= left && top >= bottom && (right > left || top > bottom)
+    }
+
+    // computed in `i64` so that they can't overflow
+    fn right(&self) -> i64 {
+        self.x as i64 + self.width as i64
+    }
+
+    fn top(&self) -> i64 {
+        self.y as i64 + self.height as i64
+    }
+
+    fn from_extents(left: i64, bottom: i64, right: i64, top: i64) -> Rect {
+        let max = u32::max_value() as i64;
+        Rect {
+            x: left as i32,
+            y: bottom as i32,
+            width: cmp::min(right - left, max) as u32,
+            height: cmp::min(top - bottom, max) as u32,
+        }
+    }
+}
+
+/// Clamps `rects` to a `width` by `height` surface, in the layout of
+/// `eglSwapBuffersWithDamageKHR`, dropping the ones that end up empty.
+pub(super) fn damage_rects(
+    rects: &[Rect],
+    width: u32,
+    height: u32,
+) -> Vec<i32> {
+    let surface = Rect::from_xywh(0, 0, width, height);
+    rects
+        .iter()
+        .filter_map(|rect| rect.intersect(&surface))
+        .flat_map(|rect| {
+            // within the surface, so they fit in an `EGLint`
+            vec![rect.x, rect.y, rect.width as i32, rect.height as i32]
+        })
+        .collect()
+}
+
+/// The most rectangles given to `eglSwapBuffersWithDamage` by
+/// `coalesced_damage_rects`, above which the bounding box is used instead.
+///
+/// EGL doesn't report how many rectangles a driver handles well, and some
+/// merge them into their bounding box anyway past a few.
+pub(super) const MAX_DAMAGE_RECTS: usize = 16;
+
+/// Like `damage_rects`, but merges the rectangles that overlap or are
+/// adjacent, and falls back to their bounding box if more than `max` are
+/// left.
+pub(super) fn coalesced_damage_rects(
+    rects: &[Rect],
+    width: u32,
+    height: u32,
+    max: usize,
+) -> Vec<i32> {
+    let surface = Rect::from_xywh(0, 0, width, height);
+    let rects = rects
+        .iter()
+        .filter_map(|rect| rect.intersect(&surface))
+        .collect::<Vec<_>>();
+    let mut rects = coalesce(rects);
+    if rects.len() > max {
+        let bounds = rects.iter().fold(rects[0], |a, b| a.union(b));
+        rects = vec![bounds];
+    }
+    damage_rects(&rects, width, height)
+}
+
+/// Merges the rectangles that touch until none of them do.
+fn coalesce(mut rects: Vec<Rect>) -> Vec<Rect> {
+    let mut merged = true;
+    while merged {
+        merged = false;
+        let mut i = 0;
+        while i < rects.len() {
+            let mut j = i + 1;
+            while j < rects.len() {
+                if rects[i].touches(&rects[j]) {
+                    let other = rects.swap_remove(j);
+                    rects[i] = rects[i].union(&other);
+                    merged = true;
+                } else {
+                    j += 1;
+                }
+            }
+            i += 1;
+        }
+    }
+    rects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{coalesced_damage_rects, damage_rects, Rect};
+
+    #[test]
+    fn negative_coordinates_are_clamped() {
+        let rects = [Rect::from_xywh(-10, -5, 20, 10)];
+        assert_eq!(damage_rects(&rects, 100, 100), vec![0, 0, 10, 5]);
+    }
+
+    #[test]
+    fn rects_outside_the_surface_are_dropped() {
+        let rects = [
+            Rect::from_xywh(100, 0, 10, 10),
+            Rect::from_xywh(0, 200, 10, 10),
+            Rect::from_xywh(-20, -20, 20, 20),
+            Rect::from_xywh(90, 90, 20, 20),
+        ];
+        assert_eq!(damage_rects(&rects, 100, 100), vec![90, 90, 10, 10]);
+    }
+
+    #[test]
+    fn empty_rects_are_dropped() {
+        let rects =
+            [Rect::from_xywh(10, 10, 0, 10), Rect::from_xywh(10, 10, 10, 0)];
+        assert!(rects[0].is_empty() && rects[1].is_empty());
+        assert!(damage_rects(&rects, 100, 100).is_empty());
+        assert!(damage_rects(&[Rect::from_xywh(0, 0, 1, 1)], 0, 0).is_empty());
+
+        let rect = Rect::from_xywh(1, 2, 3, 4);
+        assert_eq!(rect.union(&Rect::from_xywh(50, 50, 0, 0)), rect);
+        assert_eq!(Rect::from_xywh(50, 50, 0, 0).union(&rect), rect);
+    }
+
+    #[test]
+    fn sizes_saturate() {
+        let huge = Rect::from_xywh(-5, -5, u32::max_value(), u32::max_value());
+        assert_eq!(damage_rects(&[huge], 100, 100), vec![0, 0, 100, 100]);
+
+        let far = Rect::from_xywh(i32::max_value(), 0, u32::max_value(), 1);
+        assert_eq!(far.intersect(&far), Some(far));
+
+        let union = Rect::from_xywh(i32::min_value(), 0, 1, 1).union(&far);
+        assert_eq!(union.x, i32::min_value());
+        assert_eq!(union.width, u32::max_value());
+        assert_eq!(union.height, 1);
+    }
+
+    #[test]
+    fn overlapping_rects_are_merged() {
+        let rects = [
+            Rect::from_xywh(0, 0, 10, 10),
+            Rect::from_xywh(5, 5, 10, 10),
+            Rect::from_xywh(50, 50, 10, 10),
+        ];
+        assert_eq!(
+            coalesced_damage_rects(&rects, 100, 100, 16),
+            vec![0, 0, 15, 15, 50, 50, 10, 10]
+        );
+    }
+
+    #[test]
+    fn adjacent_rects_are_merged() {
+        // side by side, then merged with the one on top of both
+        let rects = [
+            Rect::from_xywh(0, 0, 10, 10),
+            Rect::from_xywh(30, 10, 20, 10),
+            Rect::from_xywh(10, 0, 10, 10),
+            Rect::from_xywh(0, 10, 20, 10),
+        ];
+        assert_eq!(
+            coalesced_damage_rects(&rects, 100, 100, 16),
+            vec![0, 0, 20, 20, 30, 10, 20, 10]
+        );
+
+        // only the corners touch
+        let rects =
+            [Rect::from_xywh(0, 0, 10, 10), Rect::from_xywh(10, 10, 5, 5)];
+        assert_eq!(
+            coalesced_damage_rects(&rects, 100, 100, 16),
+            vec![0, 0, 10, 10, 10, 10, 5, 5]
+        );
+    }
+
+    #[test]
+    fn too_many_rects_fall_back_to_the_bounding_box() {
+        let rects = (0..5)
+            .map(|i| Rect::from_xywh(i * 20, i * 10, 5, 5))
+            .collect::<Vec<_>>();
+        assert_eq!(coalesced_damage_rects(&rects, 200, 200, 5).len(), 20);
+        assert_eq!(
+            coalesced_damage_rects(&rects, 200, 200, 4),
+            vec![0, 0, 85, 45]
+        );
+
+        // clamped and dropped before being counted
+        let rects = [
+            Rect::from_xywh(-10, -10, 15, 15),
+            Rect::from_xywh(300, 0, 10, 10),
+            Rect::from_xywh(50, 50, 0, 0),
+            Rect::from_xywh(20, 20, 5, 5),
+        ];
+        assert_eq!(
+            coalesced_damage_rects(&rects, 100, 100, 1),
+            vec![0, 0, 25, 25]
+        );
+        assert!(coalesced_damage_rects(&rects[1..3], 100, 100, 1).is_empty());
+    }
+}
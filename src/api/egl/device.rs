@@ -0,0 +1,175 @@
+#![cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+
+use super::{ffi, get_client_extensions, EGL};
+
+use std::ffi::CStr;
+use std::fs;
+use std::path::Path;
+use std::ptr;
+
+/// An EGL device, usually a GPU, as exposed by
+/// `EGL_EXT_device_enumeration`.
+///
+/// Devices can be used to create headless contexts that don't need any
+/// display server.
+#[derive(Clone, Debug)]
+pub struct Device {
+    device: ffi::egl::types::EGLDeviceEXT,
+}
+
+unsafe impl Send for Device {}
+unsafe impl Sync for Device {}
+
+impl Device {
+    /// Returns all the devices, or an empty list if device enumeration isn't
+    /// supported.
+    ///
+    /// Pass one of them to `EglDeviceContextExt::new_egl_device` to create a
+    /// context on that device through `EGL_EXT_platform_device`.
+    pub fn query_all() -> Vec<Device> {
+        let egl = match *EGL {
+            Some(ref egl) => egl,
+            None => return vec![],
+        };
+
+        let extensions = get_client_extensions(egl);
+        let has_extension =
+            |e: &str| extensions.iter().find(|s| s == &e).is_some();
+        if !has_extension("EGL_EXT_device_enumeration")
+            && !has_extension("EGL_EXT_device_base")
+        {
+            return vec![];
+        }
+        if !egl.QueryDevicesEXT.is_loaded() {
+            return vec![];
+        }
+
+        unsafe {
+            let mut num_devices = 0;
+            if egl.QueryDevicesEXT(0, ptr::null_mut(), &mut num_devices) == 0 {
+                return vec![];
+            }
+
+            let mut devices = vec![ptr::null(); num_devices as usize];
+            if egl.QueryDevicesEXT(
+                num_devices,
+                devices.as_mut_ptr(),
+                &mut num_devices,
+            ) == 0
+            {
+                return vec![];
+            }
+            devices.truncate(num_devices as usize);
+
+            devices
+                .into_iter()
+                .map(|device| Device { device })
+                .collect()
+        }
+    }
+
+    /// Returns the raw `EGLDeviceEXT` handle.
+    #[inline]
+    pub fn raw_device(&self) -> ffi::egl::types::EGLDeviceEXT {
+        self.device
+    }
+
+    /// Returns the path of the DRM device file of this device, like
+    /// `/dev/dri/card0`.
+    ///
+    /// Returns `None` if the device doesn't support `EGL_EXT_device_drm`.
+    pub fn drm_device_file(&self) -> Option<String> {
+        let egl = EGL.as_ref().unwrap();
+        if !egl.QueryDeviceStringEXT.is_loaded() {
+            return None;
+        }
+
+        unsafe {
+            let extensions = egl.QueryDeviceStringEXT(
+                self.device,
+                ffi::egl::EXTENSIONS as ffi::egl::types::EGLint,
+            );
+            if extensions.is_null() {
+                return None;
+            }
+            let extensions = CStr::from_ptr(extensions).to_string_lossy();
+            if !extensions.split(' ').any(|e| e == "EGL_EXT_device_drm") {
+                return None;
+            }
+
+            let file = egl.QueryDeviceStringEXT(
+                self.device,
+                ffi::egl::DRM_DEVICE_FILE_EXT as ffi::egl::types::EGLint,
+            );
+            if file.is_null() {
+                return None;
+            }
+            Some(CStr::from_ptr(file).to_string_lossy().into_owned())
+        }
+    }
+
+    /// Returns the PCI bus ID of this device, like `0000:01:00.0`.
+    ///
+    /// The ID is found through the sysfs entry of the DRM device file, so
+    /// this returns `None` if the device doesn't support
+    /// `EGL_EXT_device_drm` or if sysfs isn't available.
+    pub fn pci_bus_id(&self) -> Option<String> {
+        let file = self.drm_device_file()?;
+        let name = Path::new(&file).file_name()?;
+        let link = fs::read_link(
+            Path::new("/sys/class/drm").join(name).join("device"),
+        )
+        .ok()?;
+        link.file_name()?.to_str().map(|id| id.to_owned())
+    }
+}
+
+/// Returns the device `display` was created from, through
+/// `EGL_EXT_device_query`.
+pub(super) unsafe fn display_device(
+    display: ffi::egl::types::EGLDisplay,
+) -> Option<Device> {
+    let egl = EGL.as_ref().unwrap();
+    if !egl.QueryDisplayAttribEXT.is_loaded() {
+        return None;
+    }
+
+    let mut device = 0;
+    if egl.QueryDisplayAttribEXT(
+        display,
+        ffi::egl::DEVICE_EXT as ffi::egl::types::EGLint,
+        &mut device,
+    ) == 0
+        || device == 0
+    {
+        return None;
+    }
+    Some(Device {
+        device: device as ffi::egl::types::EGLDeviceEXT,
+    })
+}
+
+/// Returns the device with the given PCI bus ID, like `0000:01:00.0`.
+///
+/// The PCI domain can be omitted, in which case it defaults to `0000`.
+/// Unlike the order in which devices are enumerated, bus IDs stay the same
+/// across reboots and driver updates.
+pub fn device_by_pci_id(bus_id: &str) -> Option<Device> {
+    let bus_id = if bus_id.matches(':').count() == 1 {
+        format!("0000:{}", bus_id)
+    } else {
+        bus_id.to_owned()
+    };
+
+    Device::query_all().into_iter().find(|device| {
+        device
+            .pci_bus_id()
+            .map_or(false, |id| id.eq_ignore_ascii_case(&bus_id))
+    })
+}
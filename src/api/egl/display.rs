@@ -0,0 +1,290 @@
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+use super::output::{self, OutputLayer, OutputPort};
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+use super::image;
+use super::{
+    ffi, get_native_display, proc_address, DisplayRef, Egl, NativeDisplay, EGL,
+};
+use api::load_gl;
+use CreationError;
+
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::sync::Arc;
+
+/// An initialized `EGLDisplay`, which tells which EGL implementation and
+/// driver are behind a context.
+///
+/// `eglGetDisplay` returns the same `EGLDisplay` for the same native
+/// display, so every context and every `Display` of `get_or_create` on a
+/// native display share it. glutin counts the references they hold, and only
+/// calls `eglTerminate` once the last one is dropped, so that one of them
+/// can't terminate the display while the others still use it.
+///
+/// Clones of a `Display` share its reference, and so do the ones of
+/// `ContextExt::egl_display` with their context. Displays wrapped with
+/// `from_raw` don't hold any, and must not be used once the display is
+/// terminated.
+#[derive(Clone, Debug)]
+pub struct Display {
+    display: ffi::egl::types::EGLDisplay,
+    display_ref: Option<Arc<DisplayRef>>,
+}
+
+unsafe impl Send for Display {}
+unsafe impl Sync for Display {}
+
+impl Display {
+    /// Returns the display of `native`, initializing it if needed, and keeps
+    /// it initialized for as long as the returned `Display` or one of its
+    /// clones is alive.
+    ///
+    /// This lets a display be shared with code outside of glutin, like a
+    /// video decoder, without either side terminating it from under the
+    /// other.
+    ///
+    /// ```no_run
+    /// # extern crate glutin;
+    /// # use glutin::os::unix::{EglDisplay, EglNativeDisplay};
+    /// # fn main() {
+    /// let display = EglDisplay::get_or_create(EglNativeDisplay::Wayland(None))
+    ///     .unwrap();
+    /// let decoder_display = display.clone();
+    /// drop(display);
+    /// // the display is only terminated once both handles are dropped
+    /// assert!(decoder_display.is_initialized());
+    /// # }
+    /// ```
+    pub fn get_or_create(
+        native: NativeDisplay,
+    ) -> Result<Display, CreationError> {
+        let egl = match *EGL {
+            Some(ref egl) => egl,
+            None => return Err(CreationError::NotSupported("EGL not found")),
+        };
+
+        let display = get_native_display(egl, native);
+        if display.is_null() {
+            return Err(CreationError::DisplayUnavailable);
+        }
+        let (display_ref, _) = unsafe { DisplayRef::initialize(egl, display)? };
+
+        Ok(Display {
+            display,
+            display_ref: Some(Arc::new(display_ref)),
+        })
+    }
+
+    /// Wraps an `EGLDisplay`, which must be initialized for as long as the
+    /// `Display` is used.
+    #[inline]
+    pub unsafe fn from_raw(display: ffi::egl::types::EGLDisplay) -> Display {
+        Display {
+            display,
+            display_ref: None,
+        }
+    }
+
+    /// Wraps `display` along with the reference that keeps it initialized,
+    /// if any.
+    #[inline]
+    pub(super) fn with_ref(
+        display: ffi::egl::types::EGLDisplay,
+        display_ref: Option<Arc<DisplayRef>>,
+    ) -> Display {
+        Display {
+            display,
+            display_ref,
+        }
+    }
+
+    /// Returns whether the display is still initialized.
+    ///
+    /// This is always the case for displays of `get_or_create`, but not for
+    /// the ones of `from_raw`, whose display can be terminated by whoever
+    /// initialized it.
+    pub fn is_initialized(&self) -> bool {
+        if self.display_ref.is_some() {
+            return true;
+        }
+        unsafe { query_string(self.display, ffi::egl::VENDOR).is_some() }
+    }
+
+    /// Returns the raw `EGLDisplay` handle.
+    #[inline]
+    pub fn raw_display(&self) -> ffi::egl::types::EGLDisplay {
+        self.display
+    }
+
+    /// Returns the vendor of the EGL implementation, through `EGL_VENDOR`.
+    pub fn vendor(&self) -> Option<String> {
+        unsafe { query_string(self.display, ffi::egl::VENDOR) }
+    }
+
+    /// Returns the version of EGL, followed by vendor-specific information,
+    /// through `EGL_VERSION`.
+    pub fn version(&self) -> Option<String> {
+        unsafe { query_string(self.display, ffi::egl::VERSION) }
+    }
+
+    /// Returns the extensions supported by the display.
+    ///
+    /// These don't include the client extensions, which are the same for
+    /// every display, see `client_extensions`.
+    pub fn extensions(&self) -> Vec<String> {
+        unsafe { query_string(self.display, ffi::egl::EXTENSIONS) }
+            .map(|list| list.split(' ').map(|e| e.to_string()).collect())
+            .unwrap_or_else(Vec::new)
+    }
+
+    /// Returns the client extensions of the EGL implementation, like
+    /// `EGL_EXT_platform_base`, which are queried without any display.
+    ///
+    /// Returns an empty list if EGL can't be loaded, or if it supports
+    /// neither EGL 1.5 nor `EGL_EXT_client_extensions`.
+    pub fn client_extensions() -> Vec<String> {
+        unsafe { query_string(ffi::egl::NO_DISPLAY, ffi::egl::EXTENSIONS) }
+            .map(|list| list.split(' ').map(|e| e.to_string()).collect())
+            .unwrap_or_else(Vec::new)
+    }
+
+    /// Returns the layers the display can present to, like the CRTCs and
+    /// planes of DRM, through `EGL_EXT_output_base`.
+    ///
+    /// Together with `OutputLayer::current_mode`, this tells the size of
+    /// the screen when rendering fullscreen on a device display, without X11
+    /// or Wayland. Returns `None` if the extension isn't supported.
+    ///
+    /// ```no_run
+    /// # extern crate glutin;
+    /// # use glutin::os::unix::{ContextExt, EglDevice, EglDeviceContextExt};
+    /// # fn main() {
+    /// let device = EglDevice::query_all().into_iter().next().unwrap();
+    /// let cb = glutin::ContextBuilder::new();
+    /// let context = glutin::Context::new_egl_device(cb, &device).unwrap();
+    /// let display = context.egl_display().unwrap();
+    /// for layer in display.output_layers().unwrap_or_else(Vec::new) {
+    ///     if let Some(mode) = layer.current_mode() {
+    ///         println!(
+    ///             "CRTC {:?}: {}x{} at {} Hz",
+    ///             layer.drm_crtc(),
+    ///             mode.width,
+    ///             mode.height,
+    ///             mode.refresh_rate
+    ///         );
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    pub fn output_layers(&self) -> Option<Vec<OutputLayer>> {
+        if !self.extensions().iter().any(|e| e == "EGL_EXT_output_base") {
+            return None;
+        }
+        unsafe { output::output_layers(self.display) }
+    }
+
+    /// Returns the ports screens can be plugged into, like the connectors
+    /// of DRM, through `EGL_EXT_output_base`.
+    ///
+    /// Returns `None` if the extension isn't supported.
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    pub fn output_ports(&self) -> Option<Vec<OutputPort>> {
+        if !self.extensions().iter().any(|e| e == "EGL_EXT_output_base") {
+            return None;
+        }
+        unsafe { output::output_ports(self.display) }
+    }
+
+    /// Returns the DRM format modifiers the display can import dmabufs of
+    /// the DRM fourcc `format` with, and render to, through
+    /// `EGL_EXT_image_dma_buf_import_modifiers`.
+    ///
+    /// Returns `None` if the extension isn't supported.
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    pub fn dmabuf_modifiers(&self, format: u32) -> Option<Vec<u64>> {
+        unsafe { image::dmabuf_modifiers(self.display, format) }
+    }
+
+    /// Returns the name of the driver, like `iris` or `radeonsi`, through
+    /// `EGL_MESA_query_driver`.
+    ///
+    /// Returns `None` if the extension isn't supported, which is the case
+    /// with drivers outside of Mesa.
+    pub fn driver_name(&self) -> Option<String> {
+        let egl = EGL.as_ref()?;
+        unsafe { driver_name(egl, self.display) }
+    }
+}
+
+unsafe fn query_string(
+    display: ffi::egl::types::EGLDisplay,
+    name: ffi::egl::types::EGLenum,
+) -> Option<String> {
+    let egl = EGL.as_ref()?;
+    let p = egl.QueryString(display, name as i32);
+    if p.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(p).to_string_lossy().into_owned())
+    }
+}
+
+/// Returns the name of the driver of the display through
+/// `EGL_MESA_query_driver`, or `None` if the extension isn't supported.
+pub(super) unsafe fn driver_name(
+    egl: &Egl,
+    display: ffi::egl::types::EGLDisplay,
+) -> Option<String> {
+    let extensions = egl.QueryString(display, ffi::egl::EXTENSIONS as i32);
+    if extensions.is_null()
+        || !CStr::from_ptr(extensions)
+            .to_string_lossy()
+            .split(' ')
+            .any(|e| e == "EGL_MESA_query_driver")
+    {
+        return None;
+    }
+
+    let name = "eglGetDisplayDriverName";
+    let get_display_driver_name: extern "system" fn(
+        ffi::egl::types::EGLDisplay,
+    ) -> *const c_char =
+        load_gl(name, proc_address(egl, name) as *const ()).ok()?;
+
+    let driver = get_display_driver_name(display);
+    if driver.is_null() {
+        return None;
+    }
+    Some(CStr::from_ptr(driver).to_string_lossy().into_owned())
+}
@@ -0,0 +1,373 @@
+#![cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+
+use super::{error_name, ffi, proc_address, EGL};
+use api::load_gl;
+use ContextError;
+
+use libc;
+
+use std::ffi::CStr;
+use std::mem;
+use std::os::raw::{c_int, c_void};
+use std::os::unix::io::RawFd;
+use std::ptr;
+
+/// An `EGLImageKHR`, which lets buffers from outside of OpenGL, like the
+/// dmabufs of a video decoder, be used as textures.
+///
+/// The image is destroyed when dropped, so it must not outlive the display
+/// of the context that created it.
+#[derive(Debug)]
+pub struct EglImage {
+    display: ffi::egl::types::EGLDisplay,
+    image: ffi::egl::types::EGLImageKHR,
+}
+
+impl EglImage {
+    /// Imports a single-plane dmabuf through `EGL_EXT_image_dma_buf_import`.
+    ///
+    /// `format` is a DRM fourcc code and `stride` is in bytes. The plane is
+    /// assumed to start at offset 0. `modifier` requires
+    /// `EGL_EXT_image_dma_buf_import_modifiers`.
+    ///
+    /// EGL doesn't take ownership of `fd`, so the caller may close it once
+    /// this returns.
+    pub unsafe fn from_dmabuf(
+        display: ffi::egl::types::EGLDisplay,
+        fd: RawFd,
+        format: u32,
+        width: u32,
+        height: u32,
+        stride: u32,
+        modifier: Option<u64>,
+    ) -> Result<EglImage, ContextError> {
+        let egl = EGL.as_ref().unwrap();
+
+        if !has_display_extension(display, "EGL_EXT_image_dma_buf_import")
+            || !egl.CreateImageKHR.is_loaded()
+        {
+            return Err(ContextError::OsError(format!(
+                "EGL_EXT_image_dma_buf_import is not supported"
+            )));
+        }
+
+        let mut attribs = vec![
+            ffi::egl::WIDTH as c_int,
+            width as c_int,
+            ffi::egl::HEIGHT as c_int,
+            height as c_int,
+            ffi::egl::LINUX_DRM_FOURCC_EXT as c_int,
+            format as c_int,
+            ffi::egl::DMA_BUF_PLANE0_FD_EXT as c_int,
+            fd as c_int,
+            ffi::egl::DMA_BUF_PLANE0_OFFSET_EXT as c_int,
+            0,
+            ffi::egl::DMA_BUF_PLANE0_PITCH_EXT as c_int,
+            stride as c_int,
+        ];
+
+        if let Some(modifier) = modifier {
+            if !has_display_extension(
+                display,
+                "EGL_EXT_image_dma_buf_import_modifiers",
+            ) {
+                return Err(ContextError::OsError(format!(
+                    "EGL_EXT_image_dma_buf_import_modifiers is not supported"
+                )));
+            }
+            attribs.push(ffi::egl::DMA_BUF_PLANE0_MODIFIER_LO_EXT as c_int);
+            attribs.push(modifier as u32 as c_int);
+            attribs.push(ffi::egl::DMA_BUF_PLANE0_MODIFIER_HI_EXT as c_int);
+            attribs.push((modifier >> 32) as u32 as c_int);
+        }
+
+        attribs.push(ffi::egl::NONE as c_int);
+
+        let image = egl.CreateImageKHR(
+            display,
+            ffi::egl::NO_CONTEXT,
+            ffi::egl::LINUX_DMA_BUF_EXT,
+            ptr::null(),
+            attribs.as_ptr(),
+        );
+        if image.is_null() {
+            return Err(ContextError::OsError(format!(
+                "eglCreateImageKHR failed: {}",
+                error_name(egl.GetError() as u32)
+            )));
+        }
+
+        Ok(EglImage { display, image })
+    }
+
+    /// Creates an image that shares the storage of `texture`, a
+    /// `GL_TEXTURE_2D` of `context`, through `EGL_KHR_gl_texture_2D_image`.
+    pub unsafe fn from_texture_2d(
+        display: ffi::egl::types::EGLDisplay,
+        context: ffi::egl::types::EGLContext,
+        texture: u32,
+    ) -> Result<EglImage, ContextError> {
+        let egl = EGL.as_ref().unwrap();
+
+        if !has_display_extension(display, "EGL_KHR_gl_texture_2D_image")
+            || !egl.CreateImageKHR.is_loaded()
+        {
+            return Err(ContextError::OsError(format!(
+                "EGL_KHR_gl_texture_2D_image is not supported"
+            )));
+        }
+
+        let attribs = [ffi::egl::NONE as c_int];
+        let image = egl.CreateImageKHR(
+            display,
+            context,
+            ffi::egl::GL_TEXTURE_2D_KHR,
+            texture as usize as ffi::egl::types::EGLClientBuffer,
+            attribs.as_ptr(),
+        );
+        if image.is_null() {
+            return Err(ContextError::OsError(format!(
+                "eglCreateImageKHR failed: {}",
+                error_name(egl.GetError() as u32)
+            )));
+        }
+
+        Ok(EglImage { display, image })
+    }
+
+    /// Exports the image as one dmabuf file descriptor per plane, through
+    /// `EGL_MESA_image_dma_buf_export`.
+    ///
+    /// The returned `DmabufHandle` owns the file descriptors, which stay
+    /// valid after the image is dropped.
+    pub unsafe fn export_dmabuf(&self) -> Result<DmabufHandle, ContextError> {
+        let egl = EGL.as_ref().unwrap();
+
+        if !has_display_extension(self.display, "EGL_MESA_image_dma_buf_export")
+            || !egl.ExportDMABUFImageQueryMESA.is_loaded()
+            || !egl.ExportDMABUFImageMESA.is_loaded()
+        {
+            return Err(ContextError::OsError(format!(
+                "EGL_MESA_image_dma_buf_export is not supported"
+            )));
+        }
+
+        let mut fourcc = 0;
+        let mut num_planes = 0;
+        let mut modifier = 0;
+        if egl.ExportDMABUFImageQueryMESA(
+            self.display,
+            self.image,
+            &mut fourcc,
+            &mut num_planes,
+            &mut modifier,
+        ) == 0
+        {
+            return Err(ContextError::OsError(format!(
+                "eglExportDMABUFImageQueryMESA failed: {}",
+                error_name(egl.GetError() as u32)
+            )));
+        }
+
+        let num_planes = num_planes as usize;
+        let mut fds = vec![-1; num_planes];
+        let mut strides = vec![0; num_planes];
+        let mut offsets = vec![0; num_planes];
+        if egl.ExportDMABUFImageMESA(
+            self.display,
+            self.image,
+            fds.as_mut_ptr(),
+            strides.as_mut_ptr(),
+            offsets.as_mut_ptr(),
+        ) == 0
+        {
+            return Err(ContextError::OsError(format!(
+                "eglExportDMABUFImageMESA failed: {}",
+                error_name(egl.GetError() as u32)
+            )));
+        }
+
+        Ok(DmabufHandle {
+            fds: fds.into_iter().map(|fd| fd as RawFd).collect(),
+            strides: strides.into_iter().map(|s| s as u32).collect(),
+            offsets: offsets.into_iter().map(|o| o as u32).collect(),
+            fourcc: fourcc as u32,
+            modifier: modifier as u64,
+        })
+    }
+
+    /// Returns the raw `EGLImageKHR` handle.
+    #[inline]
+    pub fn raw_image(&self) -> ffi::egl::types::EGLImageKHR {
+        self.image
+    }
+
+    /// Makes the image the storage of the texture currently bound to
+    /// `target`, usually `GL_TEXTURE_2D` or `GL_TEXTURE_EXTERNAL_OES`,
+    /// through `glEGLImageTargetTexture2DOES`.
+    ///
+    /// A context must be current on this thread.
+    pub unsafe fn bind_to_texture_2d(
+        &self,
+        target: u32,
+    ) -> Result<(), ContextError> {
+        let egl = EGL.as_ref().unwrap();
+
+        let name = "glEGLImageTargetTexture2DOES";
+        let image_target_texture_2d: extern "system" fn(u32, *const c_void) =
+            load_gl(name, proc_address(egl, name) as *const ())?;
+
+        image_target_texture_2d(target, self.image as *const c_void);
+        Ok(())
+    }
+}
+
+impl Drop for EglImage {
+    fn drop(&mut self) {
+        let egl = EGL.as_ref().unwrap();
+        unsafe {
+            egl.DestroyImageKHR(self.display, self.image);
+        }
+    }
+}
+
+/// A buffer exported as dmabufs, with one file descriptor per plane.
+///
+/// The handle owns the file descriptors and closes them when dropped. To
+/// hand them to another process or to an encoder that takes ownership, call
+/// `into_fds`, after which closing them is up to the caller.
+#[derive(Debug)]
+pub struct DmabufHandle {
+    fds: Vec<RawFd>,
+    strides: Vec<u32>,
+    offsets: Vec<u32>,
+    fourcc: u32,
+    modifier: u64,
+}
+
+impl DmabufHandle {
+    /// Returns the file descriptor of each plane. They are still owned by
+    /// the handle.
+    #[inline]
+    pub fn fds(&self) -> &[RawFd] {
+        &self.fds
+    }
+
+    /// Returns the stride of each plane, in bytes.
+    #[inline]
+    pub fn strides(&self) -> &[u32] {
+        &self.strides
+    }
+
+    /// Returns the offset of each plane, in bytes.
+    #[inline]
+    pub fn offsets(&self) -> &[u32] {
+        &self.offsets
+    }
+
+    /// Returns the DRM fourcc code of the buffer.
+    #[inline]
+    pub fn fourcc(&self) -> u32 {
+        self.fourcc
+    }
+
+    /// Returns the DRM format modifier of the buffer.
+    #[inline]
+    pub fn modifier(&self) -> u64 {
+        self.modifier
+    }
+
+    /// Gives up ownership of the file descriptors, which the caller must
+    /// close.
+    #[inline]
+    pub fn into_fds(mut self) -> Vec<RawFd> {
+        mem::replace(&mut self.fds, Vec::new())
+    }
+}
+
+impl Drop for DmabufHandle {
+    fn drop(&mut self) {
+        for &fd in &self.fds {
+            unsafe {
+                libc::close(fd);
+            }
+        }
+    }
+}
+
+/// Returns the modifiers of the DRM fourcc `format` that `display` can
+/// import dmabufs with, through `EGL_EXT_image_dma_buf_import_modifiers`.
+///
+/// Modifiers that can only be sampled as `GL_TEXTURE_EXTERNAL_OES` are left
+/// out, since such images can't be rendered to. Returns `None` if the
+/// extension isn't supported.
+pub(super) unsafe fn dmabuf_modifiers(
+    display: ffi::egl::types::EGLDisplay,
+    format: u32,
+) -> Option<Vec<u64>> {
+    let egl = EGL.as_ref().unwrap();
+    if !has_display_extension(display, "EGL_EXT_image_dma_buf_import_modifiers")
+        || !egl.QueryDmaBufModifiersEXT.is_loaded()
+    {
+        return None;
+    }
+
+    let mut num = 0;
+    if egl.QueryDmaBufModifiersEXT(
+        display,
+        format as c_int,
+        0,
+        ptr::null_mut(),
+        ptr::null_mut(),
+        &mut num,
+    ) == 0
+    {
+        return None;
+    }
+
+    let mut modifiers = vec![0; num as usize];
+    let mut external_only = vec![0; num as usize];
+    if egl.QueryDmaBufModifiersEXT(
+        display,
+        format as c_int,
+        num,
+        modifiers.as_mut_ptr(),
+        external_only.as_mut_ptr(),
+        &mut num,
+    ) == 0
+    {
+        return None;
+    }
+    modifiers.truncate(num as usize);
+
+    Some(
+        modifiers
+            .into_iter()
+            .zip(external_only)
+            .filter(|&(_, external_only)| external_only == 0)
+            .map(|(modifier, _)| modifier as u64)
+            .collect(),
+    )
+}
+
+/// Checks if the display supports `ext`.
+pub(super) unsafe fn has_display_extension(
+    display: ffi::egl::types::EGLDisplay,
+    ext: &str,
+) -> bool {
+    let egl = EGL.as_ref().unwrap();
+    let p = egl.QueryString(display, ffi::egl::EXTENSIONS as i32);
+    if p.is_null() {
+        return false;
+    }
+    CStr::from_ptr(p)
+        .to_string_lossy()
+        .split(' ')
+        .any(|e| e == ext)
+}
@@ -10,28 +10,98 @@
 #![allow(unused_variables)]
 
 use Api;
+use CaveatPolicy;
+use ConfigCaveat;
 use ContextError;
 use CreationError;
 use GlAttributes;
+use GlProfile;
 use GlRequest;
 use PixelFormat;
 use PixelFormatRequirements;
 use ReleaseBehavior;
 use Robustness;
 
+use api::load_gl;
+use registry::{self, Backend};
+
 use std::cell::Cell;
+use std::collections::HashMap;
 use std::ffi::{CStr, CString};
-use std::ops::{Deref, DerefMut};
-use std::os::raw::{c_int, c_void};
-use std::{mem, ptr};
+use std::ops::{Deref, DerefMut, RangeInclusive};
+use std::os::raw::{c_char, c_int, c_void};
+use std::sync::{Arc, Mutex};
+use std::{fmt, mem, ptr};
+
+#[cfg(not(target_os = "android"))]
+use libloading::Library;
 
+mod damage;
+mod device;
+mod display;
 pub mod ffi;
+mod image;
+mod output;
+mod render_to_texture;
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+pub use self::damage::Rect;
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+pub use self::device::{device_by_pci_id, Device};
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+pub use self::display::Display;
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+pub use self::image::{DmabufHandle, EglImage};
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+pub use self::output::{OutputLayer, OutputMode, OutputPort};
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+pub use self::render_to_texture::RenderToTexture;
 
 #[cfg(not(target_os = "android"))]
 mod egl {
     use super::ffi;
     use api::dlloader::{SymTrait, SymWrapper};
 
+    use std::ffi::CString;
+    use std::mem;
+    use std::os::raw::{c_char, c_void};
+
     #[derive(Clone)]
     pub struct Egl(pub SymWrapper<ffi::egl::Egl>);
 
@@ -39,11 +109,28 @@ mod egl {
     unsafe impl Sync for Egl {}
 
     impl SymTrait for ffi::egl::Egl {
-        fn load_with<F>(loadfn: F) -> Self
+        fn load_with<F>(mut loadfn: F) -> Self
         where
             F: FnMut(&'static str) -> *const std::os::raw::c_void,
         {
-            Self::load_with(loadfn)
+            // Extension functions aren't necessarily exported by the library,
+            // so fall back to `eglGetProcAddress` for those.
+            let get_proc_address = loadfn("eglGetProcAddress");
+            Self::load_with(|sym| {
+                let ptr = loadfn(sym);
+                if !ptr.is_null() || get_proc_address.is_null() {
+                    return ptr;
+                }
+
+                let sym = CString::new(sym.as_bytes()).unwrap();
+                unsafe {
+                    let get_proc_address: extern "system" fn(
+                        *const c_char,
+                    )
+                        -> *const c_void = mem::transmute(get_proc_address);
+                    get_proc_address(sym.as_ptr())
+                }
+            })
         }
     }
 
@@ -91,6 +178,612 @@ impl DerefMut for Egl {
 
 lazy_static! {
     pub static ref EGL: Option<Egl> = Egl::new().ok();
+
+    /// The number of `DisplayRef`s of each initialized display.
+    static ref DISPLAY_REFS: Mutex<HashMap<usize, usize>> =
+        Mutex::new(HashMap::new());
+
+    /// The highest desktop OpenGL version found by probing each display
+    /// with some attributes, so that `GlRequest::Latest` doesn't probe again
+    /// for every context.
+    ///
+    /// The entries of a display are removed when it is terminated.
+    static ref LATEST_GL_VERSIONS: Mutex<HashMap<LatestGlKey, (u8, u8)>> =
+        Mutex::new(HashMap::new());
+}
+
+/// The display and attributes the highest OpenGL version a driver gives
+/// depends on.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct LatestGlKey {
+    display: usize,
+    api: Api,
+    profile: Option<GlProfile>,
+    debug: bool,
+    forward_compatible: bool,
+    robustness: Robustness,
+}
+
+/// The desktop OpenGL versions tried for `GlRequest::Latest`, highest first.
+const GL_VERSIONS: &[(u8, u8)] = &[
+    (4, 6),
+    (4, 5),
+    (4, 4),
+    (4, 3),
+    (4, 2),
+    (4, 1),
+    (4, 0),
+    (3, 3),
+    (3, 2),
+    (3, 1),
+    (3, 0),
+];
+
+/// Keeps an `EGLDisplay` initialized.
+///
+/// `eglGetDisplay` returns the same display for the same native display, so
+/// all the contexts created on it share it, possibly from several threads.
+/// The display is only terminated once the last reference is dropped, so
+/// that dropping a context doesn't pull the display from under the others.
+#[derive(Debug)]
+struct DisplayRef(ffi::egl::types::EGLDisplay);
+
+unsafe impl Send for DisplayRef {}
+unsafe impl Sync for DisplayRef {}
+
+impl DisplayRef {
+    /// Initializes `display` and returns a reference to it along with its
+    /// version.
+    unsafe fn initialize(
+        egl: &Egl,
+        display: ffi::egl::types::EGLDisplay,
+    ) -> Result<
+        (DisplayRef, (ffi::egl::types::EGLint, ffi::egl::types::EGLint)),
+        CreationError,
+    > {
+        // the lock is held so that the display can't be terminated by
+        // another thread between the two steps
+        let mut refs = DISPLAY_REFS.lock().unwrap();
+        let mut major = 0;
+        let mut minor = 0;
+        if egl.Initialize(display, &mut major, &mut minor) == 0 {
+            return Err(CreationError::DisplayUnavailable);
+        }
+        *refs.entry(display as usize).or_insert(0) += 1;
+        Ok((DisplayRef(display), (major, minor)))
+    }
+}
+
+impl Drop for DisplayRef {
+    fn drop(&mut self) {
+        let egl = EGL.as_ref().unwrap();
+        let mut refs = DISPLAY_REFS.lock().unwrap();
+        let last = match refs.get_mut(&(self.0 as usize)) {
+            Some(count) => {
+                *count -= 1;
+                *count == 0
+            }
+            None => false,
+        };
+        if last {
+            refs.remove(&(self.0 as usize));
+            LATEST_GL_VERSIONS
+                .lock()
+                .unwrap()
+                .retain(|key, _| key.display != self.0 as usize);
+            unsafe {
+                egl.Terminate(self.0);
+            }
+        }
+    }
+}
+
+/// Returns true if an EGL context is current on the calling thread.
+pub fn has_current_context() -> bool {
+    match *EGL {
+        Some(ref egl) => unsafe {
+            egl.GetCurrentContext() != ffi::egl::NO_CONTEXT
+        },
+        None => false,
+    }
+}
+
+/// Returns true if the given `EGLDisplay` supports
+/// `EGL_KHR_surfaceless_context`, which is needed to make a context current
+/// without any surface.
+///
+/// The display must have been initialized.
+pub unsafe fn supports_surfaceless(
+    display: ffi::egl::types::EGLDisplay,
+) -> bool {
+    let egl = match *EGL {
+        Some(ref egl) => egl,
+        None => return false,
+    };
+
+    let p = egl.QueryString(display, ffi::egl::EXTENSIONS as i32);
+    if p.is_null() {
+        return false;
+    }
+    CStr::from_ptr(p)
+        .to_string_lossy()
+        .split(' ')
+        .any(|e| e == "EGL_KHR_surfaceless_context")
+}
+
+/// Returns the address of the EGL or OpenGL (ES) function `name` through
+/// `eglGetProcAddress`, or null if it isn't available.
+///
+/// Only extension functions can be loaded this way on every implementation,
+/// see `gl_proc_address` for core OpenGL functions.
+unsafe fn proc_address(egl: &Egl, name: &str) -> *const c_void {
+    let name = CString::new(name).unwrap();
+    egl.GetProcAddress(name.as_ptr()) as *const c_void
+}
+
+/// Returns the address of the OpenGL (ES) function `name` for `context`, or
+/// null if it isn't available.
+///
+/// Before EGL 1.5, `eglGetProcAddress` only has to return core functions
+/// with `EGL_KHR_get_all_proc_addresses`, and may return garbage for them
+/// otherwise. In that case they are looked up in the client library of the
+/// API of `context` first.
+unsafe fn gl_proc_address(
+    display: ffi::egl::types::EGLDisplay,
+    context: ffi::egl::types::EGLContext,
+    name: &str,
+) -> *const c_void {
+    let egl = EGL.as_ref().unwrap();
+    if !supports_get_all_proc_addresses(egl, display) {
+        let addr = client_library_proc_address(egl, display, context, name);
+        if !addr.is_null() {
+            return addr;
+        }
+    }
+    proc_address(egl, name)
+}
+
+/// Returns true if `eglGetProcAddress` can load core OpenGL functions on
+/// `display`.
+unsafe fn supports_get_all_proc_addresses(
+    egl: &Egl,
+    display: ffi::egl::types::EGLDisplay,
+) -> bool {
+    #[cfg(not(target_os = "android"))]
+    {
+        if get_client_extensions(egl)
+            .iter()
+            .any(|e| e == "EGL_KHR_client_get_all_proc_addresses")
+        {
+            return true;
+        }
+    }
+
+    let query = |name| {
+        let p = egl.QueryString(display, name as i32);
+        if p.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(p).to_string_lossy().into_owned()
+        }
+    };
+    parse_gl_version(&query(ffi::egl::VERSION)).map_or(false, |v| v >= (1, 5))
+        || query(ffi::egl::EXTENSIONS)
+            .split(' ')
+            .any(|e| e == "EGL_KHR_get_all_proc_addresses")
+}
+
+#[cfg(not(target_os = "android"))]
+lazy_static! {
+    /// The libraries core OpenGL and OpenGL ES functions are loaded from
+    /// when `eglGetProcAddress` can't be used for them.
+    static ref GL_LIBRARY: Option<Library> = open_library(&[
+        #[cfg(target_os = "windows")]
+        "opengl32.dll",
+        #[cfg(not(target_os = "windows"))]
+        "libOpenGL.so.0",
+        #[cfg(not(target_os = "windows"))]
+        "libGL.so.1",
+    ]);
+    static ref GLES_LIBRARY: Option<Library> = open_library(&[
+        #[cfg(target_os = "windows")]
+        "libGLESv2.dll",
+        #[cfg(not(target_os = "windows"))]
+        "libGLESv2.so.2",
+        #[cfg(not(target_os = "windows"))]
+        "libGLESv1_CM.so.1",
+    ]);
+}
+
+#[cfg(not(target_os = "android"))]
+fn open_library(paths: &[&str]) -> Option<Library> {
+    paths.iter().filter_map(|path| Library::new(path).ok()).next()
+}
+
+/// Looks `name` up in the OpenGL or OpenGL ES library, depending on the
+/// API of `context`.
+#[cfg(not(target_os = "android"))]
+unsafe fn client_library_proc_address(
+    egl: &Egl,
+    display: ffi::egl::types::EGLDisplay,
+    context: ffi::egl::types::EGLContext,
+    name: &str,
+) -> *const c_void {
+    let mut client_type = 0;
+    egl.QueryContext(
+        display,
+        context,
+        ffi::egl::CONTEXT_CLIENT_TYPE as i32,
+        &mut client_type,
+    );
+    let library = match client_type as u32 {
+        ffi::egl::OPENGL_API => GL_LIBRARY.as_ref(),
+        ffi::egl::OPENGL_ES_API => GLES_LIBRARY.as_ref(),
+        _ => None,
+    };
+    let name = CString::new(name).unwrap();
+    library
+        .and_then(|library| {
+            library.get::<*const c_void>(name.as_bytes_with_nul()).ok()
+        })
+        .map_or(ptr::null(), |symbol| *symbol)
+}
+
+// the libraries can't be loaded on Android, whose EGL supports
+// `EGL_KHR_get_all_proc_addresses` anyway
+#[cfg(target_os = "android")]
+unsafe fn client_library_proc_address(
+    _egl: &Egl,
+    _display: ffi::egl::types::EGLDisplay,
+    _context: ffi::egl::types::EGLContext,
+    _name: &str,
+) -> *const c_void {
+    ptr::null()
+}
+
+/// Returns true if EGL could be loaded and a display could be initialized on
+/// `native_display`.
+#[cfg(all(
+    feature = "winit",
+    any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    )
+))]
+pub fn is_available(native_display: NativeDisplay) -> bool {
+    let egl = match *EGL {
+        Some(ref egl) => egl,
+        None => return false,
+    };
+
+    let display = get_native_display(egl, native_display);
+    if display.is_null() {
+        return false;
+    }
+
+    // the reference is dropped right away, which terminates the display
+    // again unless something else uses it
+    unsafe { DisplayRef::initialize(egl, display).is_ok() }
+}
+
+/// Creates a `width` by `height` pbuffer with `config`, returning the EGL
+/// error on failure.
+unsafe fn create_pbuffer(
+    display: ffi::egl::types::EGLDisplay,
+    config: ffi::egl::types::EGLConfig,
+    (width, height): (u32, u32),
+) -> Result<ffi::egl::types::EGLSurface, u32> {
+    let egl = EGL.as_ref().unwrap();
+    let attrs = [
+        ffi::egl::WIDTH as c_int,
+        width as c_int,
+        ffi::egl::HEIGHT as c_int,
+        height as c_int,
+        ffi::egl::NONE as c_int,
+    ];
+    let surface = egl.CreatePbufferSurface(display, config, attrs.as_ptr());
+    if surface.is_null() {
+        Err(egl.GetError() as u32)
+    } else {
+        Ok(surface)
+    }
+}
+
+/// Checks that a `width` by `height` pbuffer can be created with `config`,
+/// which EGL would refuse with a generic `EGL_BAD_MATCH` or `EGL_BAD_ALLOC`.
+///
+/// Besides the maximum width and height, the config caps the number of
+/// pixels through `EGL_MAX_PBUFFER_PIXELS`, usually to less than both
+/// maximums multiplied. Returns the error message if the pbuffer is too large.
+unsafe fn check_pbuffer_size(
+    display: ffi::egl::types::EGLDisplay,
+    config: ffi::egl::types::EGLConfig,
+    (width, height): (u32, u32),
+) -> Result<(), String> {
+    let egl = EGL.as_ref().unwrap();
+    let attrib = |attrib: ffi::egl::types::EGLenum| {
+        let mut value = 0;
+        if egl.GetConfigAttrib(
+            display,
+            config,
+            attrib as ffi::egl::types::EGLint,
+            &mut value,
+        ) == 0
+            || value <= 0
+        {
+            // let `eglCreatePbufferSurface` decide
+            None
+        } else {
+            Some(value as u64)
+        }
+    };
+
+    if let (Some(max_width), Some(max_height)) = (
+        attrib(ffi::egl::MAX_PBUFFER_WIDTH),
+        attrib(ffi::egl::MAX_PBUFFER_HEIGHT),
+    ) {
+        if width as u64 > max_width || height as u64 > max_height {
+            return Err(format!(
+                "A {}x{} pbuffer is larger than the maximum of {}x{}",
+                width, height, max_width, max_height
+            ));
+        }
+    }
+    if let Some(max_pixels) = attrib(ffi::egl::MAX_PBUFFER_PIXELS) {
+        let pixels = width as u64 * height as u64;
+        if pixels > max_pixels {
+            return Err(format!(
+                "A {}x{} pbuffer has {} pixels, more than the maximum of {} \
+                 (EGL_MAX_PBUFFER_PIXELS)",
+                width, height, pixels, max_pixels
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Returns the maximum width and height of the pbuffers that can be created
+/// with `config`.
+///
+/// The total number of pixels may be further limited, see
+/// `EGL_MAX_PBUFFER_PIXELS`.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+pub unsafe fn max_pbuffer_size(
+    display: ffi::egl::types::EGLDisplay,
+    config: ffi::egl::types::EGLConfig,
+) -> Option<(u32, u32)> {
+    let egl = match *EGL {
+        Some(ref egl) => egl,
+        None => return None,
+    };
+
+    let mut width = 0;
+    let mut height = 0;
+    if egl.GetConfigAttrib(
+        display,
+        config,
+        ffi::egl::MAX_PBUFFER_WIDTH as ffi::egl::types::EGLint,
+        &mut width,
+    ) == 0
+        || egl.GetConfigAttrib(
+            display,
+            config,
+            ffi::egl::MAX_PBUFFER_HEIGHT as ffi::egl::types::EGLint,
+            &mut height,
+        ) == 0
+    {
+        return None;
+    }
+    Some((width as u32, height as u32))
+}
+
+/// Returns the smallest and largest swap intervals supported by `config`.
+///
+/// Intervals outside of this range are silently clamped by EGL.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+pub unsafe fn swap_interval_range(
+    display: ffi::egl::types::EGLDisplay,
+    config: ffi::egl::types::EGLConfig,
+) -> Option<RangeInclusive<i32>> {
+    let egl = match *EGL {
+        Some(ref egl) => egl,
+        None => return None,
+    };
+
+    let mut min = 0;
+    let mut max = 0;
+    if egl.GetConfigAttrib(
+        display,
+        config,
+        ffi::egl::MIN_SWAP_INTERVAL as ffi::egl::types::EGLint,
+        &mut min,
+    ) == 0
+        || egl.GetConfigAttrib(
+            display,
+            config,
+            ffi::egl::MAX_SWAP_INTERVAL as ffi::egl::types::EGLint,
+            &mut max,
+        ) == 0
+    {
+        return None;
+    }
+    Some(min..=max)
+}
+
+/// Sets the swap interval of the surface that is current on the calling
+/// thread, after checking that `config` supports it.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+pub unsafe fn set_swap_interval(
+    display: ffi::egl::types::EGLDisplay,
+    config: ffi::egl::types::EGLConfig,
+    interval: i32,
+) -> Result<(), ContextError> {
+    let egl = EGL.as_ref().unwrap();
+
+    if let Some(range) = swap_interval_range(display, config) {
+        if !range.contains(&interval) {
+            return Err(ContextError::OsError(format!(
+                "The swap interval {} is outside of the supported range \
+                 {}..={}",
+                interval,
+                range.start(),
+                range.end()
+            )));
+        }
+    }
+
+    if egl.SwapInterval(display, interval) == 0 {
+        return Err(ContextError::OsError(format!(
+            "eglSwapInterval failed: {}",
+            error_name(egl.GetError() as u32)
+        )));
+    }
+    Ok(())
+}
+
+/// Returns the symbolic name of an error returned by `eglGetError`, like
+/// `EGL_BAD_SURFACE`, followed by its code.
+fn error_name(code: u32) -> String {
+    let name = match code {
+        ffi::egl::SUCCESS => "EGL_SUCCESS",
+        ffi::egl::NOT_INITIALIZED => "EGL_NOT_INITIALIZED",
+        ffi::egl::BAD_ACCESS => "EGL_BAD_ACCESS",
+        ffi::egl::BAD_ALLOC => "EGL_BAD_ALLOC",
+        ffi::egl::BAD_ATTRIBUTE => "EGL_BAD_ATTRIBUTE",
+        ffi::egl::BAD_CONFIG => "EGL_BAD_CONFIG",
+        ffi::egl::BAD_CONTEXT => "EGL_BAD_CONTEXT",
+        ffi::egl::BAD_CURRENT_SURFACE => "EGL_BAD_CURRENT_SURFACE",
+        ffi::egl::BAD_DISPLAY => "EGL_BAD_DISPLAY",
+        ffi::egl::BAD_MATCH => "EGL_BAD_MATCH",
+        ffi::egl::BAD_NATIVE_PIXMAP => "EGL_BAD_NATIVE_PIXMAP",
+        ffi::egl::BAD_NATIVE_WINDOW => "EGL_BAD_NATIVE_WINDOW",
+        ffi::egl::BAD_PARAMETER => "EGL_BAD_PARAMETER",
+        ffi::egl::BAD_SURFACE => "EGL_BAD_SURFACE",
+        ffi::egl::CONTEXT_LOST => "EGL_CONTEXT_LOST",
+        _ => return format!("unknown EGL error 0x{:x}", code),
+    };
+    format!("{} (0x{:x})", name, code)
+}
+
+/// Checks that `surface` was created with a config that is compatible with
+/// the one of `context`, so that they can be made current together.
+///
+/// Configs are compatible if they have the same color buffer type and the
+/// same sizes of color, depth and stencil buffers. Contexts created without
+/// a config, through `EGL_KHR_no_config_context`, are compatible with any
+/// surface.
+unsafe fn check_surface_config(
+    display: ffi::egl::types::EGLDisplay,
+    context: ffi::egl::types::EGLContext,
+    surface: ffi::egl::types::EGLSurface,
+) -> Result<(), ContextError> {
+    let egl = EGL.as_ref().unwrap();
+    if surface == ffi::egl::NO_SURFACE {
+        return Ok(());
+    }
+
+    let mut context_id = 0;
+    let mut surface_id = 0;
+    egl.QueryContext(
+        display,
+        context,
+        ffi::egl::CONFIG_ID as i32,
+        &mut context_id,
+    );
+    egl.QuerySurface(
+        display,
+        surface,
+        ffi::egl::CONFIG_ID as i32,
+        &mut surface_id,
+    );
+    // 0 is never a valid ID, and means that the query failed or that the
+    // context has no config
+    if context_id == surface_id || context_id == 0 || surface_id == 0 {
+        return Ok(());
+    }
+
+    let config_by_id = |id| {
+        let attribs = [ffi::egl::CONFIG_ID as i32, id, ffi::egl::NONE as i32];
+        let mut config = ptr::null();
+        let mut num_configs = 0;
+        egl.ChooseConfig(
+            display,
+            attribs.as_ptr(),
+            &mut config,
+            1,
+            &mut num_configs,
+        );
+        if num_configs == 1 {
+            Some(config)
+        } else {
+            None
+        }
+    };
+    let (context_config, surface_config) =
+        match (config_by_id(context_id), config_by_id(surface_id)) {
+            (Some(context_config), Some(surface_config)) => {
+                (context_config, surface_config)
+            }
+            // let `eglMakeCurrent` decide
+            _ => return Ok(()),
+        };
+
+    let attribs = [
+        (ffi::egl::COLOR_BUFFER_TYPE, "EGL_COLOR_BUFFER_TYPE"),
+        (ffi::egl::RED_SIZE, "EGL_RED_SIZE"),
+        (ffi::egl::GREEN_SIZE, "EGL_GREEN_SIZE"),
+        (ffi::egl::BLUE_SIZE, "EGL_BLUE_SIZE"),
+        (ffi::egl::ALPHA_SIZE, "EGL_ALPHA_SIZE"),
+        (ffi::egl::LUMINANCE_SIZE, "EGL_LUMINANCE_SIZE"),
+        (ffi::egl::DEPTH_SIZE, "EGL_DEPTH_SIZE"),
+        (ffi::egl::STENCIL_SIZE, "EGL_STENCIL_SIZE"),
+    ];
+    for &(attrib, name) in attribs.iter() {
+        let mut context_value = 0;
+        let mut surface_value = 0;
+        egl.GetConfigAttrib(
+            display,
+            context_config,
+            attrib as i32,
+            &mut context_value,
+        );
+        egl.GetConfigAttrib(
+            display,
+            surface_config,
+            attrib as i32,
+            &mut surface_value,
+        );
+        if context_value != surface_value {
+            return Err(ContextError::ConfigMismatch(format!(
+                "The surface was created with config {}, which isn't \
+                 compatible with config {} of the context: their {} are {} \
+                 and {}",
+                surface_id, context_id, name, surface_value, context_value
+            )));
+        }
+    }
+    Ok(())
 }
 
 /// Specifies the type of display passed as `native_display`.
@@ -111,14 +804,143 @@ pub enum NativeDisplay {
     Other(Option<ffi::EGLNativeDisplayType>),
 }
 
+/// What's needed to create the `EGLContext` again, see
+/// `Context::reinitialize`.
+struct ContextAttribs {
+    egl_version: (ffi::egl::types::EGLint, ffi::egl::types::EGLint),
+    extensions: Vec<String>,
+    version: (u8, u8),
+    debug: bool,
+    forward_compatible: bool,
+    robustness: Robustness,
+    /// True if a no-error context was required with `with_no_error`.
+    no_error: bool,
+    release_behavior: ReleaseBehavior,
+    /// True if the context shares with a context created outside of glutin,
+    /// whose lifetime isn't tracked by any `ShareGroup`.
+    foreign_share: bool,
+}
+
+/// The contexts created by glutin that share objects with each other.
+///
+/// A context is removed from its group before being destroyed, with the lock
+/// held, so that a context recreated by `Context::reinitialize` can always
+/// share with a live member of its group.
+type ShareGroup = Arc<Mutex<Vec<usize>>>;
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+/// The context and surfaces that are current on a thread with EGL, along
+/// with the API they are current for.
+#[derive(Debug, Clone, Copy)]
+pub struct CurrentBinding {
+    api: ffi::egl::types::EGLenum,
+    display: ffi::egl::types::EGLDisplay,
+    draw: ffi::egl::types::EGLSurface,
+    read: ffi::egl::types::EGLSurface,
+    context: ffi::egl::types::EGLContext,
+}
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+impl CurrentBinding {
+    /// Returns what is current on the calling thread, or `None` if EGL isn't
+    /// available or no context is current.
+    pub fn get() -> Option<CurrentBinding> {
+        let egl = EGL.as_ref()?;
+        unsafe {
+            let context = egl.GetCurrentContext();
+            if context == ffi::egl::NO_CONTEXT {
+                return None;
+            }
+            Some(CurrentBinding {
+                api: egl.QueryAPI(),
+                display: egl.GetCurrentDisplay(),
+                draw: egl.GetCurrentSurface(ffi::egl::DRAW as i32),
+                read: egl.GetCurrentSurface(ffi::egl::READ as i32),
+                context,
+            })
+        }
+    }
+
+    /// Makes the context and surfaces current on the calling thread again.
+    ///
+    /// They must not have been destroyed, and the context must not be
+    /// current on another thread.
+    pub unsafe fn restore(&self) -> Result<(), ContextError> {
+        let egl = EGL.as_ref().unwrap();
+
+        // `eglGetCurrentContext` only returns the context of the bound API
+        if egl.BindAPI(self.api) == 0 {
+            return Err(ContextError::OsError(format!(
+                "eglBindAPI failed: {}",
+                error_name(egl.GetError() as u32)
+            )));
+        }
+
+        if egl.MakeCurrent(self.display, self.draw, self.read, self.context)
+            == 0
+        {
+            return Err(match egl.GetError() as u32 {
+                ffi::egl::CONTEXT_LOST | ffi::egl::BAD_CONTEXT => {
+                    ContextError::ContextLost
+                }
+                ffi::egl::BAD_ALLOC => ContextError::OutOfMemory,
+                err => ContextError::OsError(format!(
+                    "eglMakeCurrent failed: {}",
+                    error_name(err)
+                )),
+            });
+        }
+        Ok(())
+    }
+}
+
 pub struct Context {
     display: ffi::egl::types::EGLDisplay,
     context: ffi::egl::types::EGLContext,
     surface: Cell<ffi::egl::types::EGLSurface>,
     api: Api,
     pixel_format: PixelFormat,
-    #[cfg(target_os = "android")]
     config_id: ffi::egl::types::EGLConfig,
+    flush_on_destroy: bool,
+    /// False if the context was created outside of glutin, in which case it
+    /// must not be destroyed by us.
+    owned: bool,
+    /// True if the context was created with robust buffer access. Always
+    /// false if the context was created outside of glutin.
+    robust: bool,
+    /// `None` if the context was created outside of glutin.
+    attribs: Option<ContextAttribs>,
+    /// `None` if the context was created outside of glutin.
+    share_group: Option<ShareGroup>,
+    /// The requirements and surface type the config was chosen with, so
+    /// that contexts sharing with this one can reuse it. `None` if the
+    /// context was created outside of glutin.
+    config_reqs: Option<(PixelFormatRequirements, ffi::egl::types::EGLenum)>,
+    /// Keeps the display initialized for as long as the context or one of
+    /// the `Display`s of `display` is alive. `None` if the context was
+    /// created outside of glutin.
+    display_ref: Option<Arc<DisplayRef>>,
+    /// The version reported by the driver once the context was created.
+    /// `None` if it couldn't be queried or if the context was created
+    /// outside of glutin.
+    gl_version: Option<(u8, u8)>,
+    /// True if the driver created a lower version than requested.
+    downgraded: bool,
+    /// True if the surface is a pbuffer created by `finish_pbuffer` or
+    /// `resize_pbuffer`, which can be recreated at another size.
+    pbuffer: Cell<bool>,
 }
 
 #[cfg(target_os = "android")]
@@ -130,14 +952,11 @@ fn get_native_display(
     unsafe { egl.GetDisplay(ffi::egl::DEFAULT_DISPLAY as *mut _) }
 }
 
+/// Returns the list of client extensions, which are queried without any
+/// display.
 #[cfg(not(target_os = "android"))]
-fn get_native_display(
-    egl: &Egl,
-    native_display: NativeDisplay,
-) -> *const c_void {
-    // the first step is to query the list of extensions without any display, if
-    // supported
-    let dp_extensions = unsafe {
+fn get_client_extensions(egl: &Egl) -> Vec<String> {
+    unsafe {
         let p =
             egl.QueryString(ffi::egl::NO_DISPLAY, ffi::egl::EXTENSIONS as i32);
 
@@ -152,7 +971,17 @@ fn get_native_display(
                 .unwrap_or_else(|_| format!(""));
             list.split(' ').map(|e| e.to_string()).collect::<Vec<_>>()
         }
-    };
+    }
+}
+
+#[cfg(not(target_os = "android"))]
+fn get_native_display(
+    egl: &Egl,
+    native_display: NativeDisplay,
+) -> *const c_void {
+    // the first step is to query the list of extensions without any display, if
+    // supported
+    let dp_extensions = get_client_extensions(egl);
 
     let has_dp_extension =
         |e: &str| dp_extensions.iter().find(|s| s == &e).is_some();
@@ -305,27 +1134,23 @@ impl Context {
         native_display: NativeDisplay,
     ) -> Result<ContextPrototype<'a>, CreationError> {
         let egl = EGL.as_ref().unwrap();
+
+        // device displays aren't tied to any window system, so only pbuffers
+        // can be created on them
+        let surface_type = match native_display {
+            NativeDisplay::Device(_) => ffi::egl::PBUFFER_BIT,
+            _ => ffi::egl::WINDOW_BIT,
+        };
+
         // calling `eglGetDisplay` or equivalent
         let display = get_native_display(egl, native_display);
 
         if display.is_null() {
-            return Err(CreationError::OsError(
-                "Could not create EGL display object".to_string(),
-            ));
+            return Err(CreationError::DisplayUnavailable);
         }
 
-        let egl_version = unsafe {
-            let mut major: ffi::egl::types::EGLint = mem::uninitialized();
-            let mut minor: ffi::egl::types::EGLint = mem::uninitialized();
-
-            if egl.Initialize(display, &mut major, &mut minor) == 0 {
-                return Err(CreationError::OsError(format!(
-                    "eglInitialize failed"
-                )));
-            }
-
-            (major, minor)
-        };
+        let (display_ref, egl_version) =
+            unsafe { DisplayRef::initialize(egl, display)? };
 
         // the list of extensions supported by the client once initialized is
         // different from the list of extensions obtained earlier
@@ -342,8 +1167,49 @@ impl Context {
             vec![]
         };
 
+        if pf_reqs.release_behavior == ReleaseBehavior::None
+            && extensions
+                .iter()
+                .find(|s| s == &"EGL_KHR_context_flush_control")
+                .is_none()
+        {
+            return Err(CreationError::NotSupported(
+                "EGL_KHR_context_flush_control is not supported",
+            ));
+        }
+
+        // no-error contexts can't be debug or robust contexts, which is only
+        // an error if one was explicitly required
+        let robustness = match opengl.robustness {
+            Robustness::NotRobust | Robustness::NoError if opengl.no_error => {
+                Robustness::NoError
+            }
+            _ if opengl.no_error => {
+                return Err(CreationError::NotSupported(
+                    "No-error contexts can't be robust contexts",
+                ));
+            }
+            Robustness::NoError if opengl.debug => Robustness::NotRobust,
+            robustness => robustness,
+        };
+        if opengl.no_error && opengl.debug {
+            return Err(CreationError::NotSupported(
+                "No-error contexts can't be debug contexts",
+            ));
+        }
+        if opengl.no_error
+            && (!can_request_version(&egl_version, &extensions)
+                || !extensions
+                    .iter()
+                    .any(|s| s == "EGL_KHR_create_context_no_error"))
+        {
+            return Err(CreationError::NotSupported(
+                "EGL_KHR_create_context_no_error is not supported",
+            ));
+        }
+
         // binding the right API and choosing the version
-        let (version, api) = unsafe {
+        let (mut version, mut api) = unsafe {
             match opengl.version {
                 GlRequest::Latest => {
                     if egl_version >= (1, 4) {
@@ -403,97 +1269,1057 @@ impl Context {
             }
         };
 
-        let (config_id, pixel_format) = unsafe {
-            choose_fbconfig(egl, display, &egl_version, api, version, pf_reqs)?
-        };
+        // with `GlThenGles`, OpenGL ES is used if desktop OpenGL turns out
+        // not to work, either here or when creating the context
+        let mut gles_fallback = match opengl.version {
+            GlRequest::GlThenGles {
+                opengles_version, ..
+            } if api == Api::OpenGl => Some(opengles_version),
+            _ => None,
+        };
+
+        // contexts that share with each other must have compatible configs,
+        // so the config of the shared context is reused rather than chosen
+        // again when it was chosen from the same requirements
+        let shared_config = opengl.sharing.and_then(|ctx| {
+            match ctx.config_reqs {
+                Some((ref reqs, ty))
+                    if ctx.display == display
+                        && ctx.api == api
+                        && ty == surface_type
+                        && reqs == pf_reqs =>
+                {
+                    Some((ctx.config_id, ctx.pixel_format.clone()))
+                }
+                _ => None,
+            }
+        });
+
+        let (config_id, pixel_format) = unsafe {
+            let config = match shared_config {
+                Some(config) => Ok(config),
+                None => choose_fbconfig(
+                    egl,
+                    display,
+                    &egl_version,
+                    api,
+                    version,
+                    surface_type,
+                    pf_reqs,
+                ),
+            };
+            match config {
+                Ok(config) => config,
+                Err(_)
+                    if gles_fallback.is_some()
+                        && egl.BindAPI(ffi::egl::OPENGL_ES_API) != 0 =>
+                {
+                    api = Api::OpenGlEs;
+                    version = gles_fallback.take();
+                    choose_fbconfig(
+                        egl,
+                        display,
+                        &egl_version,
+                        api,
+                        version,
+                        surface_type,
+                        pf_reqs,
+                    )?
+                }
+                Err(err) => return Err(err),
+            }
+        };
+
+        Ok(ContextPrototype {
+            opengl: opengl,
+            display: display,
+            display_ref,
+            egl_version: egl_version,
+            extensions: extensions,
+            api: api,
+            version: version,
+            config_id: config_id,
+            pixel_format: pixel_format,
+            release_behavior: pf_reqs.release_behavior,
+            #[cfg(feature = "winit")]
+            double_buffer: pf_reqs.double_buffer,
+            gles_fallback,
+            config_reqs: (pf_reqs.clone(), surface_type),
+            robustness,
+        })
+    }
+
+    /// Wraps an `EGLContext` that was created outside of glutin.
+    ///
+    /// The returned context doesn't own any of the handles: dropping it won't
+    /// destroy the context nor terminate the display. It has no surface until
+    /// the foreign code makes one current with it.
+    pub unsafe fn from_raw(
+        display: ffi::egl::types::EGLDisplay,
+        context: ffi::egl::types::EGLContext,
+        config_id: ffi::egl::types::EGLConfig,
+    ) -> Result<Context, CreationError> {
+        let egl = match *EGL {
+            Some(ref egl) => egl,
+            None => return Err(CreationError::NotSupported("EGL not found")),
+        };
+
+        // the display is already initialized, this only gives us its version
+        let mut major: ffi::egl::types::EGLint = 0;
+        let mut minor: ffi::egl::types::EGLint = 0;
+        if egl.Initialize(display, &mut major, &mut minor) == 0 {
+            return Err(CreationError::DisplayUnavailable);
+        }
+
+        let mut client_type: ffi::egl::types::EGLint = 0;
+        if egl.QueryContext(
+            display,
+            context,
+            ffi::egl::CONTEXT_CLIENT_TYPE as ffi::egl::types::EGLint,
+            &mut client_type,
+        ) == 0
+        {
+            return Err(CreationError::OsError(format!(
+                "eglQueryContext failed"
+            )));
+        }
+        let api = match client_type as ffi::egl::types::EGLenum {
+            ffi::egl::OPENGL_API => Api::OpenGl,
+            ffi::egl::OPENGL_ES_API => Api::OpenGlEs,
+            _ => return Err(CreationError::OpenGlVersionNotSupported),
+        };
+
+        let pixel_format =
+            config_pixel_format(egl, display, &(major, minor), config_id)?;
+
+        registry::register(Backend::Egl);
+        Ok(Context {
+            display,
+            context,
+            surface: Cell::new(ffi::egl::NO_SURFACE),
+            api,
+            pixel_format,
+            config_id,
+            flush_on_destroy: false,
+            owned: false,
+            robust: false,
+            attribs: None,
+            share_group: None,
+            config_reqs: None,
+            display_ref: None,
+            gl_version: None,
+            downgraded: false,
+            pbuffer: Cell::new(false),
+        })
+    }
+
+    pub unsafe fn make_current(&self) -> Result<(), ContextError> {
+        let egl = EGL.as_ref().unwrap();
+        self.bind_api();
+
+        // `eglMakeCurrent` can be expensive, skip it if this exact binding
+        // is already current on this thread
+        if self.is_current()
+            && egl.GetCurrentDisplay() == self.display
+            && egl.GetCurrentSurface(ffi::egl::DRAW as i32)
+                == self.surface.get()
+            && egl.GetCurrentSurface(ffi::egl::READ as i32)
+                == self.surface.get()
+        {
+            return Ok(());
+        }
+
+        if self.surface.get() == ffi::egl::NO_SURFACE
+            && !supports_surfaceless(self.display)
+        {
+            return Err(ContextError::OsError(format!(
+                "This context has no surface and EGL_KHR_surfaceless_context \
+                 is not supported"
+            )));
+        }
+
+        #[cfg(test)]
+        tests::MAKE_CURRENT_CALLS.with(|calls| calls.set(calls.get() + 1));
+        let ret = egl.MakeCurrent(
+            self.display,
+            self.surface.get(),
+            self.surface.get(),
+            self.context,
+        );
+
+        if ret == 0 {
+            match egl.GetError() as u32 {
+                ffi::egl::CONTEXT_LOST | ffi::egl::BAD_CONTEXT => {
+                    Err(ContextError::ContextLost)
+                }
+                ffi::egl::BAD_ALLOC => Err(ContextError::OutOfMemory),
+                ffi::egl::BAD_MATCH => Err(ContextError::ConfigMismatch(
+                    format!("eglMakeCurrent failed with EGL_BAD_MATCH"),
+                )),
+                err => Err(ContextError::OsError(format!(
+                    "eglMakeCurrent failed: {}",
+                    error_name(err)
+                ))),
+            }
+        } else {
+            Ok(())
+        }
+    }
+
+    #[inline]
+    pub fn is_current(&self) -> bool {
+        let egl = EGL.as_ref().unwrap();
+        unsafe { self.with_api(|| egl.GetCurrentContext() == self.context) }
+    }
+
+    /// Binds the API of the context on the calling thread.
+    ///
+    /// The bound API is thread-local state, which selects the context that
+    /// `eglGetCurrentContext` returns and the one that `eglMakeCurrent`
+    /// releases. Desktop OpenGL and OpenGL ES contexts used on the same
+    /// thread would see each other's binding otherwise.
+    unsafe fn bind_api(&self) {
+        let egl = EGL.as_ref().unwrap();
+        if !self.has_bind_api() {
+            return;
+        }
+        let api = self.egl_api();
+        if egl.QueryAPI() != api {
+            #[cfg(test)]
+            tests::BIND_API_CALLS.with(|calls| calls.set(calls.get() + 1));
+            egl.BindAPI(api);
+        }
+    }
+
+    /// Returns the EGL API enum of the context.
+    fn egl_api(&self) -> ffi::egl::types::EGLenum {
+        match self.api {
+            Api::OpenGl => ffi::egl::OPENGL_API,
+            _ => ffi::egl::OPENGL_ES_API,
+        }
+    }
+
+    /// Runs `f` with the API of the context bound, and binds the previous
+    /// API again afterwards, so that querying or releasing the context
+    /// doesn't change the context `eglGetCurrentContext` returns for the
+    /// caller.
+    unsafe fn with_api<T, F: FnOnce() -> T>(&self, f: F) -> T {
+        let egl = EGL.as_ref().unwrap();
+        if !self.has_bind_api() {
+            return f();
+        }
+        let previous = egl.QueryAPI();
+        if previous == self.egl_api() {
+            return f();
+        }
+        self.bind_api();
+        let ret = f();
+        egl.BindAPI(previous);
+        ret
+    }
+
+    fn has_bind_api(&self) -> bool {
+        // EGL 1.0 and 1.1 only support OpenGL ES, and lack `eglBindAPI`.
+        // The version is unknown for contexts created outside of glutin,
+        // which assume at least EGL 1.2.
+        match self.attribs {
+            Some(ref attribs) => attribs.egl_version >= (1, 2),
+            None => true,
+        }
+    }
+
+    /// Releases the context from the calling thread, if it is current.
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    pub unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        let egl = EGL.as_ref().unwrap();
+        if !self.is_current() {
+            return Ok(());
+        }
+
+        // releasing applies to the context of the bound API
+        let ret = self.with_api(|| {
+            egl.MakeCurrent(
+                self.display,
+                ffi::egl::NO_SURFACE,
+                ffi::egl::NO_SURFACE,
+                ffi::egl::NO_CONTEXT,
+            )
+        });
+        if ret == 0 {
+            return Err(ContextError::OsError(format!(
+                "eglMakeCurrent failed: {}",
+                error_name(egl.GetError() as u32)
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn get_proc_address(&self, addr: &str) -> *const () {
+        unsafe { gl_proc_address(self.display, self.context, addr) as *const _ }
+    }
+
+    #[inline]
+    pub fn swap_buffers(&self) -> Result<(), ContextError> {
+        let egl = EGL.as_ref().unwrap();
+        if self.surface.get() == ffi::egl::NO_SURFACE {
+            return Err(ContextError::ContextLost);
+        }
+
+        // swapping has no effect on single-buffered surfaces, we have to
+        // flush ourselves for the rendering to show up
+        if !self.pixel_format.double_buffer {
+            let flush = self.get_proc_address("glFlush");
+            if !flush.is_null() {
+                unsafe {
+                    let flush: extern "system" fn() = mem::transmute(flush);
+                    flush();
+                }
+            }
+            return Ok(());
+        }
+
+        let ret = unsafe { egl.SwapBuffers(self.display, self.surface.get()) };
+
+        if ret == 0 {
+            match unsafe { egl.GetError() } as u32 {
+                ffi::egl::CONTEXT_LOST | ffi::egl::BAD_CONTEXT => {
+                    Err(ContextError::ContextLost)
+                }
+                ffi::egl::BAD_ALLOC => Err(ContextError::OutOfMemory),
+                err => Err(ContextError::OsError(format!(
+                    "eglSwapBuffers failed: {}",
+                    error_name(err)
+                ))),
+            }
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns whether `swap_buffers_with_damage` passes the damage on to
+    /// the compositor, instead of swapping the whole surface.
+    ///
+    /// This is the case if the surface is double-buffered and the display
+    /// supports `EGL_KHR_swap_buffers_with_damage` or
+    /// `EGL_EXT_swap_buffers_with_damage`.
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    pub fn damage_is_effective(&self) -> bool {
+        self.damage_swap().is_some()
+    }
+
+    /// Returns whether the KHR extension is used rather than the EXT one,
+    /// along with the size of the surface, or `None` if damage swaps aren't
+    /// supported for the surface.
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    fn damage_swap(&self) -> Option<(bool, (u32, u32))> {
+        let egl = EGL.as_ref().unwrap();
+        if self.surface.get() == ffi::egl::NO_SURFACE
+            || !self.pixel_format.double_buffer
+        {
+            return None;
+        }
+
+        let khr = egl.SwapBuffersWithDamageKHR.is_loaded()
+            && unsafe {
+                image::has_display_extension(
+                    self.display,
+                    "EGL_KHR_swap_buffers_with_damage",
+                )
+            };
+        let ext = egl.SwapBuffersWithDamageEXT.is_loaded()
+            && unsafe {
+                image::has_display_extension(
+                    self.display,
+                    "EGL_EXT_swap_buffers_with_damage",
+                )
+            };
+        if !(khr || ext) {
+            return None;
+        }
+        self.surface_size().map(|size| (khr, size))
+    }
+
+    /// Swaps the buffers like `swap_buffers`, telling the compositor that
+    /// only `rects` changed since the previous frame, through
+    /// `EGL_KHR_swap_buffers_with_damage` or
+    /// `EGL_EXT_swap_buffers_with_damage`.
+    ///
+    /// The rectangles are clamped to the surface, and the ones left empty
+    /// are dropped. If none is left, the whole surface is damaged, as EGL
+    /// does with an empty list. Without either extension, the damage is
+    /// ignored and the buffers are swapped as usual.
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    pub fn swap_buffers_with_damage(
+        &self,
+        rects: &[Rect],
+    ) -> Result<(), ContextError> {
+        self.swap_with_damage(rects, false)
+    }
+
+    /// Like `swap_buffers_with_damage`, but first merges the rectangles that
+    /// overlap or are adjacent. If more than a few are still left, their
+    /// bounding box is passed instead, as drivers may handle long lists
+    /// poorly.
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    pub fn swap_buffers_with_damage_coalesced(
+        &self,
+        rects: &[Rect],
+    ) -> Result<(), ContextError> {
+        self.swap_with_damage(rects, true)
+    }
+
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    fn swap_with_damage(
+        &self,
+        rects: &[Rect],
+        coalesce: bool,
+    ) -> Result<(), ContextError> {
+        let egl = EGL.as_ref().unwrap();
+        let surface = self.surface.get();
+        if surface == ffi::egl::NO_SURFACE {
+            return Err(ContextError::ContextLost);
+        }
+
+        let (khr, (width, height)) = match self.damage_swap() {
+            Some(damage_swap) => damage_swap,
+            None => return self.swap_buffers(),
+        };
+
+        let mut rects = if coalesce {
+            damage::coalesced_damage_rects(
+                rects,
+                width,
+                height,
+                damage::MAX_DAMAGE_RECTS,
+            )
+        } else {
+            damage::damage_rects(rects, width, height)
+        };
+        let n_rects = (rects.len() / 4) as ffi::egl::types::EGLint;
+        let ret = unsafe {
+            if khr {
+                egl.SwapBuffersWithDamageKHR(
+                    self.display,
+                    surface,
+                    rects.as_mut_ptr(),
+                    n_rects,
+                )
+            } else {
+                egl.SwapBuffersWithDamageEXT(
+                    self.display,
+                    surface,
+                    rects.as_mut_ptr(),
+                    n_rects,
+                )
+            }
+        };
+
+        if ret == 0 {
+            match unsafe { egl.GetError() } as u32 {
+                ffi::egl::CONTEXT_LOST | ffi::egl::BAD_CONTEXT => {
+                    Err(ContextError::ContextLost)
+                }
+                ffi::egl::BAD_ALLOC => Err(ContextError::OutOfMemory),
+                err => Err(ContextError::OsError(format!(
+                    "eglSwapBuffersWithDamage failed: {}",
+                    error_name(err)
+                ))),
+            }
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Destroys the `EGLContext` and creates a new one with the same config,
+    /// attributes and share group. If the old context was current on this
+    /// thread, the new one is made current instead.
+    ///
+    /// The new context is created before the old one is destroyed, so on
+    /// error the context is left untouched. If all the other contexts of the
+    /// share group were destroyed, the new context doesn't share with any.
+    pub fn reinitialize(&mut self) -> Result<(), ContextError> {
+        let egl = EGL.as_ref().unwrap();
+        let (attribs, share_group) = match (&self.attribs, &self.share_group) {
+            (&Some(ref attribs), &Some(ref group)) => (attribs, group),
+            _ => {
+                return Err(ContextError::OsError(
+                    "Cannot reinitialize a context created outside of glutin"
+                        .to_string(),
+                ));
+            }
+        };
+        if attribs.foreign_share {
+            return Err(ContextError::OsError(
+                "Cannot reinitialize a context sharing with a context created \
+                 outside of glutin"
+                    .to_string(),
+            ));
+        }
+
+        // the lock is held until the old context is replaced, so that the
+        // context shared with can't be destroyed in the meantime
+        let mut group = share_group.lock().unwrap();
+        let share = group
+            .iter()
+            .cloned()
+            .find(|&ctx| ctx != self.context as usize)
+            .map_or(ptr::null(), |ctx| ctx as ffi::egl::types::EGLContext);
+
+        unsafe {
+            self.bind_api();
+
+            let (context, robust) = create_context(
+                self.display,
+                &attribs.egl_version,
+                &attribs.extensions,
+                self.api,
+                attribs.version,
+                self.config_id,
+                attribs.debug,
+                attribs.forward_compatible,
+                attribs.robustness,
+                attribs.no_error,
+                attribs.release_behavior,
+                share,
+            )
+            .map_err(|err| match err {
+                CreationError::OutOfMemory => ContextError::OutOfMemory,
+                err => ContextError::OsError(format!("{}", err)),
+            })?;
+
+            let was_current = self.is_current();
+            let old_context = mem::replace(&mut self.context, context);
+            for ctx in group.iter_mut() {
+                if *ctx == old_context as usize {
+                    *ctx = context as usize;
+                }
+            }
+            drop(group);
+            self.robust = robust;
+
+            // the driver may pick another version for the new context
+            self.gl_version =
+                query_gl_version(self.display, self.surface.get(), context);
+            self.downgraded = match self.gl_version {
+                Some(actual) => actual < attribs.version,
+                None => false,
+            };
+
+            let ret = if was_current {
+                self.make_current()
+            } else {
+                Ok(())
+            };
+            egl.DestroyContext(self.display, old_context);
+            ret
+        }
+    }
+
+    #[inline]
+    pub fn get_api(&self) -> Api {
+        self.api
+    }
+
+    /// Returns true if the context was created with robust buffer access,
+    /// which the `TryRobust*` robustnesses don't guarantee.
+    #[inline]
+    pub fn is_robust(&self) -> bool {
+        self.robust
+    }
+
+    #[cfg(feature = "winit")]
+    #[inline]
+    pub fn get_pixel_format(&self) -> PixelFormat {
+        self.pixel_format.clone()
+    }
+
+    /// Returns true if the driver created a lower version than the one that
+    /// was requested, which EGL allows without `EGL_KHR_create_context`
+    /// and for the minor version of OpenGL ES.
+    #[inline]
+    pub fn was_downgraded(&self) -> bool {
+        self.downgraded
+    }
 
-        Ok(ContextPrototype {
-            opengl: opengl,
-            display: display,
-            egl_version: egl_version,
-            extensions: extensions,
-            api: api,
-            version: version,
-            config_id: config_id,
-            pixel_format: pixel_format,
-        })
+    /// Returns the version the context was created with, as reported by the
+    /// driver. It is the highest one the driver supports if
+    /// `GlRequest::Latest` was requested for desktop OpenGL.
+    ///
+    /// If the driver couldn't be asked, this is the version that was
+    /// requested. Returns `None` for contexts created outside of glutin.
+    pub fn get_gl_version(&self) -> Option<(u8, u8)> {
+        if self.gl_version.is_some() {
+            return self.gl_version;
+        }
+        match self.attribs {
+            Some(ref attribs)
+                if can_request_version(
+                    &attribs.egl_version,
+                    &attribs.extensions,
+                ) =>
+            {
+                Some(attribs.version)
+            }
+            _ => None,
+        }
     }
 
-    pub unsafe fn make_current(&self) -> Result<(), ContextError> {
+    /// Returns whether the content of the back buffer is preserved by
+    /// `swap_buffers`, according to the `EGL_SWAP_BEHAVIOR` of the surface.
+    pub fn swap_behavior(&self) -> Result<bool, ContextError> {
         let egl = EGL.as_ref().unwrap();
-        let ret = egl.MakeCurrent(
-            self.display,
-            self.surface.get(),
-            self.surface.get(),
-            self.context,
-        );
+        let surface = self.surface.get();
+        if surface == ffi::egl::NO_SURFACE {
+            return Err(ContextError::OsError(format!(
+                "The context has no surface"
+            )));
+        }
 
-        if ret == 0 {
-            match egl.GetError() as u32 {
-                ffi::egl::CONTEXT_LOST => return Err(ContextError::ContextLost),
-                err => panic!(
-                    "eglMakeCurrent failed (eglGetError returned 0x{:x})",
-                    err
-                ),
+        let mut value = 0;
+        unsafe {
+            if egl.QuerySurface(
+                self.display,
+                surface,
+                ffi::egl::SWAP_BEHAVIOR as ffi::egl::types::EGLint,
+                &mut value,
+            ) == 0
+            {
+                return Err(ContextError::OsError(format!(
+                    "eglQuerySurface failed: {}",
+                    error_name(egl.GetError() as u32)
+                )));
             }
-        } else {
-            Ok(())
         }
+        Ok(value == ffi::egl::BUFFER_PRESERVED as ffi::egl::types::EGLint)
     }
 
-    #[inline]
-    pub fn is_current(&self) -> bool {
+    /// Sets whether the content of the back buffer is preserved by
+    /// `swap_buffers`, through `EGL_SWAP_BEHAVIOR`.
+    ///
+    /// Preserving it requires `EGL_SWAP_BEHAVIOR_PRESERVED_BIT` in the
+    /// `EGL_SURFACE_TYPE` of the config.
+    pub fn set_swap_behavior(
+        &self,
+        preserved: bool,
+    ) -> Result<(), ContextError> {
         let egl = EGL.as_ref().unwrap();
-        unsafe { egl.GetCurrentContext() == self.context }
+        let surface = self.surface.get();
+        if surface == ffi::egl::NO_SURFACE {
+            return Err(ContextError::OsError(format!(
+                "The context has no surface"
+            )));
+        }
+
+        unsafe {
+            if preserved {
+                let mut surface_type = 0;
+                egl.GetConfigAttrib(
+                    self.display,
+                    self.config_id,
+                    ffi::egl::SURFACE_TYPE as ffi::egl::types::EGLint,
+                    &mut surface_type,
+                );
+                let bit = ffi::egl::SWAP_BEHAVIOR_PRESERVED_BIT
+                    as ffi::egl::types::EGLint;
+                if surface_type & bit == 0 {
+                    return Err(ContextError::OsError(format!(
+                        "The config of the context can't preserve the back \
+                         buffer"
+                    )));
+                }
+            }
+
+            let value = if preserved {
+                ffi::egl::BUFFER_PRESERVED
+            } else {
+                ffi::egl::BUFFER_DESTROYED
+            };
+            if egl.SurfaceAttrib(
+                self.display,
+                surface,
+                ffi::egl::SWAP_BEHAVIOR as ffi::egl::types::EGLint,
+                value as ffi::egl::types::EGLint,
+            ) == 0
+            {
+                return Err(ContextError::OsError(format!(
+                    "eglSurfaceAttrib failed: {}",
+                    error_name(egl.GetError() as u32)
+                )));
+            }
+        }
+        Ok(())
     }
 
-    pub fn get_proc_address(&self, addr: &str) -> *const () {
+    /// Returns the size of the current surface, or `None` if there is none.
+    pub fn surface_size(&self) -> Option<(u32, u32)> {
         let egl = EGL.as_ref().unwrap();
-        let addr = CString::new(addr.as_bytes()).unwrap();
-        let addr = addr.as_ptr();
-        unsafe { egl.GetProcAddress(addr) as *const _ }
+        let surface = self.surface.get();
+        if surface == ffi::egl::NO_SURFACE {
+            return None;
+        }
+
+        let mut width = 0;
+        let mut height = 0;
+        unsafe {
+            if egl.QuerySurface(
+                self.display,
+                surface,
+                ffi::egl::WIDTH as ffi::egl::types::EGLint,
+                &mut width,
+            ) == 0
+                || egl.QuerySurface(
+                    self.display,
+                    surface,
+                    ffi::egl::HEIGHT as ffi::egl::types::EGLint,
+                    &mut height,
+                ) == 0
+            {
+                return None;
+            }
+        }
+        Some((width as u32, height as u32))
     }
 
     #[inline]
-    pub fn swap_buffers(&self) -> Result<(), ContextError> {
+    pub unsafe fn raw_handle(&self) -> ffi::egl::types::EGLContext {
+        self.context
+    }
+
+    #[inline]
+    pub unsafe fn get_egl_display(&self) -> ffi::egl::types::EGLDisplay {
+        self.display
+    }
+
+    /// Returns the display of the context, which keeps it initialized even
+    /// once the context is dropped, unless the context was created outside
+    /// of glutin.
+    #[inline]
+    pub fn display(&self) -> Display {
+        Display::with_ref(self.display, self.display_ref.clone())
+    }
+
+    /// Calls `glFinish` on this context, making it current temporarily if
+    /// needed.
+    ///
+    /// The bound API and the contexts current on the calling thread are the
+    /// same afterwards.
+    unsafe fn finish(&self) {
         let egl = EGL.as_ref().unwrap();
-        if self.surface.get() == ffi::egl::NO_SURFACE {
-            return Err(ContextError::ContextLost);
-        }
 
-        let ret = unsafe { egl.SwapBuffers(self.display, self.surface.get()) };
+        // what the caller sees, for the API it has bound
+        let prev = current_binding(egl);
 
-        if ret == 0 {
-            match unsafe { egl.GetError() } as u32 {
-                ffi::egl::CONTEXT_LOST => return Err(ContextError::ContextLost),
-                err => panic!(
-                    "eglSwapBuffers failed (eglGetError returned 0x{:x})",
-                    err
-                ),
+        self.with_api(|| {
+            // what is current for the API of the context, which may differ
+            let (prev_display, prev_draw, prev_read, prev_context) =
+                current_binding(egl);
+            let context = self.context;
+
+            if prev_context != context {
+                let surface = self.surface.get();
+                if egl.MakeCurrent(self.display, surface, surface, context)
+                    == 0
+                {
+                    return;
+                }
             }
-        } else {
-            Ok(())
+
+            let finish = self.get_proc_address("glFinish");
+            if !finish.is_null() {
+                let finish: extern "system" fn() = mem::transmute(finish);
+                finish();
+            }
+
+            if prev_context == context {
+                return;
+            }
+            if prev_context == ffi::egl::NO_CONTEXT {
+                egl.MakeCurrent(
+                    self.display,
+                    ffi::egl::NO_SURFACE,
+                    ffi::egl::NO_SURFACE,
+                    ffi::egl::NO_CONTEXT,
+                );
+            } else {
+                egl.MakeCurrent(
+                    prev_display,
+                    prev_draw,
+                    prev_read,
+                    prev_context,
+                );
+            }
+        });
+
+        // some implementations only have one current context for OpenGL
+        // and OpenGL ES, which making this one current released
+        let (prev_display, prev_draw, prev_read, prev_context) = prev;
+        if prev_context != ffi::egl::NO_CONTEXT
+            && egl.GetCurrentContext() != prev_context
+        {
+            egl.MakeCurrent(prev_display, prev_draw, prev_read, prev_context);
         }
     }
 
     #[inline]
-    pub fn get_api(&self) -> Api {
-        self.api
+    pub unsafe fn get_egl_surface(&self) -> ffi::egl::types::EGLSurface {
+        self.surface.get()
     }
 
     #[inline]
-    pub fn get_pixel_format(&self) -> PixelFormat {
-        self.pixel_format.clone()
+    pub unsafe fn get_egl_config(&self) -> ffi::egl::types::EGLConfig {
+        self.config_id
     }
 
-    #[inline]
-    pub unsafe fn raw_handle(&self) -> ffi::egl::types::EGLContext {
-        self.context
+    /// Creates a window surface for `native_window` and uses it instead of
+    /// the current surface, which is destroyed.
+    ///
+    /// If the context is current on this thread, it is made current again
+    /// with the new surface.
+    pub unsafe fn bind_surface(
+        &mut self,
+        native_window: ffi::EGLNativeWindowType,
+    ) -> Result<(), ContextError> {
+        let egl = EGL.as_ref().unwrap();
+
+        let surface = egl.CreateWindowSurface(
+            self.display,
+            self.config_id,
+            native_window,
+            ptr::null(),
+        );
+        if surface.is_null() {
+            return Err(match egl.GetError() as u32 {
+                ffi::egl::BAD_ALLOC => ContextError::OutOfMemory,
+                err => ContextError::OsError(format!(
+                    "eglCreateWindowSurface failed: {}",
+                    error_name(err)
+                )),
+            });
+        }
+
+        self.replace_surface(surface)
     }
 
-    #[inline]
-    pub unsafe fn get_egl_display(&self) -> ffi::egl::types::EGLDisplay {
-        self.display
+    /// Creates a pbuffer surface backed by `buffer`, a buffer of a client
+    /// API of type `buffer_type` like `EGL_OPENVG_IMAGE`, through
+    /// `eglCreatePbufferFromClientBuffer`, and uses it instead of the current
+    /// surface, which is destroyed.
+    ///
+    /// `size` is passed as `EGL_WIDTH` and `EGL_HEIGHT`, which some buffer
+    /// types require. If the context is current on this thread, it is made
+    /// current again with the new surface.
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    pub unsafe fn bind_client_buffer(
+        &mut self,
+        buffer_type: ffi::egl::types::EGLenum,
+        buffer: ffi::egl::types::EGLClientBuffer,
+        size: Option<(u32, u32)>,
+    ) -> Result<(), ContextError> {
+        let egl = EGL.as_ref().unwrap();
+
+        // the entry point is part of EGL 1.2, and `EGL_OPENVG_IMAGE` also
+        // needs an OpenVG implementation
+        let openvg = || {
+            let apis =
+                egl.QueryString(self.display, ffi::egl::CLIENT_APIS as i32);
+            !apis.is_null()
+                && CStr::from_ptr(apis)
+                    .to_string_lossy()
+                    .split(' ')
+                    .any(|api| api == "OpenVG")
+        };
+        if !egl.CreatePbufferFromClientBuffer.is_loaded()
+            || (buffer_type == ffi::egl::OPENVG_IMAGE && !openvg())
+        {
+            return Err(ContextError::OsError(format!(
+                "Pbuffers can't be created from client buffers of type {:#x}",
+                buffer_type
+            )));
+        }
+
+        let mut attribs = Vec::with_capacity(5);
+        if let Some((width, height)) = size {
+            attribs.push(ffi::egl::WIDTH as c_int);
+            attribs.push(width as c_int);
+            attribs.push(ffi::egl::HEIGHT as c_int);
+            attribs.push(height as c_int);
+        }
+        attribs.push(ffi::egl::NONE as c_int);
+
+        let surface = egl.CreatePbufferFromClientBuffer(
+            self.display,
+            buffer_type,
+            buffer,
+            self.config_id,
+            attribs.as_ptr(),
+        );
+        if surface.is_null() {
+            return Err(match egl.GetError() as u32 {
+                ffi::egl::BAD_ALLOC => ContextError::OutOfMemory,
+                ffi::egl::BAD_PARAMETER => ContextError::OsError(format!(
+                    "Client buffers of type {:#x} are not supported",
+                    buffer_type
+                )),
+                ffi::egl::BAD_MATCH => ContextError::ConfigMismatch(format!(
+                    "The client buffer doesn't match the config of the \
+                     context"
+                )),
+                err => ContextError::OsError(format!(
+                    "eglCreatePbufferFromClientBuffer failed: {}",
+                    error_name(err)
+                )),
+            });
+        }
+
+        self.replace_surface(surface)
+    }
+
+    /// Recreates the pbuffer of the context at `width` by `height`, with
+    /// the same config, since pbuffers can't be resized in place. The
+    /// content of the pbuffer is lost.
+    ///
+    /// If the context is current on this thread, it is made current again
+    /// with the new pbuffer. Returns an error if the surface of the context
+    /// isn't a pbuffer created by `finish_pbuffer`.
+    pub fn resize_pbuffer(
+        &mut self,
+        width: u32,
+        height: u32,
+    ) -> Result<(), ContextError> {
+        if !self.pbuffer.get() {
+            return Err(ContextError::OsError(format!(
+                "Only pbuffer contexts can be resized this way"
+            )));
+        }
+
+        let surface = unsafe {
+            check_pbuffer_size(self.display, self.config_id, (width, height))
+                .map_err(ContextError::OsError)?;
+            match create_pbuffer(self.display, self.config_id, (width, height))
+            {
+                Ok(surface) => surface,
+                Err(ffi::egl::BAD_ALLOC) => {
+                    return Err(ContextError::OutOfMemory)
+                }
+                Err(err) => {
+                    return Err(ContextError::OsError(format!(
+                        "eglCreatePbufferSurface failed: {}",
+                        error_name(err)
+                    )))
+                }
+            }
+        };
+
+        unsafe { self.replace_surface(surface)? };
+        self.pbuffer.set(true);
+        Ok(())
+    }
+
+    /// Uses `surface` instead of the current surface, which is destroyed.
+    ///
+    /// The config of `surface` is checked against the one of the context
+    /// here rather than in `make_current`, since it only changes here. On
+    /// error, `surface` is destroyed and the context is left untouched.
+    unsafe fn replace_surface(
+        &mut self,
+        surface: ffi::egl::types::EGLSurface,
+    ) -> Result<(), ContextError> {
+        let egl = EGL.as_ref().unwrap();
+        if let Err(err) =
+            check_surface_config(self.display, self.context, surface)
+        {
+            egl.DestroySurface(self.display, surface);
+            return Err(err);
+        }
+        let was_current = self.is_current();
+        let old_surface = self.surface.replace(surface);
+        self.pbuffer.set(false);
+        if was_current {
+            self.make_current()?;
+        }
+        if old_surface != ffi::egl::NO_SURFACE {
+            egl.DestroySurface(self.display, old_surface);
+        }
+        Ok(())
+    }
+
+    /// Destroys the surface of the context, which keeps its GL objects.
+    ///
+    /// If the context is current on this thread, it stays current without
+    /// a surface if `EGL_KHR_surfaceless_context` is supported, and is made
+    /// not current otherwise.
+    pub unsafe fn unbind_surface(&mut self) -> Result<(), ContextError> {
+        let egl = EGL.as_ref().unwrap();
+        if self.surface.get() == ffi::egl::NO_SURFACE {
+            return Ok(());
+        }
+
+        if self.is_current() {
+            let context = if supports_surfaceless(self.display) {
+                self.context
+            } else {
+                ffi::egl::NO_CONTEXT
+            };
+            let ret = egl.MakeCurrent(
+                self.display,
+                ffi::egl::NO_SURFACE,
+                ffi::egl::NO_SURFACE,
+                context,
+            );
+            if ret == 0 {
+                return Err(ContextError::OsError(format!(
+                    "eglMakeCurrent failed: {}",
+                    error_name(egl.GetError() as u32)
+                )));
+            }
+        }
+
+        egl.DestroySurface(self.display, self.surface.get());
+        self.surface.set(ffi::egl::NO_SURFACE);
+        self.pbuffer.set(false);
+        Ok(())
     }
 
     // Handle Android Life Cycle.
@@ -551,6 +2377,30 @@ impl Context {
 
         egl.DestroySurface(self.display, self.surface.get());
         self.surface.set(ffi::egl::NO_SURFACE);
+        self.pbuffer.set(false);
+    }
+}
+
+impl fmt::Debug for Context {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let egl = EGL.as_ref().unwrap();
+        let display = unsafe { Display::from_raw(self.display) };
+        let mut config_id = 0;
+        unsafe {
+            egl.GetConfigAttrib(
+                self.display,
+                self.config_id,
+                ffi::egl::CONFIG_ID as i32,
+                &mut config_id,
+            );
+        }
+
+        f.debug_struct("Egl")
+            .field("api", &self.api)
+            .field("vendor", &display.vendor())
+            .field("version", &display.version())
+            .field("config_id", &config_id)
+            .finish()
     }
 }
 
@@ -559,13 +2409,40 @@ unsafe impl Sync for Context {}
 
 impl Drop for Context {
     fn drop(&mut self) {
+        registry::unregister(Backend::Egl);
+
+        if !self.owned {
+            // a surface bound with `bind_surface` is still ours
+            if self.surface.get() != ffi::egl::NO_SURFACE {
+                let egl = EGL.as_ref().unwrap();
+                unsafe {
+                    egl.DestroySurface(self.display, self.surface.get());
+                }
+            }
+            return;
+        }
+
         let egl = EGL.as_ref().unwrap();
         unsafe {
+            if self.flush_on_destroy {
+                self.finish();
+            }
+
+            // the context leaves its group before being destroyed, so that
+            // the others don't share with a dead context
+            let group = self.share_group.as_ref().map(|group| {
+                let mut group = group.lock().unwrap();
+                group.retain(|&ctx| ctx != self.context as usize);
+                group
+            });
+
             // we don't call MakeCurrent(0, 0) because we are not sure that the
             // context is still the current one
             egl.DestroyContext(self.display, self.context);
+            drop(group);
             egl.DestroySurface(self.display, self.surface.get());
-            egl.Terminate(self.display);
+            // the display is terminated by `display_ref`, if nothing else
+            // holds it
         }
     }
 }
@@ -573,16 +2450,30 @@ impl Drop for Context {
 pub struct ContextPrototype<'a> {
     opengl: &'a GlAttributes<&'a Context>,
     display: ffi::egl::types::EGLDisplay,
+    display_ref: DisplayRef,
     egl_version: (ffi::egl::types::EGLint, ffi::egl::types::EGLint),
     extensions: Vec<String>,
     api: Api,
     version: Option<(u8, u8)>,
     config_id: ffi::egl::types::EGLConfig,
     pixel_format: PixelFormat,
+    release_behavior: ReleaseBehavior,
+    #[cfg(feature = "winit")]
+    double_buffer: Option<bool>,
+    /// The OpenGL ES version to fall back to if creating a desktop OpenGL
+    /// context fails.
+    gles_fallback: Option<(u8, u8)>,
+    config_reqs: (PixelFormatRequirements, ffi::egl::types::EGLenum),
+    /// The requested robustness, which is `NoError` if a no-error context
+    /// was requested with `GlAttributes::no_error`.
+    robustness: Robustness,
 }
 
 impl<'a> ContextPrototype<'a> {
-    pub fn get_native_visual_id(&self) -> ffi::egl::types::EGLint {
+    #[cfg(feature = "winit")]
+    pub fn get_native_visual_id(
+        &self,
+    ) -> Result<ffi::egl::types::EGLint, CreationError> {
         let egl = EGL.as_ref().unwrap();
         let mut value = unsafe { mem::uninitialized() };
         let ret = unsafe {
@@ -594,166 +2485,386 @@ impl<'a> ContextPrototype<'a> {
             )
         };
         if ret == 0 {
-            panic!("eglGetConfigAttrib failed")
+            return Err(CreationError::OsError(format!(
+                "eglGetConfigAttrib failed: {}",
+                error_name(unsafe { egl.GetError() } as u32)
+            )));
         };
-        value
+        Ok(value)
     }
 
+    #[cfg(feature = "winit")]
     pub fn finish(
         self,
         native_window: ffi::EGLNativeWindowType,
     ) -> Result<Context, CreationError> {
-        let egl = EGL.as_ref().unwrap();
-        let surface = unsafe {
+        // window surfaces are double-buffered unless told otherwise
+        let attrs = match self.double_buffer {
+            Some(false) => vec![
+                ffi::egl::RENDER_BUFFER as c_int,
+                ffi::egl::SINGLE_BUFFER as c_int,
+                ffi::egl::NONE as c_int,
+            ],
+            _ => vec![ffi::egl::NONE as c_int],
+        };
+        let create_surface = |display, config| unsafe {
+            let egl = EGL.as_ref().unwrap();
             let surface = egl.CreateWindowSurface(
-                self.display,
-                self.config_id,
+                display,
+                config,
                 native_window,
-                ptr::null(),
+                attrs.as_ptr(),
             );
             if surface.is_null() {
-                return Err(CreationError::OsError(format!(
-                    "eglCreateWindowSurface failed"
-                )));
+                return Err(match egl.GetError() as u32 {
+                    ffi::egl::BAD_ALLOC => CreationError::OutOfMemory,
+                    err => CreationError::OsError(format!(
+                        "eglCreateWindowSurface failed: {}",
+                        error_name(err)
+                    )),
+                });
             }
-            surface
+            Ok(surface)
         };
 
-        self.finish_impl(surface)
+        self.finish_impl(create_surface, false)
     }
 
-    #[cfg(any(target_os = "android", target_os = "windows"))]
     pub fn finish_pbuffer(
         self,
         dimensions: (u32, u32),
     ) -> Result<Context, CreationError> {
-        let egl = EGL.as_ref().unwrap();
-        let attrs = &[
-            ffi::egl::WIDTH as c_int,
-            dimensions.0 as c_int,
-            ffi::egl::HEIGHT as c_int,
-            dimensions.1 as c_int,
-            ffi::egl::NONE as c_int,
-        ];
-
-        let surface = unsafe {
-            let surface = egl.CreatePbufferSurface(
-                self.display,
-                self.config_id,
-                attrs.as_ptr(),
-            );
-            if surface.is_null() {
-                return Err(CreationError::OsError(format!(
-                    "eglCreatePbufferSurface failed"
-                )));
-            }
-            surface
+        let create_surface = |display, config| unsafe {
+            check_pbuffer_size(display, config, dimensions)
+                .map_err(CreationError::OsError)?;
+            create_pbuffer(display, config, dimensions).map_err(|err| {
+                match err {
+                    ffi::egl::BAD_ALLOC => CreationError::OutOfMemory,
+                    err => CreationError::OsError(format!(
+                        "eglCreatePbufferSurface failed: {}",
+                        error_name(err)
+                    )),
+                }
+            })
         };
 
-        self.finish_impl(surface)
+        self.finish_impl(create_surface, true)
     }
 
-    fn finish_impl(
-        self,
-        surface: ffi::egl::types::EGLSurface,
-    ) -> Result<Context, CreationError> {
-        let share = match self.opengl.sharing {
-            Some(ctx) => ctx.context,
-            None => ptr::null(),
-        };
-
-        let context = unsafe {
-            if let Some(version) = self.version {
-                create_context(
-                    self.display,
-                    &self.egl_version,
-                    &self.extensions,
-                    self.api,
-                    version,
-                    self.config_id,
-                    self.opengl.debug,
-                    self.opengl.robustness,
-                    share,
-                )?
-            } else if self.api == Api::OpenGlEs {
-                if let Ok(ctx) = create_context(
-                    self.display,
-                    &self.egl_version,
-                    &self.extensions,
-                    self.api,
-                    (2, 0),
-                    self.config_id,
-                    self.opengl.debug,
-                    self.opengl.robustness,
-                    share,
-                ) {
-                    ctx
-                } else if let Ok(ctx) = create_context(
-                    self.display,
-                    &self.egl_version,
-                    &self.extensions,
-                    self.api,
-                    (1, 0),
-                    self.config_id,
-                    self.opengl.debug,
-                    self.opengl.robustness,
-                    share,
-                ) {
-                    ctx
-                } else {
-                    return Err(CreationError::OpenGlVersionNotSupported);
-                }
+    /// Creates a context of `api`, trying a few versions if `version` is
+    /// `None`.
+    unsafe fn create_versioned_context(
+        &self,
+        api: Api,
+        version: Option<(u8, u8)>,
+        share: ffi::egl::types::EGLContext,
+    ) -> Result<((ffi::egl::types::EGLContext, bool), (u8, u8)), CreationError>
+    {
+        if let Some(version) = version {
+            let ctx = create_context(
+                self.display,
+                &self.egl_version,
+                &self.extensions,
+                api,
+                version,
+                self.config_id,
+                self.opengl.debug,
+                self.opengl.forward_compatible,
+                self.robustness,
+                self.opengl.no_error,
+                self.release_behavior,
+                share,
+            )?;
+            Ok((ctx, version))
+        } else if api == Api::OpenGlEs {
+            if let Ok(ctx) = create_context(
+                self.display,
+                &self.egl_version,
+                &self.extensions,
+                api,
+                (2, 0),
+                self.config_id,
+                self.opengl.debug,
+                self.opengl.forward_compatible,
+                self.robustness,
+                self.opengl.no_error,
+                self.release_behavior,
+                share,
+            ) {
+                Ok((ctx, (2, 0)))
+            } else if let Ok(ctx) = create_context(
+                self.display,
+                &self.egl_version,
+                &self.extensions,
+                api,
+                (1, 0),
+                self.config_id,
+                self.opengl.debug,
+                self.opengl.forward_compatible,
+                self.robustness,
+                self.opengl.no_error,
+                self.release_behavior,
+                share,
+            ) {
+                Ok((ctx, (1, 0)))
             } else {
+                Err(CreationError::OpenGlVersionNotSupported)
+            }
+        } else if can_request_version(&self.egl_version, &self.extensions) {
+            // only probe from the version found for the first context of
+            // the display with the same attributes, the driver can't do
+            // better for the next ones
+            let key = LatestGlKey {
+                display: self.display as usize,
+                api,
+                profile: self.opengl.profile,
+                debug: self.opengl.debug,
+                forward_compatible: self.opengl.forward_compatible,
+                robustness: self.robustness,
+            };
+            let latest = LATEST_GL_VERSIONS.lock().unwrap().get(&key).cloned();
+            let versions = GL_VERSIONS
+                .iter()
+                .cloned()
+                .filter(|&version| latest.map_or(true, |l| version <= l))
+                .chain(Some((1, 0)));
+
+            for version in versions {
                 if let Ok(ctx) = create_context(
                     self.display,
                     &self.egl_version,
                     &self.extensions,
-                    self.api,
-                    (3, 2),
-                    self.config_id,
-                    self.opengl.debug,
-                    self.opengl.robustness,
-                    share,
-                ) {
-                    ctx
-                } else if let Ok(ctx) = create_context(
-                    self.display,
-                    &self.egl_version,
-                    &self.extensions,
-                    self.api,
-                    (3, 1),
-                    self.config_id,
-                    self.opengl.debug,
-                    self.opengl.robustness,
-                    share,
-                ) {
-                    ctx
-                } else if let Ok(ctx) = create_context(
-                    self.display,
-                    &self.egl_version,
-                    &self.extensions,
-                    self.api,
-                    (1, 0),
+                    api,
+                    version,
                     self.config_id,
                     self.opengl.debug,
-                    self.opengl.robustness,
+                    self.opengl.forward_compatible,
+                    self.robustness,
+                    self.opengl.no_error,
+                    self.release_behavior,
                     share,
                 ) {
-                    ctx
-                } else {
-                    return Err(CreationError::OpenGlVersionNotSupported);
+                    LATEST_GL_VERSIONS
+                        .lock()
+                        .unwrap()
+                        .entry(key)
+                        .or_insert(version);
+                    return Ok((ctx, version));
                 }
             }
+            Err(CreationError::OpenGlVersionNotSupported)
+        } else {
+            // without `EGL_KHR_create_context` the version can't be asked
+            // for, and the driver picks it
+            let ctx = create_context(
+                self.display,
+                &self.egl_version,
+                &self.extensions,
+                api,
+                (1, 0),
+                self.config_id,
+                self.opengl.debug,
+                self.opengl.forward_compatible,
+                self.robustness,
+                self.opengl.no_error,
+                self.release_behavior,
+                share,
+            )?;
+            Ok((ctx, (1, 0)))
+        }
+    }
+
+    /// Checks if the config of the prototype can be used with the given
+    /// version of OpenGL ES.
+    unsafe fn config_supports_gles(&self, version: (u8, u8)) -> bool {
+        let egl = EGL.as_ref().unwrap();
+        let bit = match version {
+            (3, _) => ffi::egl::OPENGL_ES3_BIT,
+            (2, _) => ffi::egl::OPENGL_ES2_BIT,
+            _ => ffi::egl::OPENGL_ES_BIT,
+        };
+        let mut renderable = 0;
+        egl.GetConfigAttrib(
+            self.display,
+            self.config_id,
+            ffi::egl::RENDERABLE_TYPE as ffi::egl::types::EGLint,
+            &mut renderable,
+        ) != 0
+            && renderable & bit as ffi::egl::types::EGLint != 0
+    }
+
+    /// Chooses a config for OpenGL ES `version` from the same requirements,
+    /// for when the config of the prototype can't be used with it.
+    ///
+    /// A window may already have been created with the native visual of the
+    /// config of the prototype, so the new config must have the same one.
+    unsafe fn choose_gles_config(
+        &self,
+        version: (u8, u8),
+    ) -> Result<(ffi::egl::types::EGLConfig, PixelFormat), CreationError> {
+        let egl = EGL.as_ref().unwrap();
+        let (ref reqs, surface_type) = self.config_reqs;
+        let (config_id, pixel_format) = choose_fbconfig(
+            egl,
+            self.display,
+            &self.egl_version,
+            Api::OpenGlEs,
+            Some(version),
+            surface_type,
+            reqs,
+        )?;
+
+        let visual_id = |config| {
+            let mut value = 0;
+            egl.GetConfigAttrib(
+                self.display,
+                config,
+                ffi::egl::NATIVE_VISUAL_ID as ffi::egl::types::EGLint,
+                &mut value,
+            );
+            value
+        };
+        if surface_type == ffi::egl::WINDOW_BIT
+            && visual_id(config_id) != visual_id(self.config_id)
+        {
+            return Err(CreationError::NoMatchingConfig {
+                requested: reqs.clone(),
+            });
+        }
+        Ok((config_id, pixel_format))
+    }
+
+    /// Creates the context, then its surface with `create_surface`, which is
+    /// given the display and the config, as the config can change when
+    /// falling back to OpenGL ES.
+    fn finish_impl<F>(
+        mut self,
+        create_surface: F,
+        pbuffer: bool,
+    ) -> Result<Context, CreationError>
+    where
+        F: FnOnce(
+            ffi::egl::types::EGLDisplay,
+            ffi::egl::types::EGLConfig,
+        ) -> Result<ffi::egl::types::EGLSurface, CreationError>,
+    {
+        let (share, share_group) = match self.opengl.sharing {
+            Some(ctx) => (ctx.context, ctx.share_group.clone()),
+            None => (ptr::null(), None),
+        };
+        let foreign_share = !share.is_null() && share_group.is_none();
+        let share_group =
+            share_group.unwrap_or_else(|| Arc::new(Mutex::new(Vec::new())));
+
+        let context = unsafe {
+            self.create_versioned_context(self.api, self.version, share)
+        };
+        let (api, ((context, robust), version)) =
+            match (context, self.gles_fallback) {
+                (Ok(context), _) => (self.api, context),
+                (Err(err), Some(version)) => unsafe {
+                    let egl = EGL.as_ref().unwrap();
+                    if egl.BindAPI(ffi::egl::OPENGL_ES_API) == 0 {
+                        return Err(err);
+                    }
+                    // the desktop config may not support OpenGL ES
+                    if !self.config_supports_gles(version) {
+                        match self.choose_gles_config(version) {
+                            Ok((config_id, pixel_format)) => {
+                                self.config_id = config_id;
+                                self.pixel_format = pixel_format;
+                            }
+                            Err(_) => return Err(err),
+                        }
+                    }
+                    let context = self.create_versioned_context(
+                        Api::OpenGlEs,
+                        Some(version),
+                        share,
+                    )?;
+                    (Api::OpenGlEs, context)
+                },
+                (Err(err), None) => return Err(err),
+            };
+
+        let surface = match create_surface(self.display, self.config_id) {
+            Ok(surface) => surface,
+            Err(err) => {
+                let egl = EGL.as_ref().unwrap();
+                unsafe { egl.DestroyContext(self.display, context) };
+                return Err(err);
+            }
+        };
+
+        // the version is only known for sure once the driver is asked
+        let requested = if api == self.api {
+            self.version
+        } else {
+            self.gles_fallback
         };
+        let gl_version =
+            unsafe { query_gl_version(self.display, surface, context) };
+        let downgraded = match (requested, gl_version) {
+            (Some(requested), Some(actual)) => actual < requested,
+            _ => false,
+        };
+        if downgraded && self.opengl.strict_version {
+            let egl = EGL.as_ref().unwrap();
+            unsafe {
+                egl.DestroyContext(self.display, context);
+                if surface != ffi::egl::NO_SURFACE {
+                    egl.DestroySurface(self.display, surface);
+                }
+            }
+            return Err(CreationError::OpenGlVersionNotSupported);
+        }
+
+        // the window system may not honor the requested render buffer
+        let mut pixel_format = self.pixel_format;
+        if surface != ffi::egl::NO_SURFACE {
+            let egl = EGL.as_ref().unwrap();
+            let mut render_buffer = 0;
+            unsafe {
+                egl.QuerySurface(
+                    self.display,
+                    surface,
+                    ffi::egl::RENDER_BUFFER as ffi::egl::types::EGLint,
+                    &mut render_buffer,
+                );
+            }
+            pixel_format.double_buffer =
+                render_buffer != ffi::egl::SINGLE_BUFFER as i32;
+        }
 
+        share_group.lock().unwrap().push(context as usize);
+        registry::register(Backend::Egl);
         Ok(Context {
             display: self.display,
             context: context,
             surface: Cell::new(surface),
-            api: self.api,
-            pixel_format: self.pixel_format,
-            #[cfg(target_os = "android")]
+            api,
+            pixel_format,
             config_id: self.config_id,
+            flush_on_destroy: self.opengl.flush_on_destroy,
+            owned: true,
+            robust,
+            attribs: Some(ContextAttribs {
+                egl_version: self.egl_version,
+                extensions: self.extensions,
+                version,
+                debug: self.opengl.debug,
+                forward_compatible: self.opengl.forward_compatible,
+                robustness: self.robustness,
+                no_error: self.opengl.no_error,
+                release_behavior: self.release_behavior,
+                foreign_share,
+            }),
+            share_group: Some(share_group),
+            config_reqs: Some(self.config_reqs),
+            display_ref: Some(Arc::new(self.display_ref)),
+            gl_version,
+            downgraded,
+            pbuffer: Cell::new(pbuffer),
         })
     }
 }
@@ -764,8 +2875,11 @@ unsafe fn choose_fbconfig(
     egl_version: &(ffi::egl::types::EGLint, ffi::egl::types::EGLint),
     api: Api,
     version: Option<(u8, u8)>,
+    surface_type: ffi::egl::types::EGLenum,
     reqs: &PixelFormatRequirements,
 ) -> Result<(ffi::egl::types::EGLConfig, PixelFormat), CreationError> {
+    let software_driver = is_software_driver(egl, display);
+
     let descriptor = {
         let mut out: Vec<c_int> = Vec::with_capacity(37);
 
@@ -777,7 +2891,7 @@ unsafe fn choose_fbconfig(
         out.push(ffi::egl::SURFACE_TYPE as c_int);
         // TODO: Some versions of Mesa report a BAD_ATTRIBUTE error
         // if we ask for PBUFFER_BIT as well as WINDOW_BIT
-        out.push((ffi::egl::WINDOW_BIT) as c_int);
+        out.push(surface_type as c_int);
 
         match (api, version) {
             (Api::OpenGlEs, Some((3, _))) => {
@@ -806,7 +2920,9 @@ unsafe fn choose_fbconfig(
                     out.push(ffi::egl::OPENGL_ES_BIT as c_int);
                 }
             }
-            (Api::OpenGlEs, _) => unimplemented!(),
+            (Api::OpenGlEs, _) => {
+                return Err(CreationError::OpenGlVersionNotSupported);
+            }
             (Api::OpenGl, _) => {
                 if egl_version < &(1, 3) {
                     return Err(CreationError::NoAvailablePixelFormat);
@@ -816,10 +2932,16 @@ unsafe fn choose_fbconfig(
                 out.push(ffi::egl::CONFORMANT as c_int);
                 out.push(ffi::egl::OPENGL_BIT as c_int);
             }
-            (_, _) => unimplemented!(),
+            (_, _) => return Err(CreationError::OpenGlVersionNotSupported),
         };
 
-        if let Some(hardware_accelerated) = reqs.hardware_accelerated {
+        // with a caveat policy, the configs are filtered after being chosen,
+        // and the caveat is meaningless when the driver is known
+        if let (Some(hardware_accelerated), None, None) = (
+            reqs.hardware_accelerated,
+            reqs.caveat_policy,
+            software_driver,
+        ) {
             out.push(ffi::egl::CONFIG_CAVEAT as c_int);
             out.push(if hardware_accelerated {
                 ffi::egl::NONE as c_int
@@ -828,7 +2950,22 @@ unsafe fn choose_fbconfig(
             });
         }
 
-        if let Some(color) = reqs.color_bits {
+        if let Some(luminance) = reqs.luminance_bits {
+            if egl_version < &(1, 2) {
+                return Err(CreationError::NoAvailablePixelFormat);
+            }
+            out.push(ffi::egl::COLOR_BUFFER_TYPE as c_int);
+            out.push(ffi::egl::LUMINANCE_BUFFER as c_int);
+            out.push(ffi::egl::LUMINANCE_SIZE as c_int);
+            out.push(luminance as c_int);
+        } else if let Some((red, green, blue, _)) = reqs.exact_color_bits {
+            out.push(ffi::egl::RED_SIZE as c_int);
+            out.push(red as c_int);
+            out.push(ffi::egl::GREEN_SIZE as c_int);
+            out.push(green as c_int);
+            out.push(ffi::egl::BLUE_SIZE as c_int);
+            out.push(blue as c_int);
+        } else if let Some(color) = reqs.color_bits {
             out.push(ffi::egl::RED_SIZE as c_int);
             out.push((color / 3) as c_int);
             out.push(ffi::egl::GREEN_SIZE as c_int);
@@ -852,10 +2989,6 @@ unsafe fn choose_fbconfig(
             out.push(stencil as c_int);
         }
 
-        if let Some(true) = reqs.double_buffer {
-            return Err(CreationError::NoAvailablePixelFormat);
-        }
-
         if let Some(multisampling) = reqs.multisampling {
             out.push(ffi::egl::SAMPLES as c_int);
             out.push(multisampling as c_int);
@@ -870,38 +3003,167 @@ unsafe fn choose_fbconfig(
             out.push(xid as c_int);
         }
 
-        // FIXME: srgb is not taken into account
-
-        match reqs.release_behavior {
-            ReleaseBehavior::Flush => (),
-            ReleaseBehavior::None => {
-                // TODO: with EGL you need to manually set the behavior
-                unimplemented!()
+        #[cfg(target_os = "android")]
+        {
+            if reqs.recordable {
+                out.push(ffi::egl::RECORDABLE_ANDROID as c_int);
+                out.push(ffi::egl::TRUE as c_int);
             }
         }
 
+        // FIXME: srgb is not taken into account
+
         out.push(ffi::egl::NONE as c_int);
         out
     };
 
-    // calling `eglChooseConfig`
-    let mut config_id = mem::uninitialized();
-    let mut num_configs = mem::uninitialized();
+    // calling `eglChooseConfig`, the configs are sorted by caveat so the
+    // first acceptable one is the best one
+    let mut num_configs = 0;
+    if egl.ChooseConfig(
+        display,
+        descriptor.as_ptr(),
+        ptr::null_mut(),
+        0,
+        &mut num_configs,
+    ) == 0
+    {
+        return Err(CreationError::OsError(format!("eglChooseConfig failed")));
+    }
+    let mut configs = vec![ptr::null(); num_configs as usize];
     if egl.ChooseConfig(
         display,
         descriptor.as_ptr(),
-        &mut config_id,
-        1,
+        configs.as_mut_ptr(),
+        num_configs,
         &mut num_configs,
     ) == 0
     {
         return Err(CreationError::OsError(format!("eglChooseConfig failed")));
     }
-    if num_configs == 0 {
-        return Err(CreationError::NoAvailablePixelFormat);
+    configs.truncate(num_configs as usize);
+
+    // `eglChooseConfig` treats the color sizes as minimums and puts the
+    // deepest configs first, so exact sizes have to be checked by hand
+    let has_exact_color_bits = |config| match reqs.exact_color_bits {
+        Some((red, green, blue, alpha)) => [
+            (ffi::egl::RED_SIZE, red),
+            (ffi::egl::GREEN_SIZE, green),
+            (ffi::egl::BLUE_SIZE, blue),
+            (ffi::egl::ALPHA_SIZE, alpha),
+        ]
+        .iter()
+        .all(|&(attrib, bits)| {
+            let mut value = 0;
+            egl.GetConfigAttrib(
+                display,
+                config,
+                attrib as ffi::egl::types::EGLint,
+                &mut value,
+            ) != 0
+                && value == bits as ffi::egl::types::EGLint
+        }),
+        None => true,
+    };
+
+    let policy = reqs.caveat_policy.unwrap_or(CaveatPolicy::AcceptAny);
+    let config_id = match configs.into_iter().find(|&config| {
+        policy.accepts(config_caveat(egl, display, config))
+            && has_exact_color_bits(config)
+    }) {
+        Some(config) => config,
+        None => {
+            return Err(CreationError::NoMatchingConfig {
+                requested: reqs.clone(),
+            });
+        }
+    };
+
+    // all the configs of a display come from the same driver
+    if let (Some(hardware_accelerated), Some(software)) =
+        (reqs.hardware_accelerated, software_driver)
+    {
+        if hardware_accelerated == software {
+            return Err(CreationError::NoMatchingConfig {
+                requested: reqs.clone(),
+            });
+        }
+    }
+
+    let desc = config_pixel_format(egl, display, egl_version, config_id)?;
+
+    Ok((config_id, desc))
+}
+
+/// Returns whether the display is driven by one of the software rasterizers
+/// of Mesa, through `EGL_MESA_query_driver`, or `None` if the driver can't be
+/// queried.
+///
+/// Unlike other drivers, those don't mark their configs as slow.
+unsafe fn is_software_driver(
+    egl: &Egl,
+    display: ffi::egl::types::EGLDisplay,
+) -> Option<bool> {
+    let driver = display::driver_name(egl, display)?;
+    Some(match &*driver {
+        "swrast" | "kms_swrast" | "llvmpipe" | "softpipe" | "swr" => true,
+        _ => false,
+    })
+}
+
+/// Returns the `EGL_CONFIG_CAVEAT` of `config_id`.
+unsafe fn config_caveat(
+    egl: &Egl,
+    display: ffi::egl::types::EGLDisplay,
+    config_id: ffi::egl::types::EGLConfig,
+) -> ConfigCaveat {
+    let mut value = 0;
+    egl.GetConfigAttrib(
+        display,
+        config_id,
+        ffi::egl::CONFIG_CAVEAT as ffi::egl::types::EGLint,
+        &mut value,
+    );
+    match value as ffi::egl::types::EGLenum {
+        ffi::egl::SLOW_CONFIG => ConfigCaveat::Slow,
+        ffi::egl::NON_CONFORMANT_CONFIG => ConfigCaveat::NonConformant,
+        _ => ConfigCaveat::None,
     }
+}
+
+/// Returns true if `config` has `EGL_RECORDABLE_ANDROID` set.
+#[cfg(target_os = "android")]
+unsafe fn config_recordable(
+    egl: &Egl,
+    display: ffi::egl::types::EGLDisplay,
+    config: ffi::egl::types::EGLConfig,
+) -> bool {
+    let mut value = 0;
+    egl.GetConfigAttrib(
+        display,
+        config,
+        ffi::egl::RECORDABLE_ANDROID as ffi::egl::types::EGLint,
+        &mut value,
+    ) != 0
+        && value == ffi::egl::TRUE as ffi::egl::types::EGLint
+}
+
+#[cfg(not(target_os = "android"))]
+unsafe fn config_recordable(
+    _egl: &Egl,
+    _display: ffi::egl::types::EGLDisplay,
+    _config: ffi::egl::types::EGLConfig,
+) -> bool {
+    false
+}
 
-    // analyzing each config
+/// Queries the attributes of `config_id` and turns them into a `PixelFormat`.
+unsafe fn config_pixel_format(
+    egl: &Egl,
+    display: ffi::egl::types::EGLDisplay,
+    egl_version: &(ffi::egl::types::EGLint, ffi::egl::types::EGLint),
+    config_id: ffi::egl::types::EGLConfig,
+) -> Result<PixelFormat, CreationError> {
     macro_rules! attrib {
         ($egl:expr, $display:expr, $config:expr, $attr:expr) => {{
             let mut value = mem::uninitialized();
@@ -920,13 +3182,15 @@ unsafe fn choose_fbconfig(
         }};
     };
 
-    let desc = PixelFormat {
-        hardware_accelerated: attrib!(
-            egl,
-            display,
-            config_id,
-            ffi::egl::CONFIG_CAVEAT
-        ) != ffi::egl::SLOW_CONFIG as i32,
+    Ok(PixelFormat {
+        hardware_accelerated: match is_software_driver(egl, display) {
+            Some(software) => !software,
+            None => {
+                attrib!(egl, display, config_id, ffi::egl::CONFIG_CAVEAT)
+                    != ffi::egl::SLOW_CONFIG as i32
+            }
+        },
+        caveat: config_caveat(egl, display, config_id),
         color_bits: attrib!(egl, display, config_id, ffi::egl::RED_SIZE) as u8
             + attrib!(egl, display, config_id, ffi::egl::BLUE_SIZE) as u8
             + attrib!(egl, display, config_id, ffi::egl::GREEN_SIZE) as u8,
@@ -944,9 +3208,135 @@ unsafe fn choose_fbconfig(
             a => Some(a as u16),
         },
         srgb: false, // TODO: use EGL_KHR_gl_colorspace to know that
+        luminance_bits: if egl_version >= &(1, 2)
+            && attrib!(egl, display, config_id, ffi::egl::COLOR_BUFFER_TYPE)
+                == ffi::egl::LUMINANCE_BUFFER as i32
+        {
+            attrib!(egl, display, config_id, ffi::egl::LUMINANCE_SIZE) as u8
+        } else {
+            0
+        },
+        accum_bits: 0, // EGL has no accumulation buffers
+        native_visual_id: match attrib!(
+            egl,
+            display,
+            config_id,
+            ffi::egl::NATIVE_VISUAL_ID
+        ) {
+            0 => None,
+            id => Some(id as u32),
+        },
+        recordable: config_recordable(egl, display, config_id),
+    })
+}
+
+/// Returns the version of the API of `context`, through `GL_MAJOR_VERSION`
+/// and `GL_MINOR_VERSION`, or by parsing `GL_VERSION` before OpenGL (ES) 3.0.
+///
+/// The context is made current with `surface` for the query, after which
+/// what was current before is made current again.
+unsafe fn query_gl_version(
+    display: ffi::egl::types::EGLDisplay,
+    surface: ffi::egl::types::EGLSurface,
+    context: ffi::egl::types::EGLContext,
+) -> Option<(u8, u8)> {
+    const GL_VERSION: u32 = 0x1F02;
+    const GL_MAJOR_VERSION: u32 = 0x821B;
+    const GL_MINOR_VERSION: u32 = 0x821C;
+
+    let egl = EGL.as_ref().unwrap();
+    let load = |name| gl_proc_address(display, context, name) as *const ();
+    let get_integerv: extern "system" fn(u32, *mut i32) =
+        load_gl("glGetIntegerv", load("glGetIntegerv")).ok()?;
+    let get_string: extern "system" fn(u32) -> *const c_char =
+        load_gl("glGetString", load("glGetString")).ok()?;
+
+    let previous_display = egl.GetCurrentDisplay();
+    let previous_draw = egl.GetCurrentSurface(ffi::egl::DRAW as i32);
+    let previous_read = egl.GetCurrentSurface(ffi::egl::READ as i32);
+    let previous_context = egl.GetCurrentContext();
+    if egl.MakeCurrent(display, surface, surface, context) == 0 {
+        return None;
+    }
+
+    // the enums are unknown before 3.0, in which case they are left as is
+    let mut major = -1;
+    let mut minor = -1;
+    get_integerv(GL_MAJOR_VERSION, &mut major);
+    get_integerv(GL_MINOR_VERSION, &mut minor);
+    let version = if major >= 0 && minor >= 0 {
+        Some((major as u8, minor as u8))
+    } else {
+        // like `OpenGL ES 2.0 Mesa 19.0.0` or `2.1 Mesa 19.0.0`
+        let version = get_string(GL_VERSION);
+        if version.is_null() {
+            None
+        } else {
+            parse_gl_version(&CStr::from_ptr(version).to_string_lossy())
+        }
     };
 
-    Ok((config_id, desc))
+    if previous_display.is_null() {
+        egl.MakeCurrent(
+            display,
+            ffi::egl::NO_SURFACE,
+            ffi::egl::NO_SURFACE,
+            ffi::egl::NO_CONTEXT,
+        );
+    } else {
+        egl.MakeCurrent(
+            previous_display,
+            previous_draw,
+            previous_read,
+            previous_context,
+        );
+    }
+    version
+}
+
+/// Returns the display, draw and read surfaces and context that are current
+/// on the calling thread for the bound API.
+unsafe fn current_binding(
+    egl: &Egl,
+) -> (
+    ffi::egl::types::EGLDisplay,
+    ffi::egl::types::EGLSurface,
+    ffi::egl::types::EGLSurface,
+    ffi::egl::types::EGLContext,
+) {
+    (
+        egl.GetCurrentDisplay(),
+        egl.GetCurrentSurface(ffi::egl::DRAW as i32),
+        egl.GetCurrentSurface(ffi::egl::READ as i32),
+        egl.GetCurrentContext(),
+    )
+}
+
+/// Parses the first `major.minor` of a `GL_VERSION` string.
+fn parse_gl_version(version: &str) -> Option<(u8, u8)> {
+    let number = version
+        .split_whitespace()
+        .find(|s| s.starts_with(|c: char| c.is_ascii_digit()))?;
+    let mut parts = number.split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts
+        .next()?
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect::<String>()
+        .parse()
+        .ok()?;
+    Some((major, minor))
+}
+
+/// Returns whether the version of a context can be chosen, through EGL 1.5
+/// or `EGL_KHR_create_context`.
+fn can_request_version(
+    egl_version: &(ffi::egl::types::EGLint, ffi::egl::types::EGLint),
+    extensions: &[String],
+) -> bool {
+    egl_version >= &(1, 5)
+        || extensions.iter().any(|s| s == "EGL_KHR_create_context")
 }
 
 unsafe fn create_context(
@@ -957,20 +3347,20 @@ unsafe fn create_context(
     version: (u8, u8),
     config_id: ffi::egl::types::EGLConfig,
     gl_debug: bool,
+    forward_compatible: bool,
     gl_robustness: Robustness,
+    require_no_error: bool,
+    release_behavior: ReleaseBehavior,
     share: ffi::EGLContext,
-) -> Result<ffi::egl::types::EGLContext, CreationError> {
+) -> Result<(ffi::egl::types::EGLContext, bool), CreationError> {
     let egl = EGL.as_ref().unwrap();
 
     let mut context_attributes = Vec::with_capacity(10);
     let mut flags = 0;
+    let mut no_error = false;
+    let mut robust = false;
 
-    if egl_version >= &(1, 5)
-        || extensions
-            .iter()
-            .find(|s| s == &"EGL_KHR_create_context")
-            .is_some()
-    {
+    if can_request_version(egl_version, extensions) {
         context_attributes.push(ffi::egl::CONTEXT_MAJOR_VERSION as i32);
         context_attributes.push(version.0 as i32);
         context_attributes.push(ffi::egl::CONTEXT_MINOR_VERSION as i32);
@@ -986,6 +3376,8 @@ unsafe fn create_context(
         match gl_robustness {
             Robustness::NotRobust => (),
 
+            // falls back to `NotRobust` when the extension is missing, and
+            // `Context::new` already dropped it for debug contexts
             Robustness::NoError => {
                 if extensions
                     .iter()
@@ -995,6 +3387,7 @@ unsafe fn create_context(
                     context_attributes
                         .push(ffi::egl::CONTEXT_OPENGL_NO_ERROR_KHR as c_int);
                     context_attributes.push(1);
+                    no_error = true;
                 }
             }
 
@@ -1008,6 +3401,7 @@ unsafe fn create_context(
                         .push(ffi::egl::NO_RESET_NOTIFICATION as c_int);
                     flags =
                         flags | ffi::egl::CONTEXT_OPENGL_ROBUST_ACCESS as c_int;
+                    robust = true;
                 } else {
                     return Err(CreationError::RobustnessNotSupported);
                 }
@@ -1023,6 +3417,7 @@ unsafe fn create_context(
                         .push(ffi::egl::NO_RESET_NOTIFICATION as c_int);
                     flags =
                         flags | ffi::egl::CONTEXT_OPENGL_ROBUST_ACCESS as c_int;
+                    robust = true;
                 }
             }
 
@@ -1036,6 +3431,7 @@ unsafe fn create_context(
                         .push(ffi::egl::LOSE_CONTEXT_ON_RESET as c_int);
                     flags =
                         flags | ffi::egl::CONTEXT_OPENGL_ROBUST_ACCESS as c_int;
+                    robust = true;
                 } else {
                     return Err(CreationError::RobustnessNotSupported);
                 }
@@ -1051,6 +3447,7 @@ unsafe fn create_context(
                         .push(ffi::egl::LOSE_CONTEXT_ON_RESET as c_int);
                     flags =
                         flags | ffi::egl::CONTEXT_OPENGL_ROBUST_ACCESS as c_int;
+                    robust = true;
                 }
             }
         }
@@ -1069,6 +3466,19 @@ unsafe fn create_context(
             // ffi::egl::CONTEXT_OPENGL_DEBUG_BIT_KHR as i32;
         }
 
+        // only desktop OpenGL 3.0+ has a notion of forward compatibility
+        if forward_compatible && api == Api::OpenGl && version >= (3, 0) {
+            if egl_version >= &(1, 5) {
+                context_attributes
+                    .push(ffi::egl::CONTEXT_OPENGL_FORWARD_COMPATIBLE as c_int);
+                context_attributes.push(ffi::egl::TRUE as c_int);
+            } else {
+                flags = flags
+                    | ffi::egl::CONTEXT_OPENGL_FORWARD_COMPATIBLE_BIT_KHR
+                        as c_int;
+            }
+        }
+
         // In at least some configurations, the Android emulator’s GL
         // implementation advertises support for the
         // EGL_KHR_create_context extension but returns BAD_ATTRIBUTE
@@ -1091,6 +3501,25 @@ unsafe fn create_context(
         context_attributes.push(version.0 as i32);
     }
 
+    match release_behavior {
+        ReleaseBehavior::Flush => (),
+        ReleaseBehavior::None => {
+            if extensions
+                .iter()
+                .find(|s| s == &"EGL_KHR_context_flush_control")
+                .is_none()
+            {
+                return Err(CreationError::NotSupported(
+                    "EGL_KHR_context_flush_control is not supported",
+                ));
+            }
+            context_attributes
+                .push(ffi::egl::CONTEXT_RELEASE_BEHAVIOR_KHR as c_int);
+            context_attributes
+                .push(ffi::egl::CONTEXT_RELEASE_BEHAVIOR_NONE_KHR as c_int);
+        }
+    }
+
     context_attributes.push(ffi::egl::NONE as i32);
 
     let context = egl.CreateContext(
@@ -1102,12 +3531,327 @@ unsafe fn create_context(
 
     if context.is_null() {
         match egl.GetError() as u32 {
+            // some drivers advertise the extensions but reject the
+            // attributes, in which case optional ones are dropped
+            ffi::egl::BAD_MATCH | ffi::egl::BAD_ATTRIBUTE
+                if no_error && require_no_error =>
+            {
+                return Err(CreationError::NotSupported(
+                    "The driver rejected EGL_CONTEXT_OPENGL_NO_ERROR_KHR",
+                ));
+            }
+            ffi::egl::BAD_MATCH | ffi::egl::BAD_ATTRIBUTE
+                if no_error || (robust && gl_robustness.is_optional()) =>
+            {
+                return create_context(
+                    display,
+                    egl_version,
+                    extensions,
+                    api,
+                    version,
+                    config_id,
+                    gl_debug,
+                    forward_compatible,
+                    Robustness::NotRobust,
+                    false,
+                    release_behavior,
+                    share,
+                );
+            }
             ffi::egl::BAD_MATCH | ffi::egl::BAD_ATTRIBUTE => {
                 return Err(CreationError::OpenGlVersionNotSupported);
             }
-            e => panic!("eglCreateContext failed: 0x{:x}", e),
+            ffi::egl::BAD_ALLOC => return Err(CreationError::OutOfMemory),
+            ffi::egl::BAD_CONTEXT => {
+                return Err(CreationError::OsError(format!(
+                    "eglCreateContext failed: the context to share with is \
+                     not a valid context"
+                )));
+            }
+            e => {
+                return Err(CreationError::OsError(format!(
+                    "eglCreateContext failed: {}",
+                    error_name(e)
+                )));
+            }
+        }
+    }
+
+    Ok((context, robust))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::sync::Mutex;
+
+    thread_local! {
+        /// The number of `eglMakeCurrent` calls made by
+        /// `Context::make_current` on this thread.
+        pub static MAKE_CURRENT_CALLS: Cell<usize> = Cell::new(0);
+        /// The number of `eglBindAPI` calls made to bind the API of a
+        /// context on this thread.
+        pub static BIND_API_CALLS: Cell<usize> = Cell::new(0);
+    }
+
+    lazy_static! {
+        /// Held by the tests using the EGL device, which all share the same
+        /// display.
+        static ref DEVICE_LOCK: Mutex<()> = Mutex::new(());
+    }
+
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    #[test]
+    fn make_current_twice_calls_egl_once() {
+        use super::{Context, Device, NativeDisplay};
+        use {GlAttributes, PixelFormatRequirements};
+
+        let _lock = DEVICE_LOCK.lock().unwrap();
+        let device = match Device::query_all().into_iter().next() {
+            Some(device) => device,
+            None => return,
+        };
+        let mut pf_reqs = PixelFormatRequirements::default();
+        pf_reqs.hardware_accelerated = None;
+        let gl_attr: GlAttributes<&Context> = GlAttributes::default();
+        let native_display = NativeDisplay::Device(device.raw_device());
+        let context = Context::new(&pf_reqs, &gl_attr, native_display)
+            .and_then(|p| p.finish_pbuffer((1, 1)))
+            .unwrap();
+
+        let calls = || MAKE_CURRENT_CALLS.with(|calls| calls.get());
+        let bind_api_calls = || BIND_API_CALLS.with(|calls| calls.get());
+        let before = calls();
+        unsafe {
+            context.make_current().unwrap();
+            let binds = bind_api_calls();
+            context.make_current().unwrap();
+            context.make_current().unwrap();
+            // the API stays bound on the thread
+            assert_eq!(bind_api_calls(), binds);
+        }
+        assert_eq!(calls() - before, 1);
+        assert!(context.is_current());
+    }
+
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    #[test]
+    fn pbuffer_size_checks_the_pixel_cap() {
+        use super::{
+            check_pbuffer_size, ffi, max_pbuffer_size, Context, Device,
+            NativeDisplay, EGL,
+        };
+        use {GlAttributes, PixelFormatRequirements};
+
+        let _lock = DEVICE_LOCK.lock().unwrap();
+        let device = match Device::query_all().into_iter().next() {
+            Some(device) => device,
+            None => return,
+        };
+        let mut pf_reqs = PixelFormatRequirements::default();
+        pf_reqs.hardware_accelerated = None;
+        let gl_attr: GlAttributes<&Context> = GlAttributes::default();
+        let native_display = NativeDisplay::Device(device.raw_device());
+        let context = Context::new(&pf_reqs, &gl_attr, native_display)
+            .and_then(|p| p.finish_pbuffer((1, 1)))
+            .unwrap();
+
+        let (display, config) = (context.display, context.config_id);
+        let (width, height) =
+            unsafe { max_pbuffer_size(display, config).unwrap() };
+        let mut max_pixels = 0;
+        unsafe {
+            EGL.as_ref().unwrap().GetConfigAttrib(
+                display,
+                config,
+                ffi::egl::MAX_PBUFFER_PIXELS as i32,
+                &mut max_pixels,
+            );
+        }
+
+        let check = |size| unsafe { check_pbuffer_size(display, config, size) };
+        assert!(check((width + 1, 1)).unwrap_err().contains("maximum"));
+        if max_pixels > 0 && (max_pixels as u64) < width as u64 * height as u64
+        {
+            let err = check((width, height)).unwrap_err();
+            assert!(err.contains("EGL_MAX_PBUFFER_PIXELS"), "{}", err);
+        }
+        assert!(check((1, 1)).is_ok());
+    }
+
+    #[cfg(all(
+        feature = "winit",
+        any(
+            target_os = "linux",
+            target_os = "dragonfly",
+            target_os = "freebsd",
+            target_os = "netbsd",
+            target_os = "openbsd"
+        )
+    ))]
+    #[test]
+    fn is_available_releases_the_display() {
+        use super::{
+            ffi, get_native_display, is_available, Device, NativeDisplay,
+            DISPLAY_REFS, EGL,
+        };
+
+        let _lock = DEVICE_LOCK.lock().unwrap();
+        let device = match Device::query_all().into_iter().next() {
+            Some(device) => device,
+            None => return,
+        };
+        let egl = EGL.as_ref().unwrap();
+        let native_display = || NativeDisplay::Device(device.raw_device());
+        let display = get_native_display(egl, native_display());
+
+        assert!(is_available(native_display()));
+        let refs = DISPLAY_REFS.lock().unwrap();
+        assert!(!refs.contains_key(&(display as usize)));
+
+        // the display was terminated again
+        unsafe {
+            let version = egl.QueryString(display, ffi::egl::VERSION as i32);
+            assert!(version.is_null());
+            assert_eq!(egl.GetError() as u32, ffi::egl::NOT_INITIALIZED);
+        }
+    }
+
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    #[test]
+    fn latest_gl_versions_are_cached_per_attributes() {
+        use super::{Context, Device, NativeDisplay, LATEST_GL_VERSIONS};
+        use {GlAttributes, PixelFormatRequirements};
+
+        let _lock = DEVICE_LOCK.lock().unwrap();
+        let device = match Device::query_all().into_iter().next() {
+            Some(device) => device,
+            None => return,
+        };
+        let mut pf_reqs = PixelFormatRequirements::default();
+        pf_reqs.hardware_accelerated = None;
+        let new_context = |debug| {
+            let mut gl_attr: GlAttributes<&Context> = GlAttributes::default();
+            gl_attr.debug = debug;
+            let native_display = NativeDisplay::Device(device.raw_device());
+            Context::new(&pf_reqs, &gl_attr, native_display)
+                .and_then(|p| p.finish_pbuffer((1, 1)))
+                .unwrap()
+        };
+        let entries = |display| {
+            let versions = LATEST_GL_VERSIONS.lock().unwrap();
+            versions.keys().filter(|key| key.display == display).count()
+        };
+
+        let context = new_context(false);
+        let display = unsafe { context.get_egl_display() } as usize;
+        assert_eq!(entries(display), 1);
+        let debug_context = new_context(true);
+        assert_eq!(entries(display), 2);
+
+        // the display is terminated along with the last context
+        drop(context);
+        drop(debug_context);
+        assert_eq!(entries(display), 0);
+    }
+
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    #[test]
+    fn core_functions_from_the_client_library() {
+        use super::{
+            client_library_proc_address, gl_proc_address, Context, Device,
+            NativeDisplay, EGL,
+        };
+        use std::ffi::CStr;
+        use std::mem;
+        use std::os::raw::c_char;
+        use {GlAttributes, PixelFormatRequirements};
+
+        const GL_VERSION: u32 = 0x1F02;
+
+        let _lock = DEVICE_LOCK.lock().unwrap();
+        let device = match Device::query_all().into_iter().next() {
+            Some(device) => device,
+            None => return,
+        };
+        let mut pf_reqs = PixelFormatRequirements::default();
+        pf_reqs.hardware_accelerated = None;
+        let gl_attr: GlAttributes<&Context> = GlAttributes::default();
+        let native_display = NativeDisplay::Device(device.raw_device());
+        let context = Context::new(&pf_reqs, &gl_attr, native_display)
+            .and_then(|p| p.finish_pbuffer((1, 1)))
+            .unwrap();
+        unsafe { context.make_current().unwrap() };
+
+        let egl = EGL.as_ref().unwrap();
+        let version = |addr| unsafe {
+            let get_string: extern "system" fn(u32) -> *const c_char =
+                mem::transmute(addr);
+            CStr::from_ptr(get_string(GL_VERSION)).to_owned()
+        };
+        let from_library = unsafe {
+            client_library_proc_address(
+                egl,
+                context.display,
+                context.context,
+                "glGetString",
+            )
+        };
+        if from_library.is_null() {
+            eprintln!("the OpenGL library couldn't be loaded, skipping");
+            return;
         }
+        let from_egl = unsafe {
+            gl_proc_address(context.display, context.context, "glGetString")
+        };
+        assert_eq!(version(from_library), version(from_egl));
+    }
+
+    #[test]
+    fn parse_gl_version() {
+        use super::parse_gl_version as parse;
+
+        assert_eq!(parse("4.6 (Core Profile) Mesa 19.0.0"), Some((4, 6)));
+        assert_eq!(parse("4.6.0 NVIDIA 418.56"), Some((4, 6)));
+        assert_eq!(parse("2.1 Mesa 19.0.0"), Some((2, 1)));
+        assert_eq!(parse("OpenGL ES 3.2 Mesa 19.0.0"), Some((3, 2)));
+        assert_eq!(parse("OpenGL ES 3.0 V@331.0 (GIT@35e467f)"), Some((3, 0)));
+        assert_eq!(parse("OpenGL ES-CM 1.1 Mesa 19.0.0"), Some((1, 1)));
     }
 
-    Ok(context)
+    #[test]
+    fn parse_malformed_gl_version() {
+        use super::parse_gl_version as parse;
+
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("OpenGL ES"), None);
+        assert_eq!(parse("4"), None);
+        assert_eq!(parse("4. Mesa"), None);
+        assert_eq!(parse("4.x"), None);
+        assert_eq!(parse("300.0"), None);
+    }
 }
@@ -0,0 +1,296 @@
+#![cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+
+use super::{device, ffi, EGL};
+
+use libc;
+
+#[cfg(target_os = "linux")]
+use std::fs::OpenOptions;
+#[cfg(target_os = "linux")]
+use std::mem;
+#[cfg(target_os = "linux")]
+use std::os::unix::io::AsRawFd;
+use std::ptr;
+
+/// A layer of a display that images can be presented to, as exposed by
+/// `EGL_EXT_output_base`.
+///
+/// With `EGL_EXT_output_drm`, each layer is a CRTC or a plane of DRM.
+#[derive(Clone, Debug)]
+pub struct OutputLayer {
+    display: ffi::egl::types::EGLDisplay,
+    layer: ffi::egl::types::EGLOutputLayerEXT,
+}
+
+/// A port of a display that a screen can be plugged into, as exposed by
+/// `EGL_EXT_output_base`.
+///
+/// With `EGL_EXT_output_drm`, each port is a connector of DRM.
+#[derive(Clone, Debug)]
+pub struct OutputPort {
+    display: ffi::egl::types::EGLDisplay,
+    port: ffi::egl::types::EGLOutputPortEXT,
+}
+
+unsafe impl Send for OutputLayer {}
+unsafe impl Sync for OutputLayer {}
+unsafe impl Send for OutputPort {}
+unsafe impl Sync for OutputPort {}
+
+/// The mode a CRTC is scanning out, which is the size a fullscreen
+/// framebuffer should have.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OutputMode {
+    pub width: u32,
+    pub height: u32,
+    /// The refresh rate, in Hz.
+    pub refresh_rate: u32,
+}
+
+impl OutputLayer {
+    /// Returns the raw `EGLOutputLayerEXT` handle.
+    #[inline]
+    pub fn raw_layer(&self) -> ffi::egl::types::EGLOutputLayerEXT {
+        self.layer
+    }
+
+    /// Returns the ID of the DRM CRTC of this layer, or `None` if it isn't a
+    /// CRTC.
+    pub fn drm_crtc(&self) -> Option<u32> {
+        self.query(ffi::egl::DRM_CRTC_EXT)
+    }
+
+    /// Returns the ID of the DRM plane of this layer, or `None` if it isn't
+    /// a plane.
+    pub fn drm_plane(&self) -> Option<u32> {
+        self.query(ffi::egl::DRM_PLANE_EXT)
+    }
+
+    /// Returns the mode the CRTC of this layer is currently scanning out.
+    ///
+    /// The mode is read from the DRM device file of the device of the
+    /// display, which doesn't require being the DRM master. Returns `None`
+    /// if the layer isn't a CRTC, if the display wasn't created from a
+    /// device, or if the CRTC isn't scanning out. Only supported on Linux.
+    pub fn current_mode(&self) -> Option<OutputMode> {
+        let crtc = self.drm_crtc()?;
+        let file = unsafe { device::display_device(self.display) }?
+            .drm_device_file()?;
+        crtc_mode(&file, crtc)
+    }
+
+    fn query(&self, attrib: ffi::egl::types::EGLenum) -> Option<u32> {
+        let egl = EGL.as_ref().unwrap();
+        let mut value = 0;
+        unsafe {
+            if egl.QueryOutputLayerAttribEXT(
+                self.display,
+                self.layer,
+                attrib as ffi::egl::types::EGLint,
+                &mut value,
+            ) == 0
+            {
+                return None;
+            }
+        }
+        Some(value as u32)
+    }
+}
+
+impl OutputPort {
+    /// Returns the raw `EGLOutputPortEXT` handle.
+    #[inline]
+    pub fn raw_port(&self) -> ffi::egl::types::EGLOutputPortEXT {
+        self.port
+    }
+
+    /// Returns the ID of the DRM connector of this port.
+    pub fn drm_connector(&self) -> Option<u32> {
+        let egl = EGL.as_ref().unwrap();
+        let mut value = 0;
+        unsafe {
+            if egl.QueryOutputPortAttribEXT(
+                self.display,
+                self.port,
+                ffi::egl::DRM_CONNECTOR_EXT as ffi::egl::types::EGLint,
+                &mut value,
+            ) == 0
+            {
+                return None;
+            }
+        }
+        Some(value as u32)
+    }
+}
+
+/// Returns the output layers of `display`, or `None` if it doesn't support
+/// `EGL_EXT_output_base`.
+pub(super) unsafe fn output_layers(
+    display: ffi::egl::types::EGLDisplay,
+) -> Option<Vec<OutputLayer>> {
+    let egl = EGL.as_ref().unwrap();
+    if !egl.GetOutputLayersEXT.is_loaded() {
+        return None;
+    }
+
+    let layers = enumerate(|layers, max, num| {
+        egl.GetOutputLayersEXT(display, ptr::null(), layers, max, num)
+    })?;
+    Some(
+        layers
+            .into_iter()
+            .map(|layer| OutputLayer { display, layer })
+            .collect(),
+    )
+}
+
+/// Returns the output ports of `display`, or `None` if it doesn't support
+/// `EGL_EXT_output_base`.
+pub(super) unsafe fn output_ports(
+    display: ffi::egl::types::EGLDisplay,
+) -> Option<Vec<OutputPort>> {
+    let egl = EGL.as_ref().unwrap();
+    if !egl.GetOutputPortsEXT.is_loaded() {
+        return None;
+    }
+
+    let ports = enumerate(|ports, max, num| {
+        egl.GetOutputPortsEXT(display, ptr::null(), ports, max, num)
+    })?;
+    Some(
+        ports
+            .into_iter()
+            .map(|port| OutputPort { display, port })
+            .collect(),
+    )
+}
+
+/// Calls `get` once for the number of handles, then once to fill them in.
+unsafe fn enumerate<F>(get: F) -> Option<Vec<*const libc::c_void>>
+where
+    F: Fn(
+        *mut *const libc::c_void,
+        ffi::egl::types::EGLint,
+        *mut ffi::egl::types::EGLint,
+    ) -> ffi::egl::types::EGLBoolean,
+{
+    let mut num = 0;
+    if get(ptr::null_mut(), 0, &mut num) == 0 {
+        return None;
+    }
+
+    let mut handles = vec![ptr::null(); num as usize];
+    if get(handles.as_mut_ptr(), num, &mut num) == 0 {
+        return None;
+    }
+    handles.truncate(num as usize);
+    Some(handles)
+}
+
+/// `struct drm_mode_modeinfo` of the kernel.
+#[cfg(target_os = "linux")]
+#[allow(dead_code)]
+#[repr(C)]
+struct DrmModeModeInfo {
+    clock: u32,
+    hdisplay: u16,
+    hsync_start: u16,
+    hsync_end: u16,
+    htotal: u16,
+    hskew: u16,
+    vdisplay: u16,
+    vsync_start: u16,
+    vsync_end: u16,
+    vtotal: u16,
+    vscan: u16,
+    vrefresh: u32,
+    flags: u32,
+    type_: u32,
+    name: [u8; 32],
+}
+
+/// `struct drm_mode_crtc` of the kernel.
+#[cfg(target_os = "linux")]
+#[allow(dead_code)]
+#[repr(C)]
+struct DrmModeCrtc {
+    set_connectors_ptr: u64,
+    count_connectors: u32,
+    crtc_id: u32,
+    fb_id: u32,
+    x: u32,
+    y: u32,
+    gamma_size: u32,
+    mode_valid: u32,
+    mode: DrmModeModeInfo,
+}
+
+/// Returns `DRM_IOCTL_MODE_GETCRTC`, which is
+/// `_IOWR('d', 0xA1, struct drm_mode_crtc)`.
+///
+/// `libc` knows how the architecture encodes the direction and the size.
+#[cfg(target_os = "linux")]
+fn get_crtc_request() -> libc::Ioctl {
+    libc::_IOWR::<DrmModeCrtc>(b'd' as u32, 0xA1)
+}
+
+/// Reads the mode of `crtc` through `DRM_IOCTL_MODE_GETCRTC`.
+#[cfg(target_os = "linux")]
+fn crtc_mode(drm_device_file: &str, crtc: u32) -> Option<OutputMode> {
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(drm_device_file)
+        .ok()?;
+
+
+    unsafe {
+        let mut info: DrmModeCrtc = mem::zeroed();
+        info.crtc_id = crtc;
+        let request = get_crtc_request();
+        if libc::ioctl(file.as_raw_fd(), request, &mut info) != 0 {
+            return None;
+        }
+        if info.mode_valid == 0 {
+            return None;
+        }
+
+        let mode = &info.mode;
+        // older drivers leave `vrefresh` unset
+        let refresh_rate = if mode.vrefresh != 0 {
+            mode.vrefresh
+        } else if mode.htotal != 0 && mode.vtotal != 0 {
+            (mode.clock as u64 * 1000
+                / (mode.htotal as u64 * mode.vtotal as u64)) as u32
+        } else {
+            0
+        };
+        Some(OutputMode {
+            width: mode.hdisplay as u32,
+            height: mode.vdisplay as u32,
+            refresh_rate,
+        })
+    }
+}
+
+// the layout of the DRM ioctls hasn't been checked on the BSDs
+#[cfg(not(target_os = "linux"))]
+fn crtc_mode(_drm_device_file: &str, _crtc: u32) -> Option<OutputMode> {
+    None
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    #[test]
+    fn get_crtc_request() {
+        // the value of `DRM_IOCTL_MODE_GETCRTC` in the kernel headers
+        assert_eq!(super::get_crtc_request() as u64, 0xC068_64A1);
+    }
+}
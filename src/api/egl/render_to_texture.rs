@@ -0,0 +1,300 @@
+#![cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+
+use super::{check_surface_config, error_name, ffi, gl_proc_address, EGL};
+use api::load_gl;
+use ContextError;
+
+use std::cell::Cell;
+use std::marker::PhantomData;
+use std::mem;
+use std::os::raw::c_int;
+
+const GL_TEXTURE_2D: u32 = 0x0DE1;
+const GL_TEXTURE_MIN_FILTER: u32 = 0x2801;
+const GL_LINEAR: i32 = 0x2601;
+
+/// A pbuffer whose content can be sampled as a `GL_TEXTURE_2D`, through
+/// `eglBindTexImage`.
+///
+/// Render to it within `with_current`, after which the texture returned by
+/// `texture_id` holds the result. It borrows the context it was created
+/// with, which is the one it renders with and the one that owns the
+/// texture.
+pub struct RenderToTexture<'a> {
+    display: ffi::egl::types::EGLDisplay,
+    context: ffi::egl::types::EGLContext,
+    pbuffer: ffi::egl::types::EGLSurface,
+    dimensions: (u32, u32),
+    texture: u32,
+    /// Whether the pbuffer is currently bound to the texture.
+    bound: Cell<bool>,
+    bind_texture: extern "system" fn(u32, u32),
+    delete_textures: extern "system" fn(i32, *const u32),
+    _context: PhantomData<&'a ()>,
+}
+
+impl<'a> RenderToTexture<'a> {
+    /// Creates a `width` by `height` pbuffer with the config of `context`,
+    /// along with the texture it gets bound to.
+    ///
+    /// If `largest` is true and the pbuffer can't be that large, the largest
+    /// available pbuffer is created instead, see `dimensions`.
+    ///
+    /// `context` must be current on this thread, and its config must
+    /// support `EGL_BIND_TO_TEXTURE_RGBA`.
+    pub unsafe fn new(
+        display: ffi::egl::types::EGLDisplay,
+        context: ffi::egl::types::EGLContext,
+        config: ffi::egl::types::EGLConfig,
+        width: u32,
+        height: u32,
+        largest: bool,
+    ) -> Result<RenderToTexture<'a>, ContextError> {
+        let egl = EGL.as_ref().unwrap();
+
+        if egl.GetCurrentContext() != context {
+            return Err(ContextError::NotCurrentOnThisThread);
+        }
+
+        let mut bind_to_texture = 0;
+        egl.GetConfigAttrib(
+            display,
+            config,
+            ffi::egl::BIND_TO_TEXTURE_RGBA as c_int,
+            &mut bind_to_texture,
+        );
+        if bind_to_texture == 0 {
+            return Err(ContextError::OsError(format!(
+                "The config of the context can't be bound to a texture"
+            )));
+        }
+
+        let load = |name| gl_proc_address(display, context, name) as *const ();
+        let gen_textures: extern "system" fn(i32, *mut u32) =
+            load_gl("glGenTextures", load("glGenTextures"))?;
+        let tex_parameter: extern "system" fn(u32, u32, i32) =
+            load_gl("glTexParameteri", load("glTexParameteri"))?;
+        let bind_texture: extern "system" fn(u32, u32) =
+            load_gl("glBindTexture", load("glBindTexture"))?;
+        let delete_textures: extern "system" fn(i32, *const u32) =
+            load_gl("glDeleteTextures", load("glDeleteTextures"))?;
+
+        let attribs = [
+            ffi::egl::WIDTH as c_int,
+            width as c_int,
+            ffi::egl::HEIGHT as c_int,
+            height as c_int,
+            ffi::egl::TEXTURE_FORMAT as c_int,
+            ffi::egl::TEXTURE_RGBA as c_int,
+            ffi::egl::TEXTURE_TARGET as c_int,
+            ffi::egl::TEXTURE_2D as c_int,
+            ffi::egl::LARGEST_PBUFFER as c_int,
+            largest as c_int,
+            ffi::egl::NONE as c_int,
+        ];
+        let pbuffer =
+            egl.CreatePbufferSurface(display, config, attribs.as_ptr());
+        if pbuffer.is_null() {
+            return Err(match egl.GetError() as u32 {
+                ffi::egl::BAD_ALLOC => ContextError::OutOfMemory,
+                err => ContextError::OsError(format!(
+                    "eglCreatePbufferSurface failed: {}",
+                    error_name(err)
+                )),
+            });
+        }
+
+        // with `EGL_LARGEST_PBUFFER`, the pbuffer can be smaller than asked
+        let mut actual_width = 0;
+        let mut actual_height = 0;
+        egl.QuerySurface(
+            display,
+            pbuffer,
+            ffi::egl::WIDTH as c_int,
+            &mut actual_width,
+        );
+        egl.QuerySurface(
+            display,
+            pbuffer,
+            ffi::egl::HEIGHT as c_int,
+            &mut actual_height,
+        );
+
+        // the default filter uses mipmaps, which the pbuffer doesn't have
+        let mut texture = 0;
+        gen_textures(1, &mut texture);
+        bind_texture(GL_TEXTURE_2D, texture);
+        tex_parameter(GL_TEXTURE_2D, GL_TEXTURE_MIN_FILTER, GL_LINEAR);
+
+        Ok(RenderToTexture {
+            display,
+            context,
+            pbuffer,
+            dimensions: (actual_width as u32, actual_height as u32),
+            texture,
+            bound: Cell::new(false),
+            bind_texture,
+            delete_textures,
+            _context: PhantomData,
+        })
+    }
+
+    /// Makes the context current with the pbuffer, calls `f`, then makes it
+    /// current with its previous surfaces again and binds the pbuffer to
+    /// the texture.
+    ///
+    /// The context must be current on this thread. Once this returns, the
+    /// texture is bound to `GL_TEXTURE_2D`.
+    pub fn with_current<R, F>(&self, f: F) -> Result<R, ContextError>
+    where
+        F: FnOnce() -> R,
+    {
+        let egl = EGL.as_ref().unwrap();
+        unsafe {
+            if egl.GetCurrentContext() != self.context {
+                return Err(ContextError::NotCurrentOnThisThread);
+            }
+            let draw = egl.GetCurrentSurface(ffi::egl::DRAW as c_int);
+            let read = egl.GetCurrentSurface(ffi::egl::READ as c_int);
+
+            // a surface can't be rendered to while it is bound
+            if self.bound.get() {
+                egl.ReleaseTexImage(
+                    self.display,
+                    self.pbuffer,
+                    ffi::egl::BACK_BUFFER as c_int,
+                );
+                self.bound.set(false);
+            }
+
+            self.make_current(self.pbuffer, self.pbuffer)?;
+            let restore = RestoreSurfaces {
+                texture: self,
+                draw,
+                read,
+            };
+            let result = f();
+            restore.restore()?;
+
+            (self.bind_texture)(GL_TEXTURE_2D, self.texture);
+            if egl.BindTexImage(
+                self.display,
+                self.pbuffer,
+                ffi::egl::BACK_BUFFER as c_int,
+            ) == 0
+            {
+                return Err(ContextError::OsError(format!(
+                    "eglBindTexImage failed: {}",
+                    error_name(egl.GetError() as u32)
+                )));
+            }
+            self.bound.set(true);
+
+            Ok(result)
+        }
+    }
+
+    /// Returns the name of the texture, which holds what was rendered during
+    /// the last call to `with_current`.
+    #[inline]
+    pub fn texture_id(&self) -> u32 {
+        self.texture
+    }
+
+    /// Returns the width and height of the pbuffer, as reported by EGL.
+    ///
+    /// They are smaller than requested if the pbuffer was created as the
+    /// largest available one.
+    #[inline]
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.dimensions
+    }
+
+    /// Returns the raw `EGLSurface` of the pbuffer.
+    #[inline]
+    pub fn raw_surface(&self) -> ffi::egl::types::EGLSurface {
+        self.pbuffer
+    }
+
+    unsafe fn make_current(
+        &self,
+        draw: ffi::egl::types::EGLSurface,
+        read: ffi::egl::types::EGLSurface,
+    ) -> Result<(), ContextError> {
+        let egl = EGL.as_ref().unwrap();
+        check_surface_config(self.display, self.context, draw)?;
+        check_surface_config(self.display, self.context, read)?;
+
+        if egl.MakeCurrent(self.display, draw, read, self.context) == 0 {
+            return Err(match egl.GetError() as u32 {
+                ffi::egl::CONTEXT_LOST | ffi::egl::BAD_CONTEXT => {
+                    ContextError::ContextLost
+                }
+                ffi::egl::BAD_ALLOC => ContextError::OutOfMemory,
+                ffi::egl::BAD_MATCH => ContextError::ConfigMismatch(format!(
+                    "eglMakeCurrent failed with EGL_BAD_MATCH"
+                )),
+                err => ContextError::OsError(format!(
+                    "eglMakeCurrent failed: {}",
+                    error_name(err)
+                )),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Makes the context of `texture` current with `draw` and `read` again
+/// once dropped, so that it happens even if the closure given to
+/// `with_current` panics.
+struct RestoreSurfaces<'b, 'a: 'b> {
+    texture: &'b RenderToTexture<'a>,
+    draw: ffi::egl::types::EGLSurface,
+    read: ffi::egl::types::EGLSurface,
+}
+
+impl<'b, 'a> RestoreSurfaces<'b, 'a> {
+    /// Restores the surfaces, returning the error that dropping would
+    /// ignore.
+    fn restore(self) -> Result<(), ContextError> {
+        let ret = unsafe { self.texture.make_current(self.draw, self.read) };
+        mem::forget(self);
+        ret
+    }
+}
+
+impl<'b, 'a> Drop for RestoreSurfaces<'b, 'a> {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = self.texture.make_current(self.draw, self.read);
+        }
+    }
+}
+
+impl<'a> Drop for RenderToTexture<'a> {
+    fn drop(&mut self) {
+        let egl = EGL.as_ref().unwrap();
+        unsafe {
+            if self.bound.get() {
+                egl.ReleaseTexImage(
+                    self.display,
+                    self.pbuffer,
+                    ffi::egl::BACK_BUFFER as c_int,
+                );
+            }
+            egl.DestroySurface(self.display, self.pbuffer);
+
+            // the texture can only be deleted while the context is current,
+            // otherwise it lives as long as the context
+            if egl.GetCurrentContext() == self.context {
+                (self.delete_textures)(1, &self.texture);
+            }
+        }
+    }
+}
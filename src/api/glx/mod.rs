@@ -6,14 +6,16 @@
     target_os = "openbsd"
 ))]
 
+use registry::{self, Backend};
 use {
-    Api, ContextError, CreationError, GlAttributes, GlProfile, GlRequest,
-    PixelFormat, PixelFormatRequirements, ReleaseBehavior, Robustness,
+    Api, ConfigCaveat, ContextError, CreationError, GlAttributes, GlProfile,
+    GlRequest, PixelFormat, PixelFormatRequirements, ReleaseBehavior,
+    Robustness,
 };
 
 use std::ffi::{CStr, CString};
-use std::sync::Arc;
-use std::{mem, ptr, slice};
+use std::sync::{Arc, Mutex};
+use std::{fmt, mem, ptr, slice};
 
 use libc::{self, c_int};
 
@@ -80,6 +82,18 @@ pub use self::glx::Glx;
 
 lazy_static! {
     pub static ref GLX: Option<Glx> = Glx::new().ok();
+
+    /// Held while the X error handler is swapped, since it is global to the
+    /// process and contexts may be created from several threads at once.
+    static ref X_ERROR_HANDLER_LOCK: Mutex<()> = Mutex::new(());
+}
+
+/// Returns true if a GLX context is current on the calling thread.
+pub fn has_current_context() -> bool {
+    match *GLX {
+        Some(ref glx) => unsafe { !glx.GetCurrentContext().is_null() },
+        None => false,
+    }
 }
 
 pub struct Context {
@@ -87,6 +101,7 @@ pub struct Context {
     window: ffi::Window,
     context: ffi::GLXContext,
     pixel_format: PixelFormat,
+    flush_on_destroy: bool,
 }
 
 impl Context {
@@ -134,8 +149,7 @@ impl Context {
                 screen_id,
                 pf_reqs,
                 transparent,
-            )
-            .map_err(|_| CreationError::NoAvailablePixelFormat)?
+            )?
         };
 
         // getting the visual infos
@@ -164,6 +178,16 @@ impl Context {
 
     pub unsafe fn make_current(&self) -> Result<(), ContextError> {
         let glx = GLX.as_ref().unwrap();
+
+        // skip the round-trip if this exact binding is already current on
+        // this thread
+        if glx.GetCurrentContext() == self.context
+            && glx.GetCurrentDrawable() == self.window
+            && glx.GetCurrentDisplay() == self.xconn.display as *mut _
+        {
+            return Ok(());
+        }
+
         let res = glx.MakeCurrent(
             self.xconn.display as *mut _,
             self.window,
@@ -180,6 +204,30 @@ impl Context {
         }
     }
 
+    /// Calls `glFinish` on this context, making it current temporarily if
+    /// needed.
+    unsafe fn finish(&self) {
+        let glx = GLX.as_ref().unwrap();
+
+        let prev_display = glx.GetCurrentDisplay();
+        let prev_drawable = glx.GetCurrentDrawable();
+        let prev_context = glx.GetCurrentContext();
+
+        if prev_context != self.context && self.make_current().is_err() {
+            return;
+        }
+
+        let finish = self.get_proc_address("glFinish");
+        if !finish.is_null() {
+            let finish: extern "system" fn() = mem::transmute(finish);
+            finish();
+        }
+
+        if !prev_context.is_null() && prev_context != self.context {
+            glx.MakeCurrent(prev_display, prev_drawable, prev_context);
+        }
+    }
+
     #[inline]
     pub fn is_current(&self) -> bool {
         let glx = GLX.as_ref().unwrap();
@@ -225,13 +273,39 @@ impl Context {
     }
 }
 
+impl fmt::Debug for Context {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let glx = GLX.as_ref().unwrap();
+        let mut config_id = 0;
+        unsafe {
+            glx.QueryContext(
+                self.xconn.display as *mut _,
+                self.context,
+                ffi::glx::FBCONFIG_ID as c_int,
+                &mut config_id,
+            );
+        }
+
+        f.debug_struct("Glx")
+            .field("api", &Api::OpenGl)
+            .field("config_id", &config_id)
+            .finish()
+    }
+}
+
 unsafe impl Send for Context {}
 unsafe impl Sync for Context {}
 
 impl Drop for Context {
     fn drop(&mut self) {
+        registry::unregister(Backend::Glx);
+
         let glx = GLX.as_ref().unwrap();
         unsafe {
+            if self.flush_on_destroy {
+                self.finish();
+            }
+
             if self.is_current() {
                 glx.MakeCurrent(
                     self.xconn.display as *mut _,
@@ -303,6 +377,7 @@ impl<'a> ContextPrototype<'a> {
                             *opengl_version,
                             self.opengl.profile,
                             self.opengl.debug,
+                            self.opengl.forward_compatible,
                             self.opengl.robustness,
                             share,
                             self.xconn.display,
@@ -323,6 +398,7 @@ impl<'a> ContextPrototype<'a> {
                         (1, 0),
                         self.opengl.profile,
                         self.opengl.debug,
+                        self.opengl.forward_compatible,
                         self.opengl.robustness,
                         share,
                         self.xconn.display,
@@ -340,6 +416,7 @@ impl<'a> ContextPrototype<'a> {
                 (major, minor),
                 self.opengl.profile,
                 self.opengl.debug,
+                self.opengl.forward_compatible,
                 self.opengl.robustness,
                 share,
                 self.xconn.display,
@@ -357,6 +434,7 @@ impl<'a> ContextPrototype<'a> {
                 (major, minor),
                 self.opengl.profile,
                 self.opengl.debug,
+                self.opengl.forward_compatible,
                 self.opengl.robustness,
                 share,
                 self.xconn.display,
@@ -418,11 +496,13 @@ impl<'a> ContextPrototype<'a> {
             };
         }
 
+        registry::register(Backend::Glx);
         Ok(Context {
             xconn: self.xconn,
             window,
             context,
             pixel_format: self.pixel_format,
+            flush_on_destroy: self.opengl.flush_on_destroy,
         })
     }
 }
@@ -441,6 +521,7 @@ fn create_context(
     version: (u8, u8),
     profile: Option<GlProfile>,
     debug: bool,
+    forward_compatible: bool,
     robustness: Robustness,
     share: ffi::GLXContext,
     display: *mut ffi::Display,
@@ -449,6 +530,7 @@ fn create_context(
 ) -> Result<ffi::GLXContext, CreationError> {
     let glx = GLX.as_ref().unwrap();
     unsafe {
+        let _guard = X_ERROR_HANDLER_LOCK.lock().unwrap();
         let old_callback = (xlib.XSetErrorHandler)(Some(x_error_callback));
         let context = if check_ext(extensions, "GLX_ARB_create_context") {
             let mut attributes = Vec::with_capacity(9);
@@ -523,6 +605,13 @@ fn create_context(
                         flags | ffi::glx_extra::CONTEXT_DEBUG_BIT_ARB as c_int;
                 }
 
+                // the flag is an error before OpenGL 3.0
+                if forward_compatible && version >= (3, 0) {
+                    flags = flags
+                        | ffi::glx_extra::CONTEXT_FORWARD_COMPATIBLE_BIT_ARB
+                            as c_int;
+                }
+
                 flags
             };
 
@@ -552,9 +641,7 @@ fn create_context(
 
         if context.is_null() {
             // TODO: check for errors and return `OpenGlVersionNotSupported`
-            return Err(CreationError::OsError(format!(
-                "GL context creation failed"
-            )));
+            return Err(CreationError::ContextCreationFailed);
         }
 
         Ok(context)
@@ -570,7 +657,11 @@ unsafe fn choose_fbconfig(
     screen_id: libc::c_int,
     reqs: &PixelFormatRequirements,
     transparent: bool,
-) -> Result<(ffi::glx::types::GLXFBConfig, PixelFormat), ()> {
+) -> Result<(ffi::glx::types::GLXFBConfig, PixelFormat), CreationError> {
+    if reqs.luminance_bits.is_some() {
+        return Err(CreationError::NoAvailablePixelFormat);
+    }
+
     let descriptor = {
         let mut out: Vec<c_int> = Vec::with_capacity(37);
 
@@ -595,7 +686,9 @@ unsafe fn choose_fbconfig(
             if check_ext(extensions, "GLX_ARB_fbconfig_float") {
                 out.push(ffi::glx_extra::RGBA_FLOAT_BIT_ARB as c_int);
             } else {
-                return Err(());
+                return Err(CreationError::ExtensionMissing(
+                    "GLX_ARB_fbconfig_float",
+                ));
             }
         } else {
             out.push(ffi::glx::RGBA_BIT as c_int);
@@ -636,7 +729,9 @@ unsafe fn choose_fbconfig(
                 out.push(ffi::glx_extra::SAMPLES_ARB as c_int);
                 out.push(multisampling as c_int);
             } else {
-                return Err(());
+                return Err(CreationError::ExtensionMissing(
+                    "GLX_ARB_multisample",
+                ));
             }
         }
 
@@ -651,7 +746,9 @@ unsafe fn choose_fbconfig(
                 out.push(ffi::glx_extra::FRAMEBUFFER_SRGB_CAPABLE_EXT as c_int);
                 out.push(1);
             } else {
-                return Err(());
+                return Err(CreationError::ExtensionMissing(
+                    "GLX_ARB_framebuffer_sRGB",
+                ));
             }
         }
 
@@ -686,11 +783,10 @@ unsafe fn choose_fbconfig(
             descriptor.as_ptr(),
             &mut num_configs,
         );
-        if configs.is_null() {
-            return Err(());
-        }
-        if num_configs == 0 {
-            return Err(());
+        if configs.is_null() || num_configs == 0 {
+            return Err(CreationError::NoMatchingConfig {
+                requested: reqs.clone(),
+            });
         }
 
         let config = if transparent {
@@ -711,7 +807,9 @@ unsafe fn choose_fbconfig(
         let res = if let Some(&conf) = config {
             Ok(conf)
         } else {
-            Err(())
+            Err(CreationError::NoMatchingConfig {
+                requested: reqs.clone(),
+            })
         };
 
         (xlib.XFree)(configs as *mut _);
@@ -728,6 +826,11 @@ unsafe fn choose_fbconfig(
     let pf_desc = PixelFormat {
         hardware_accelerated: get_attrib(ffi::glx::CONFIG_CAVEAT as c_int)
             != ffi::glx::SLOW_CONFIG as c_int,
+        caveat: match get_attrib(ffi::glx::CONFIG_CAVEAT as c_int) as u32 {
+            ffi::glx::SLOW_CONFIG => ConfigCaveat::Slow,
+            ffi::glx::NON_CONFORMANT_CONFIG => ConfigCaveat::NonConformant,
+            _ => ConfigCaveat::None,
+        },
         color_bits: get_attrib(ffi::glx::RED_SIZE as c_int) as u8
             + get_attrib(ffi::glx::GREEN_SIZE as c_int) as u8
             + get_attrib(ffi::glx::BLUE_SIZE as c_int) as u8,
@@ -746,6 +849,17 @@ unsafe fn choose_fbconfig(
             || get_attrib(
                 ffi::glx_extra::FRAMEBUFFER_SRGB_CAPABLE_EXT as c_int,
             ) != 0,
+        luminance_bits: 0,
+        accum_bits: (get_attrib(ffi::glx::ACCUM_RED_SIZE as c_int)
+            + get_attrib(ffi::glx::ACCUM_GREEN_SIZE as c_int)
+            + get_attrib(ffi::glx::ACCUM_BLUE_SIZE as c_int)
+            + get_attrib(ffi::glx::ACCUM_ALPHA_SIZE as c_int))
+            as u8,
+        native_visual_id: match get_attrib(ffi::glx::VISUAL_ID as c_int) {
+            0 => None,
+            id => Some(id as u32),
+        },
+        recordable: false,
     };
 
     Ok((fb_config, pf_desc))
@@ -61,6 +61,7 @@
 #![cfg(target_os = "ios")]
 
 use std::ffi::CString;
+use std::fmt;
 use std::io;
 use std::mem;
 use std::os::raw::*;
@@ -70,9 +71,10 @@ use objc::runtime::{Class, Object, Sel, BOOL, NO, YES};
 
 use os::ios::{WindowBuilderExt, WindowExt};
 use os::ContextTraitExt;
+use registry::{self, Backend};
 use {
-    Api, ContextError, CreationError, EventsLoop, GlAttributes, GlRequest,
-    PixelFormat, PixelFormatRequirements, Window, WindowBuilder,
+    Api, ConfigCaveat, ContextError, CreationError, EventsLoop, GlAttributes,
+    GlRequest, PixelFormat, PixelFormatRequirements, Window, WindowBuilder,
 };
 
 mod ffi;
@@ -172,6 +174,14 @@ fn validate_version(version: u8) -> Result<NSUInteger, CreationError> {
     }
 }
 
+/// Returns true if an EAGL context is current on the calling thread.
+pub fn has_current_context() -> bool {
+    let context_class =
+        Class::get("EAGLContext").expect("Failed to get class `EAGLContext`");
+    let current: id = unsafe { msg_send![context_class, currentContext] };
+    current != nil
+}
+
 impl Context {
     #[inline]
     pub fn new(
@@ -210,6 +220,7 @@ impl Context {
             let eagl_context = Context::create_context(version)?;
             let view = window.get_uiview() as id;
             let mut context = Context { eagl_context, view };
+            registry::register(Backend::Eagl);
             context.init_context(&window);
             context
         };
@@ -249,9 +260,7 @@ impl Context {
             version -= 1;
         }
         if valid_context == nil {
-            Err(CreationError::OsError(format!(
-                "Failed to create an OpenGL ES context with any version"
-            )))
+            Err(CreationError::ContextCreationFailed)
         } else {
             Ok(eagl_context)
         }
@@ -334,11 +343,17 @@ impl Context {
         }
     }
 
+    #[inline]
+    pub fn framebuffer_size(&self) -> Option<(u32, u32)> {
+        None
+    }
+
     #[inline]
     pub fn get_pixel_format(&self) -> PixelFormat {
         let color_format = ColorFormat::for_view(self.view);
         PixelFormat {
             hardware_accelerated: true,
+            caveat: ConfigCaveat::None,
             color_bits: color_format.color_bits(),
             alpha_bits: color_format.alpha_bits(),
             depth_bits: depth_for_view(self.view),
@@ -347,6 +362,10 @@ impl Context {
             double_buffer: true,
             multisampling: multisampling_for_view(self.view),
             srgb: color_format.srgb(),
+            luminance_bits: 0,
+            accum_bits: 0,
+            native_visual_id: None,
+            recordable: false,
         }
     }
 
@@ -355,6 +374,13 @@ impl Context {
         // N/A
     }
 
+    #[inline]
+    pub fn reinitialize(&mut self) -> Result<(), ContextError> {
+        Err(ContextError::OsError(
+            "Context reinitialization is only supported with EGL".to_string(),
+        ))
+    }
+
     #[inline]
     pub unsafe fn make_current(&self) -> Result<(), ContextError> {
         let context_class = Class::get("EAGLContext")
@@ -442,8 +468,17 @@ fn create_view_class() {
     }
 }
 
+impl fmt::Debug for Context {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Eagl")
+            .field("api", &Api::OpenGlEs)
+            .finish()
+    }
+}
+
 impl Drop for Context {
     fn drop(&mut self) {
+        registry::unregister(Backend::Eagl);
         let _: () = unsafe { msg_send![self.eagl_context, release] };
     }
 }
@@ -459,4 +494,14 @@ impl ContextTraitExt for Context {
     unsafe fn get_egl_display(&self) -> Option<*const c_void> {
         None
     }
+
+    #[inline]
+    unsafe fn get_egl_surface(&self) -> Option<*const c_void> {
+        None
+    }
+
+    #[inline]
+    unsafe fn get_egl_config(&self) -> Option<*const c_void> {
+        None
+    }
 }
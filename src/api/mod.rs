@@ -2,7 +2,27 @@ pub mod android;
 pub mod caca;
 pub mod dlloader;
 pub mod egl;
+#[cfg(feature = "winit")]
 pub mod glx;
 pub mod ios;
 pub mod osmesa;
 pub mod wgl;
+
+use ContextError;
+
+use std::mem;
+
+/// Turns the address of the OpenGL function `name` into a function pointer
+/// of type `T`, or returns an error if it is null.
+pub(crate) unsafe fn load_gl<T: Copy>(
+    name: &str,
+    addr: *const (),
+) -> Result<T, ContextError> {
+    if addr.is_null() {
+        return Err(ContextError::OsError(format!(
+            "{} couldn't be loaded",
+            name
+        )));
+    }
+    Ok(mem::transmute_copy::<*const (), T>(&addr))
+}
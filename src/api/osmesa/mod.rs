@@ -3,13 +3,22 @@
     target_os = "dragonfly",
     target_os = "freebsd",
     target_os = "netbsd",
-    target_os = "openbsd"
+    target_os = "openbsd",
+    all(
+        feature = "osmesa",
+        any(target_os = "macos", target_os = "windows")
+    )
 ))]
 
+#[cfg(not(target_os = "windows"))]
 extern crate osmesa_sys;
+#[cfg(target_os = "windows")]
+mod osmesa_sys;
 
 use libc;
+use shared_library;
 use Api;
+use ConfigCaveat;
 use ContextError;
 use CreationError;
 use GlAttributes;
@@ -19,6 +28,8 @@ use PixelFormat;
 use PixelFormatRequirements;
 use Robustness;
 
+use registry::{self, Backend};
+
 use std::error::Error;
 use std::ffi::CString;
 use std::fmt::{Debug, Display, Error as FormatError, Formatter};
@@ -29,11 +40,58 @@ pub mod ffi {
     pub use super::osmesa_sys::OSMesaContext;
 }
 
+/// An OsMesa context, along with the buffer it renders to by default.
+///
+/// OsMesa tracks the current context and its buffer per thread, and renders
+/// into the buffer from the thread the context is current on, whenever GL
+/// is called there. The buffer is owned by the context, so it lives as long
+/// as any binding to it, except across `resize`, which may reallocate it:
+/// the context must not be current on another thread then.
 pub struct OsMesaContext {
     context: osmesa_sys::OSMesaContext,
     buffer: Vec<u32>,
     width: u32,
     height: u32,
+    /// The number of pixels between the starts of two rows, or 0 if the
+    /// rows are tightly packed. Never smaller than the width otherwise.
+    row_length: u32,
+    flush_on_destroy: bool,
+    pixel_format: PixelFormat,
+}
+
+/// The type of the channels of a buffer OsMesa renders to.
+///
+/// The channels are always in RGBA order, which is the format OsMesa
+/// contexts are created with.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OsMesaColorFormat {
+    /// 8 bits per channel, which is what the buffer of the context uses.
+    Rgba8,
+    /// 16 bits per channel, as native-endian `u16`s.
+    Rgba16,
+    /// 32 bits per channel, as `f32`s.
+    Rgba32F,
+}
+
+impl OsMesaColorFormat {
+    /// Returns the number of bytes of a pixel.
+    #[inline]
+    pub fn bytes_per_pixel(&self) -> usize {
+        match *self {
+            OsMesaColorFormat::Rgba8 => 4,
+            OsMesaColorFormat::Rgba16 => 8,
+            OsMesaColorFormat::Rgba32F => 16,
+        }
+    }
+
+    #[inline]
+    fn gl_type(&self) -> libc::c_uint {
+        match *self {
+            OsMesaColorFormat::Rgba8 => 0x1401,   // GL_UNSIGNED_BYTE
+            OsMesaColorFormat::Rgba16 => 0x1403,  // GL_UNSIGNED_SHORT
+            OsMesaColorFormat::Rgba32F => 0x1406, // GL_FLOAT
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -54,18 +112,33 @@ impl Error for NoEsOrWebGlSupported {
     }
 }
 
+/// Why the OsMesa library couldn't be loaded.
+///
+/// `shared_library::LoadingError` doesn't implement `Error`, so it is
+/// wrapped to be the source of `CreationError::NoBackendAvailable`.
 #[derive(Debug)]
-struct LoadingError(String);
+pub struct LoadingError(shared_library::LoadingError);
 
 impl LoadingError {
-    fn new<D: Debug>(d: D) -> Self {
-        LoadingError(format!("{:?}", d))
+    /// Returns the error of `shared_library` this was created from.
+    #[inline]
+    pub fn get_ref(&self) -> &shared_library::LoadingError {
+        &self.0
     }
 }
 
 impl Display for LoadingError {
     fn fmt(&self, f: &mut Formatter) -> Result<(), FormatError> {
-        write!(f, "Failed to load OsMesa dynamic library: {}", self.0)
+        match self.0 {
+            shared_library::LoadingError::LibraryNotFound { ref descr } => {
+                write!(f, "Failed to load the OsMesa library: {}", descr)
+            }
+            shared_library::LoadingError::SymbolNotFound { symbol } => write!(
+                f,
+                "The OsMesa library doesn't have the `{}` symbol",
+                symbol
+            ),
+        }
     }
 }
 
@@ -75,15 +148,116 @@ impl Error for LoadingError {
     }
 }
 
+impl From<shared_library::LoadingError> for LoadingError {
+    #[inline]
+    fn from(err: shared_library::LoadingError) -> Self {
+        LoadingError(err)
+    }
+}
+
+impl From<shared_library::LoadingError> for CreationError {
+    #[inline]
+    fn from(err: shared_library::LoadingError) -> Self {
+        CreationError::NoBackendAvailable(Box::new(LoadingError::from(err)))
+    }
+}
+
+/// Returns true if an OsMesa context is current on the calling thread.
+pub fn has_current_context() -> bool {
+    // Calling into OsMesa without the library being loaded panics.
+    if osmesa_sys::OsMesa::try_loading().is_err() {
+        return false;
+    }
+    unsafe { !osmesa_sys::OSMesaGetCurrentContext().is_null() }
+}
+
+/// The OsMesa context that is current on a thread, along with the buffer it
+/// renders to.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+#[derive(Debug, Clone, Copy)]
+pub struct CurrentBinding {
+    context: osmesa_sys::OSMesaContext,
+    buffer: *mut c_void,
+    type_: libc::c_uint,
+    width: libc::c_int,
+    height: libc::c_int,
+}
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+impl CurrentBinding {
+    /// Returns what is current on the calling thread, or `None` if OsMesa
+    /// isn't available or no context is current.
+    pub fn get() -> Option<CurrentBinding> {
+        if !has_current_context() {
+            return None;
+        }
+
+        unsafe {
+            let context = osmesa_sys::OSMesaGetCurrentContext();
+            let mut width = 0;
+            let mut height = 0;
+            let mut format = 0;
+            let mut buffer = ptr::null_mut();
+            osmesa_sys::OSMesaGetColorBuffer(
+                context,
+                &mut width,
+                &mut height,
+                &mut format,
+                &mut buffer,
+            );
+            let mut type_ = 0;
+            osmesa_sys::OSMesaGetIntegerv(osmesa_sys::OSMESA_TYPE, &mut type_);
+
+            Some(CurrentBinding {
+                context,
+                buffer,
+                type_: type_ as libc::c_uint,
+                width,
+                height,
+            })
+        }
+    }
+
+    /// Makes the context current on the calling thread again, with the same
+    /// buffer.
+    ///
+    /// Neither the context nor the buffer must have been destroyed.
+    pub unsafe fn restore(&self) -> Result<(), ContextError> {
+        let ret = osmesa_sys::OSMesaMakeCurrent(
+            self.context,
+            self.buffer,
+            self.type_,
+            self.width,
+            self.height,
+        );
+        if ret == 0 {
+            return Err(ContextError::OsError(format!(
+                "OSMesaMakeCurrent failed"
+            )));
+        }
+        Ok(())
+    }
+}
+
 impl OsMesaContext {
     pub fn new(
         dimensions: (u32, u32),
-        _pf_reqs: &PixelFormatRequirements,
+        pf_reqs: &PixelFormatRequirements,
         opengl: &GlAttributes<&OsMesaContext>,
     ) -> Result<OsMesaContext, CreationError> {
-        osmesa_sys::OsMesa::try_loading()
-            .map_err(LoadingError::new)
-            .map_err(|e| CreationError::NoBackendAvailable(Box::new(e)))?;
+        osmesa_sys::OsMesa::try_loading()?;
 
         if opengl.sharing.is_some() {
             panic!("Context sharing not possible with OsMesa")
@@ -97,19 +271,37 @@ impl OsMesaContext {
             _ => (),
         }
 
-        // TODO: use `pf_reqs` for the format
-
-        let mut attribs = Vec::new();
+        // TODO: use the rest of `pf_reqs` for the format
+
+        // the buffer sizes take 6 values and the profile and the version at
+        // most 6, plus the terminating NULL
+        let mut attribs: [libc::c_int; 13] = [0; 13];
+        let mut len = 0;
+        let mut push = |value| {
+            attribs[len] = value;
+            len += 1;
+        };
+
+        // the sizes are always given so that the pixel format is known,
+        // these are the defaults of OsMesa
+        let depth_bits = pf_reqs.depth_bits.unwrap_or(24);
+        let stencil_bits = pf_reqs.stencil_bits.unwrap_or(8);
+        push(osmesa_sys::OSMESA_DEPTH_BITS);
+        push(depth_bits as libc::c_int);
+        push(osmesa_sys::OSMESA_STENCIL_BITS);
+        push(stencil_bits as libc::c_int);
+        push(osmesa_sys::OSMESA_ACCUM_BITS);
+        push(0);
 
         if let Some(profile) = opengl.profile {
-            attribs.push(osmesa_sys::OSMESA_PROFILE);
+            push(osmesa_sys::OSMESA_PROFILE);
 
             match profile {
                 GlProfile::Compatibility => {
-                    attribs.push(osmesa_sys::OSMESA_COMPAT_PROFILE);
+                    push(osmesa_sys::OSMESA_COMPAT_PROFILE);
                 }
                 GlProfile::Core => {
-                    attribs.push(osmesa_sys::OSMESA_CORE_PROFILE);
+                    push(osmesa_sys::OSMESA_CORE_PROFILE);
                 }
             }
         }
@@ -117,10 +309,10 @@ impl OsMesaContext {
         match opengl.version {
             GlRequest::Latest => {}
             GlRequest::Specific(Api::OpenGl, (major, minor)) => {
-                attribs.push(osmesa_sys::OSMESA_CONTEXT_MAJOR_VERSION);
-                attribs.push(major as libc::c_int);
-                attribs.push(osmesa_sys::OSMESA_CONTEXT_MINOR_VERSION);
-                attribs.push(minor as libc::c_int);
+                push(osmesa_sys::OSMESA_CONTEXT_MAJOR_VERSION);
+                push(major as libc::c_int);
+                push(osmesa_sys::OSMESA_CONTEXT_MINOR_VERSION);
+                push(minor as libc::c_int);
             }
             GlRequest::Specific(Api::OpenGlEs, _)
             | GlRequest::Specific(Api::WebGl, _) => {
@@ -132,49 +324,158 @@ impl OsMesaContext {
                 opengl_version: (major, minor),
                 ..
             } => {
-                attribs.push(osmesa_sys::OSMESA_CONTEXT_MAJOR_VERSION);
-                attribs.push(major as libc::c_int);
-                attribs.push(osmesa_sys::OSMESA_CONTEXT_MINOR_VERSION);
-                attribs.push(minor as libc::c_int);
+                push(osmesa_sys::OSMESA_CONTEXT_MAJOR_VERSION);
+                push(major as libc::c_int);
+                push(osmesa_sys::OSMESA_CONTEXT_MINOR_VERSION);
+                push(minor as libc::c_int);
             }
         }
 
         // attribs array must be NULL terminated.
-        attribs.push(0);
+        push(0);
+
+        let context = unsafe {
+            osmesa_sys::OSMesaCreateContextAttribs(
+                attribs.as_ptr(),
+                ptr::null_mut(),
+            )
+        };
+        if context.is_null() {
+            return Err(CreationError::ContextCreationFailed);
+        }
 
+        registry::register(Backend::OsMesa);
         Ok(OsMesaContext {
             width: dimensions.0,
             height: dimensions.1,
+            row_length: 0,
+            flush_on_destroy: opengl.flush_on_destroy,
             buffer: ::std::iter::repeat(unsafe { mem::uninitialized() })
                 .take((dimensions.0 * dimensions.1) as usize)
                 .collect(),
-            context: unsafe {
-                let ctx = osmesa_sys::OSMesaCreateContextAttribs(
-                    attribs.as_ptr(),
-                    ptr::null_mut(),
-                );
-                if ctx.is_null() {
-                    return Err(CreationError::OsError(
-                        "OSMesaCreateContextAttribs failed".to_string(),
-                    ));
-                }
-                ctx
+            context,
+            pixel_format: PixelFormat {
+                hardware_accelerated: false,
+                caveat: ConfigCaveat::None,
+                color_bits: 24,
+                alpha_bits: 8,
+                depth_bits,
+                stencil_bits,
+                stereoscopy: false,
+                double_buffer: false,
+                multisampling: None,
+                srgb: false,
+                luminance_bits: 0,
+                accum_bits: 0,
+                native_visual_id: None,
+                recordable: false,
             },
         })
     }
 
+    /// Returns the buffer of the context, whose rows are `get_row_length`
+    /// pixels apart.
     #[inline]
     pub fn get_framebuffer(&self) -> &[u32] {
         &self.buffer
     }
 
+    /// Returns the number of pixels between the starts of two rows of the
+    /// buffer of the context.
+    #[inline]
+    pub fn get_row_length(&self) -> u32 {
+        effective_row_length(self.row_length, self.width)
+    }
+
+    /// Sets the number of pixels between the starts of two rows, through
+    /// `OSMESA_ROW_LENGTH`, so that rows can be padded. `0` packs them
+    /// tightly.
+    ///
+    /// This applies to the buffer of the context, which is reallocated to
+    /// hold the padding, and to the buffers of `make_current_raw_buffer`.
+    /// Returns an error if `pixels` isn't `0` and is smaller than the width
+    /// of the buffer of the context.
+    pub fn set_row_length(&mut self, pixels: u32) -> Result<(), ContextError> {
+        if pixels != 0 && pixels < self.width {
+            return Err(ContextError::OsError(format!(
+                "The row length {} is smaller than the width {}",
+                pixels, self.width
+            )));
+        }
+        self.row_length = pixels;
+        let (width, height) = (self.width, self.height);
+        self.resize(width, height);
+        Ok(())
+    }
+
     #[inline]
     pub fn get_dimensions(&self) -> (u32, u32) {
         (self.width, self.height)
     }
 
-    #[inline]
+    /// Changes the size of the buffer that is rendered to. The buffer is
+    /// only reallocated if it grows, and its content is lost.
+    ///
+    /// If the context is current and the new size can't be rendered to, like
+    /// `0x0`, the context stops being current.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        let row_length = self.get_row_length();
+        resize_buffer(&mut self.buffer, row_length, height);
+
+        // OsMesa keeps a pointer to the buffer and its size. If the new
+        // buffer can't be used, the context is released instead of keeping
+        // a pointer to memory that may have been freed
+        if self.is_current() {
+            unsafe {
+                if self.make_current().is_err() {
+                    osmesa_sys::OSMesaMakeCurrent(
+                        ptr::null_mut(),
+                        ptr::null_mut(),
+                        0,
+                        0,
+                        0,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Returns the largest buffer OsMesa can render to, or `None` if the
+    /// context isn't current, which OsMesa requires to answer.
+    pub fn max_buffer_size(&self) -> Option<(u32, u32)> {
+        if !self.is_current() {
+            return None;
+        }
+
+        let mut width = 0;
+        let mut height = 0;
+        unsafe {
+            osmesa_sys::OSMesaGetIntegerv(
+                osmesa_sys::OSMESA_MAX_WIDTH,
+                &mut width,
+            );
+            osmesa_sys::OSMesaGetIntegerv(
+                osmesa_sys::OSMESA_MAX_HEIGHT,
+                &mut height,
+            );
+        }
+        Some((width as u32, height as u32))
+    }
+
+    /// Makes the context current with its buffer.
+    ///
+    /// Returns an error if the buffer is empty, like after a resize to the
+    /// size of a minimized window, or larger than what OsMesa supports.
     pub unsafe fn make_current(&self) -> Result<(), ContextError> {
+        if self.width == 0 || self.height == 0 {
+            return Err(ContextError::OsError(format!(
+                "Can't make an OsMesa context current with a {}x{} buffer",
+                self.width, self.height
+            )));
+        }
+
         let ret = osmesa_sys::OSMesaMakeCurrent(
             self.context,
             self.buffer.as_ptr() as *mut _,
@@ -182,12 +483,90 @@ impl OsMesaContext {
             self.width as libc::c_int,
             self.height as libc::c_int,
         );
+        if ret == 0 {
+            return Err(ContextError::OsError(format!(
+                "OSMesaMakeCurrent failed for a {}x{} buffer",
+                self.width, self.height
+            )));
+        }
+        self.apply_row_length(self.width);
+
+        Ok(())
+    }
+
+    /// Sets `OSMESA_ROW_LENGTH` for a buffer of the given width, once the
+    /// context is current with it.
+    unsafe fn apply_row_length(&self, width: u32) {
+        let row_length = if self.row_length > width {
+            self.row_length
+        } else {
+            0
+        };
+        osmesa_sys::OSMesaPixelStore(
+            osmesa_sys::OSMESA_ROW_LENGTH,
+            row_length as libc::c_int,
+        );
+    }
 
-        // an error can only happen in case of invalid parameter, which would
-        // indicate a bug in glutin
+    /// Makes the context current, rendering to the `width` by `height`
+    /// buffer at `ptr` instead of its own.
+    ///
+    /// `ptr` must point to `row_length * height * format.bytes_per_pixel()`
+    /// writable bytes, where `row_length` is the one set with
+    /// `set_row_length` if it is larger than `width`, and `width` otherwise.
+    /// It must be aligned for the channel type of `format`. OsMesa
+    /// keeps the pointer and writes through it whenever the context renders,
+    /// so the memory must outlive its use by the context, which lasts until
+    /// the context is made current with another buffer, another context is
+    /// made current, or the context is destroyed. Nothing else may access
+    /// the memory while OsMesa renders to it, which is after a call to
+    /// `glFinish` at the latest.
+    ///
+    /// Rows are stored bottom to top. The buffer of the context is left
+    /// untouched, so `get_framebuffer` doesn't return what is rendered here,
+    /// and `make_current` and `resize` go back to rendering to it.
+    pub unsafe fn make_current_raw_buffer(
+        &self,
+        ptr: *mut u8,
+        width: u32,
+        height: u32,
+        format: OsMesaColorFormat,
+    ) -> Result<(), ContextError> {
+        if ptr.is_null() {
+            return Err(ContextError::OsError(format!(
+                "The buffer to render to is null"
+            )));
+        }
+        // OsMesa would accept an empty buffer, and the size the caller has
+        // to provide must fit in memory
+        let size = (width.max(self.row_length) as usize)
+            .checked_mul(height as usize)
+            .and_then(|pixels| pixels.checked_mul(format.bytes_per_pixel()));
+        if width == 0 || height == 0 || size.is_none() {
+            return Err(ContextError::OsError(format!(
+                "Can't render to a {}x{} {:?} buffer",
+                width, height, format
+            )));
+        }
+        self.apply_row_length(width);
+
+        let ret = osmesa_sys::OSMesaMakeCurrent(
+            self.context,
+            ptr as *mut c_void,
+            format.gl_type(),
+            width as libc::c_int,
+            height as libc::c_int,
+        );
+
+        // unlike with the buffer of the context, the parameters come from
+        // the caller, so they can be invalid, like a size that is too large
         if ret == 0 {
-            panic!("OSMesaMakeCurrent failed");
+            return Err(ContextError::OsError(format!(
+                "OSMesaMakeCurrent failed for a {}x{} {:?} buffer",
+                width, height, format
+            )));
         }
+        self.apply_row_length(width);
 
         Ok(())
     }
@@ -197,6 +576,35 @@ impl OsMesaContext {
         unsafe { osmesa_sys::OSMesaGetCurrentContext() == self.context }
     }
 
+    /// Releases the context from the calling thread, if it is current.
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    pub unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        if !self.is_current() {
+            return Ok(());
+        }
+
+        // a null context and buffer unbinds the current context
+        let ret = osmesa_sys::OSMesaMakeCurrent(
+            ptr::null_mut(),
+            ptr::null_mut(),
+            0,
+            0,
+            0,
+        );
+        if ret == 0 {
+            return Err(ContextError::OsError(format!(
+                "OSMesaMakeCurrent failed"
+            )));
+        }
+        Ok(())
+    }
+
     #[inline]
     pub fn get_proc_address(&self, addr: &str) -> *const () {
         unsafe {
@@ -214,7 +622,7 @@ impl OsMesaContext {
 
     #[inline]
     pub fn get_pixel_format(&self) -> PixelFormat {
-        unimplemented!();
+        self.pixel_format.clone()
     }
 
     #[inline]
@@ -223,12 +631,111 @@ impl OsMesaContext {
     }
 }
 
+impl Debug for OsMesaContext {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), FormatError> {
+        f.debug_struct("OsMesa")
+            .field("api", &Api::OpenGl)
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .finish()
+    }
+}
+
+/// Returns the number of pixels between the starts of two rows of a buffer
+/// `width` pixels wide, where a `row_length` of 0 packs the rows tightly.
+fn effective_row_length(row_length: u32, width: u32) -> u32 {
+    row_length.max(width)
+}
+
+/// Resizes `buffer` to `height` rows of `row_length` pixels, all zeroed.
+///
+/// The capacity of the buffer is kept when it shrinks, so that growing it
+/// back to at most its largest size doesn't reallocate.
+fn resize_buffer(buffer: &mut Vec<u32>, row_length: u32, height: u32) {
+    buffer.clear();
+    buffer.resize(row_length as usize * height as usize, 0);
+}
+
 impl Drop for OsMesaContext {
     #[inline]
     fn drop(&mut self) {
-        unsafe { osmesa_sys::OSMesaDestroyContext(self.context) }
+        registry::unregister(Backend::OsMesa);
+
+        unsafe {
+            if self.flush_on_destroy
+                && (self.is_current() || self.make_current().is_ok())
+            {
+                let finish = self.get_proc_address("glFinish");
+                if !finish.is_null() {
+                    let finish: extern "system" fn() = mem::transmute(finish);
+                    finish();
+                }
+            }
+
+            osmesa_sys::OSMesaDestroyContext(self.context)
+        }
     }
 }
 
+// The context is a handle that OsMesa lets any thread make current, and
+// the buffer is only written to by GL calls on the thread it is current on.
+// Making it current on two threads at once is prevented by `make_current`
+// being unsafe, like with the other backends. Reading the buffer through
+// `get_framebuffer` while another thread renders to it is a data race that
+// only GL calls, which are unsafe too, can cause.
 unsafe impl Send for OsMesaContext {}
 unsafe impl Sync for OsMesaContext {}
+
+#[cfg(test)]
+mod tests {
+    use super::{effective_row_length, resize_buffer};
+
+    #[test]
+    fn shrinking_keeps_the_buffer() {
+        let mut buffer = Vec::new();
+        resize_buffer(&mut buffer, 64, 64);
+        assert_eq!(buffer.len(), 64 * 64);
+        let ptr = buffer.as_ptr();
+
+        for pixel in buffer.iter_mut() {
+            *pixel = 0xff0000ff;
+        }
+        resize_buffer(&mut buffer, 16, 8);
+        assert_eq!(buffer.len(), 16 * 8);
+        assert!(buffer.capacity() >= 64 * 64);
+        assert_eq!(buffer.as_ptr(), ptr);
+        assert!(buffer.iter().all(|&pixel| pixel == 0));
+
+        // growing back within the capacity doesn't reallocate either
+        resize_buffer(&mut buffer, 128, 32);
+        assert_eq!(buffer.len(), 128 * 32);
+        assert_eq!(buffer.as_ptr(), ptr);
+        assert!(buffer.iter().all(|&pixel| pixel == 0));
+    }
+
+    #[test]
+    fn growing_reallocates_the_buffer() {
+        let mut buffer = Vec::new();
+        resize_buffer(&mut buffer, 16, 16);
+        resize_buffer(&mut buffer, 128, 64);
+        assert_eq!(buffer.len(), 128 * 64);
+        assert!(buffer.iter().all(|&pixel| pixel == 0));
+
+        resize_buffer(&mut buffer, 0, 0);
+        assert!(buffer.is_empty());
+        assert!(buffer.capacity() >= 128 * 64);
+    }
+
+    #[test]
+    fn rows_are_padded_to_the_row_length() {
+        assert_eq!(effective_row_length(0, 10), 10);
+        assert_eq!(effective_row_length(16, 10), 16);
+
+        // a 10x4 buffer with rows of 16 pixels, then packed again
+        let mut buffer = Vec::new();
+        resize_buffer(&mut buffer, effective_row_length(16, 10), 4);
+        assert_eq!(buffer.len(), 16 * 4);
+        resize_buffer(&mut buffer, effective_row_length(0, 10), 4);
+        assert_eq!(buffer.len(), 10 * 4);
+    }
+}
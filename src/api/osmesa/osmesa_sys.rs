@@ -0,0 +1,167 @@
+//! Bindings to the OsMesa library on Windows.
+//!
+//! `osmesa-sys` looks for `libOSMesa.so` on every platform but MacOS, while
+//! the library is named `osmesa.dll` on Windows. Its functions are declared
+//! with `APIENTRY`, which is `stdcall` on 32-bit Windows, so they are loaded
+//! with the "system" calling convention here.
+
+#![allow(non_snake_case)]
+
+use shared_library::dynamic_library::DynamicLibrary;
+use shared_library::LoadingError;
+
+use std::mem;
+use std::os::raw::{c_char, c_int, c_uchar, c_uint, c_void};
+use std::path::Path;
+
+const LIB_NAME: &'static str = "osmesa.dll";
+
+#[repr(C)]
+pub struct osmesa_context;
+
+pub type OSMesaContext = *mut osmesa_context;
+pub type OSMESAproc = Option<unsafe extern "system" fn()>;
+
+// OSMesaGetIntegerv
+pub const OSMESA_TYPE: c_int = 0x0023;
+pub const OSMESA_MAX_WIDTH: c_int = 0x0024;
+pub const OSMESA_MAX_HEIGHT: c_int = 0x0025;
+
+// OSMesaPixelStore
+pub const OSMESA_ROW_LENGTH: c_int = 0x0010;
+
+// OSMesaCreateContextAttribs
+pub const OSMESA_DEPTH_BITS: c_int = 0x30;
+pub const OSMESA_STENCIL_BITS: c_int = 0x31;
+pub const OSMESA_ACCUM_BITS: c_int = 0x32;
+pub const OSMESA_PROFILE: c_int = 0x33;
+pub const OSMESA_CORE_PROFILE: c_int = 0x34;
+pub const OSMESA_COMPAT_PROFILE: c_int = 0x35;
+pub const OSMESA_CONTEXT_MAJOR_VERSION: c_int = 0x36;
+pub const OSMESA_CONTEXT_MINOR_VERSION: c_int = 0x37;
+
+/// The functions of the OsMesa library, which stays loaded for as long as
+/// the process runs.
+pub struct OsMesa {
+    _library: DynamicLibrary,
+    create_context_attribs: unsafe extern "system" fn(
+        *const c_int,
+        OSMesaContext,
+    ) -> OSMesaContext,
+    destroy_context: unsafe extern "system" fn(OSMesaContext),
+    get_color_buffer: unsafe extern "system" fn(
+        OSMesaContext,
+        *mut c_int,
+        *mut c_int,
+        *mut c_int,
+        *mut *mut c_void,
+    ) -> c_uchar,
+    get_current_context: unsafe extern "system" fn() -> OSMesaContext,
+    get_integerv: unsafe extern "system" fn(c_int, *mut c_int),
+    get_proc_address: unsafe extern "system" fn(*const c_char) -> OSMESAproc,
+    make_current: unsafe extern "system" fn(
+        OSMesaContext,
+        *mut c_void,
+        c_uint,
+        c_int,
+        c_int,
+    ) -> c_uchar,
+    pixel_store: unsafe extern "system" fn(c_int, c_int),
+}
+
+lazy_static! {
+    static ref OSMESA: Result<OsMesa, LoadingError> = OsMesa::open();
+}
+
+impl OsMesa {
+    /// Loads the library the first time it is called, and returns the same
+    /// result afterwards.
+    pub fn try_loading() -> Result<&'static OsMesa, LoadingError> {
+        OSMESA.as_ref().map_err(|err| err.clone())
+    }
+
+    fn open() -> Result<OsMesa, LoadingError> {
+        let library = DynamicLibrary::open(Some(Path::new(LIB_NAME)))
+            .map_err(|descr| LoadingError::LibraryNotFound { descr })?;
+
+        macro_rules! symbol {
+            ($name:expr) => {
+                unsafe {
+                    match library.symbol::<()>($name) {
+                        Ok(ptr) => mem::transmute(ptr),
+                        Err(_) => {
+                            return Err(LoadingError::SymbolNotFound {
+                                symbol: $name,
+                            });
+                        }
+                    }
+                }
+            };
+        }
+
+        Ok(OsMesa {
+            create_context_attribs: symbol!("OSMesaCreateContextAttribs"),
+            destroy_context: symbol!("OSMesaDestroyContext"),
+            get_color_buffer: symbol!("OSMesaGetColorBuffer"),
+            get_current_context: symbol!("OSMesaGetCurrentContext"),
+            get_integerv: symbol!("OSMesaGetIntegerv"),
+            get_proc_address: symbol!("OSMesaGetProcAddress"),
+            make_current: symbol!("OSMesaMakeCurrent"),
+            pixel_store: symbol!("OSMesaPixelStore"),
+            _library: library,
+        })
+    }
+}
+
+// Like with `osmesa-sys`, calling these without the library being loaded
+// panics.
+fn osmesa() -> &'static OsMesa {
+    OsMesa::try_loading().expect("Could not open the OsMesa library")
+}
+
+pub unsafe fn OSMesaCreateContextAttribs(
+    attribList: *const c_int,
+    sharelist: OSMesaContext,
+) -> OSMesaContext {
+    (osmesa().create_context_attribs)(attribList, sharelist)
+}
+
+pub unsafe fn OSMesaDestroyContext(ctx: OSMesaContext) {
+    (osmesa().destroy_context)(ctx)
+}
+
+pub unsafe fn OSMesaGetColorBuffer(
+    c: OSMesaContext,
+    width: *mut c_int,
+    height: *mut c_int,
+    format: *mut c_int,
+    buffer: *mut *mut c_void,
+) -> c_uchar {
+    (osmesa().get_color_buffer)(c, width, height, format, buffer)
+}
+
+pub unsafe fn OSMesaGetCurrentContext() -> OSMesaContext {
+    (osmesa().get_current_context)()
+}
+
+pub unsafe fn OSMesaGetIntegerv(pname: c_int, value: *mut c_int) {
+    (osmesa().get_integerv)(pname, value)
+}
+
+pub unsafe fn OSMesaGetProcAddress(funcName: *const c_char) -> OSMESAproc {
+    (osmesa().get_proc_address)(funcName)
+}
+
+pub unsafe fn OSMesaMakeCurrent(
+    ctx: OSMesaContext,
+    buffer: *mut c_void,
+    _type: c_uint,
+    width: c_int,
+    height: c_int,
+) -> c_uchar {
+    (osmesa().make_current)(ctx, buffer, _type, width, height)
+}
+
+pub unsafe fn OSMesaPixelStore(pname: c_int, value: c_int) {
+    (osmesa().pixel_store)(pname, value)
+}
@@ -1,6 +1,7 @@
 #![cfg(any(target_os = "windows"))]
 
 use Api;
+use ConfigCaveat;
 use ContextError;
 use CreationError;
 use GlAttributes;
@@ -11,12 +12,14 @@ use PixelFormatRequirements;
 use ReleaseBehavior;
 use Robustness;
 
+use registry::{self, Backend};
+
 use self::make_current_guard::CurrentContextGuard;
 
 use std::ffi::{CStr, CString, OsStr};
 use std::os::raw::{c_int, c_void};
 use std::os::windows::ffi::OsStrExt;
-use std::{io, mem, ptr};
+use std::{fmt, io, mem, ptr};
 
 use winapi::shared::minwindef::HMODULE;
 use winapi::shared::minwindef::*;
@@ -45,6 +48,43 @@ pub struct Context {
 
     /// The pixel format that has been used to create this context.
     pixel_format: PixelFormat,
+
+    /// Whether `glFinish` must be called before destroying the context.
+    flush_on_destroy: bool,
+}
+
+impl Drop for Context {
+    fn drop(&mut self) {
+        registry::unregister(Backend::Wgl);
+
+        if !self.flush_on_destroy {
+            return;
+        }
+
+        unsafe {
+            let prev_hdc = gl::wgl::GetCurrentDC();
+            let prev_context = gl::wgl::GetCurrentContext();
+
+            if !self.is_current() && self.make_current().is_err() {
+                return;
+            }
+
+            let finish = self.get_proc_address("glFinish");
+            if !finish.is_null() {
+                let finish: extern "system" fn() = mem::transmute(finish);
+                finish();
+            }
+
+            if prev_context != self.context.0 as *const c_void {
+                gl::wgl::MakeCurrent(prev_hdc, prev_context);
+            }
+        }
+    }
+}
+
+/// Returns true if a WGL context is current on the calling thread.
+pub fn has_current_context() -> bool {
+    unsafe { !gl::wgl::GetCurrentContext().is_null() }
 }
 
 /// A simple wrapper that destroys the window when it is destroyed.
@@ -161,11 +201,13 @@ impl Context {
             }
         }
 
+        registry::register(Backend::Wgl);
         Ok(Context {
             context: context,
             hdc: hdc,
             gl_library: gl_library,
             pixel_format: pixel_format,
+            flush_on_destroy: opengl.flush_on_destroy,
         })
     }
 
@@ -232,6 +274,16 @@ impl Context {
     }
 }
 
+impl fmt::Debug for Context {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let pixel_format_id = unsafe { GetPixelFormat(self.hdc) };
+        f.debug_struct("Wgl")
+            .field("api", &Api::OpenGl)
+            .field("pixel_format_id", &pixel_format_id)
+            .finish()
+    }
+}
+
 unsafe impl Send for Context {}
 unsafe impl Sync for Context {}
 
@@ -397,6 +449,15 @@ unsafe fn create_context(
                         flags | gl::wgl_extra::CONTEXT_DEBUG_BIT_ARB as c_int;
                 }
 
+                // the flag is an error before OpenGL 3.0
+                if opengl.forward_compatible
+                    && opengl.version.to_gl_version() >= Some((3, 0))
+                {
+                    flags = flags
+                        | gl::wgl_extra::CONTEXT_FORWARD_COMPATIBLE_BIT_ARB
+                            as c_int;
+                }
+
                 flags
             };
 
@@ -454,7 +515,7 @@ unsafe fn choose_native_pixel_format(
     // TODO: hardware acceleration is not handled
 
     // handling non-supported stuff
-    if reqs.float_color_buffer {
+    if reqs.float_color_buffer || reqs.luminance_bits.is_some() {
         return Err(());
     }
 
@@ -548,6 +609,7 @@ unsafe fn choose_native_pixel_format(
 
     let pf_desc = PixelFormat {
         hardware_accelerated: (output.dwFlags & PFD_GENERIC_FORMAT) == 0,
+        caveat: ConfigCaveat::None,
         color_bits: output.cRedBits + output.cGreenBits + output.cBlueBits,
         alpha_bits: output.cAlphaBits,
         depth_bits: output.cDepthBits,
@@ -556,6 +618,10 @@ unsafe fn choose_native_pixel_format(
         double_buffer: (output.dwFlags & PFD_DOUBLEBUFFER) != 0,
         multisampling: None,
         srgb: false,
+        luminance_bits: 0,
+        accum_bits: output.cAccumBits,
+        native_visual_id: None,
+        recordable: false,
     };
 
     if pf_desc.alpha_bits < reqs.alpha_bits.unwrap_or(0) {
@@ -593,6 +659,10 @@ unsafe fn choose_arb_pixel_format(
     hdc: HDC,
     reqs: &PixelFormatRequirements,
 ) -> Result<(c_int, PixelFormat), ()> {
+    if reqs.luminance_bits.is_some() {
+        return Err(());
+    }
+
     let descriptor = {
         let mut out: Vec<c_int> = Vec::with_capacity(37);
 
@@ -747,6 +817,7 @@ unsafe fn choose_arb_pixel_format(
     let pf_desc = PixelFormat {
         hardware_accelerated: get_info(gl::wgl_extra::ACCELERATION_ARB)
             != gl::wgl_extra::NO_ACCELERATION_ARB,
+        caveat: ConfigCaveat::None,
         color_bits: get_info(gl::wgl_extra::RED_BITS_ARB) as u8
             + get_info(gl::wgl_extra::GREEN_BITS_ARB) as u8
             + get_info(gl::wgl_extra::BLUE_BITS_ARB) as u8,
@@ -784,6 +855,10 @@ unsafe fn choose_arb_pixel_format(
         } else {
             false
         },
+        luminance_bits: 0,
+        accum_bits: get_info(gl::wgl_extra::ACCUM_BITS_ARB) as u8,
+        native_visual_id: None,
+        recordable: false,
     };
 
     Ok((format_id, pf_desc))
@@ -991,9 +1066,7 @@ fn choose_dummy_pixel_format(hdc: HDC) -> Result<c_int, CreationError> {
     // now querying
     let pf_id = unsafe { ChoosePixelFormat(hdc, &descriptor) };
     if pf_id == 0 {
-        return Err(CreationError::OsError(
-            "No available pixel format".to_owned(),
-        ));
+        return Err(CreationError::NoAvailablePixelFormat);
     }
 
     Ok(pf_id)
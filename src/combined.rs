@@ -54,11 +54,12 @@ impl CombinedContext {
         el: &EventsLoop,
     ) -> Result<Self, CreationError> {
         let ContextBuilder { pf_reqs, gl_attr } = cb;
+        let sharing = gl_attr.sharing;
         let gl_attr = gl_attr.map_sharing(|ctx| &ctx.context);
         platform::Context::new(wb, el, &pf_reqs, &gl_attr).map(
             |(window, context)| CombinedContext {
                 window,
-                context: Context { context },
+                context: Context::from_platform(context, sharing),
             },
         )
     }
@@ -82,7 +83,13 @@ impl CombinedContext {
     /// next time the screen is refreshed. However drivers can choose to
     /// override your vsync settings, which means that you can't know in
     /// advance whether `swap_buffers` will block or not.
+    ///
+    /// The context must be current on the calling thread, otherwise
+    /// `ContextError::NotCurrentOnThisThread` is returned.
     pub fn swap_buffers(&self) -> Result<(), ContextError> {
+        if !self.context.context.is_current() {
+            return Err(ContextError::NotCurrentOnThisThread);
+        }
         self.context.context.swap_buffers()
     }
 
@@ -99,10 +106,27 @@ impl CombinedContext {
     /// The easiest way of doing this is to take every `Resized` window event
     /// that is received with a `LogicalSize` and convert it to a
     /// `PhysicalSize` and pass it into this function.
+    ///
+    /// It must be called from the thread on which the context is current,
+    /// once the window has its new size, and before rendering the next
+    /// frame. On the other platforms this does nothing, so it can always be
+    /// called.
     pub fn resize(&self, size: dpi::PhysicalSize) {
         let (width, height) = size.into();
         self.context.context.resize(width, height);
     }
+
+    /// Recreates the GL context in place. See `Context::reinitialize`.
+    pub fn reinitialize(&mut self) -> Result<(), ContextError> {
+        self.context.reinitialize()
+    }
+
+    /// Checks that the context renders, see `Context::smoke_test`, then
+    /// swaps the buffers.
+    pub unsafe fn smoke_test(&self) -> Result<(), ContextError> {
+        self.context.smoke_test()?;
+        self.swap_buffers()
+    }
 }
 
 impl ContextTrait for CombinedContext {
@@ -1,5 +1,22 @@
 use super::*;
 
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_void};
+use std::sync::{Arc, Mutex};
+
+/// The closure given to `set_debug_callback`. It's boxed twice so that the
+/// user pointer given to OpenGL is thin.
+type DebugCallback = Box<Box<FnMut(DebugMessage) + Send>>;
+
+/// Loads an OpenGL function through the proc address of `$ctx`, returning a
+/// `ContextError` from the surrounding function if it isn't available.
+macro_rules! load_gl {
+    ($ctx:expr, $name:expr, $ty:ty) => {
+        ::api::load_gl::<$ty>($name, $ctx.get_proc_address($name))?
+    };
+}
+
 /// Represents an OpenGL context.
 ///
 /// A `Context` is normally associated with a single Window, however `Context`s
@@ -24,6 +41,29 @@ use super::*;
 /// ```
 pub struct Context {
     pub(crate) context: platform::Context,
+    /// Identifies the share group of the context: all the contexts that
+    /// share their objects hold the same `Arc`.
+    share_group: Arc<()>,
+    /// Addresses already returned by `get_proc_address`. They are stored as
+    /// `usize` because raw pointers aren't `Send`.
+    proc_addresses: Mutex<HashMap<String, usize>>,
+    /// `glFlush` and `glFinish`, loaded on the first call to `flush` and
+    /// `finish`, so that calling them every frame doesn't look them up.
+    flush_fn: Mutex<Option<extern "system" fn()>>,
+    finish_fn: Mutex<Option<extern "system" fn()>>,
+    /// The closure that OpenGL holds a pointer to. It must be dropped after
+    /// the context, which is why it comes last.
+    debug_callback: Mutex<Option<DebugCallback>>,
+}
+
+impl std::fmt::Debug for Context {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Context")
+            .field("backend", &self.context)
+            .field("api", &self.get_api())
+            .field("shared", &self.is_shared())
+            .finish()
+    }
 }
 
 impl ContextTrait for Context {
@@ -36,7 +76,8 @@ impl ContextTrait for Context {
     }
 
     fn get_proc_address(&self, addr: &str) -> *const () {
-        self.context.get_proc_address(addr)
+        let mut proc_addresses = self.proc_addresses.lock().unwrap();
+        self.cached_proc_address(&mut proc_addresses, addr)
     }
 
     fn get_api(&self) -> Api {
@@ -55,13 +96,519 @@ impl Context {
     ///  incompatible system, out of memory, etc.). This should be very rare.
     ///  - If the OpenGL context could not be created. This generally happens
     ///  because the underlying platform doesn't support a requested feature.
+    #[cfg(feature = "winit")]
     pub fn new(
         el: &winit::EventsLoop,
         cb: ContextBuilder,
     ) -> Result<Self, CreationError> {
         let ContextBuilder { pf_reqs, gl_attr } = cb;
+        let sharing = gl_attr.sharing;
         let gl_attr = gl_attr.map_sharing(|ctx| &ctx.context);
         platform::Context::new_context(el, &pf_reqs, &gl_attr)
-            .map(|context| Context { context })
+            .map(|context| Context::from_platform(context, sharing))
+    }
+
+    /// `sharing` is the context that `context` was created to share with.
+    pub(crate) fn from_platform(
+        context: platform::Context,
+        sharing: Option<&Context>,
+    ) -> Self {
+        Context {
+            context,
+            share_group: match sharing {
+                Some(ctx) => ctx.share_group.clone(),
+                None => Arc::new(()),
+            },
+            proc_addresses: Mutex::new(HashMap::new()),
+            flush_fn: Mutex::new(None),
+            finish_fn: Mutex::new(None),
+            debug_callback: Mutex::new(None),
+        }
+    }
+
+    /// Returns whether this context and `other` share their objects, either
+    /// because one was built with the other through
+    /// `ContextBuilder::with_shared_lists` or because both share with a
+    /// third one.
+    ///
+    /// Contexts adopted through `RawContextExt` are never considered to be
+    /// shared, since glutin doesn't know how they were created.
+    pub fn shares_with(&self, other: &Context) -> bool {
+        Arc::ptr_eq(&self.share_group, &other.share_group)
+    }
+
+    /// Returns whether at least one other living context shares its objects
+    /// with this one.
+    pub fn is_shared(&self) -> bool {
+        Arc::strong_count(&self.share_group) > 1
+    }
+
+    /// Returns the address of an OpenGL function, like `get_proc_address`,
+    /// but always asks the backend instead of looking in the cache of
+    /// already resolved addresses.
+    pub fn get_proc_address_uncached(&self, addr: &str) -> *const () {
+        self.context.get_proc_address(addr)
+    }
+
+    /// Returns the addresses of several OpenGL functions at once, in the
+    /// same order as `names`, with null pointers for the functions that
+    /// aren't available.
+    ///
+    /// This is the same as calling `get_proc_address` for each name, but the
+    /// cache of resolved addresses is only locked once.
+    pub fn load_fns<'a>(
+        &self,
+        names: &[&'a str],
+    ) -> Vec<(&'a str, *const c_void)> {
+        let mut proc_addresses = self.proc_addresses.lock().unwrap();
+        names
+            .iter()
+            .map(|&name| {
+                let ptr = self.cached_proc_address(&mut proc_addresses, name);
+                (name, ptr as *const c_void)
+            })
+            .collect()
+    }
+
+    /// Looks `addr` up in `proc_addresses`, asking the backend if it isn't
+    /// there yet.
+    ///
+    /// Null addresses aren't cached: some backends, like WGL, only resolve
+    /// functions while the context is current, so a later call may succeed.
+    fn cached_proc_address(
+        &self,
+        proc_addresses: &mut HashMap<String, usize>,
+        addr: &str,
+    ) -> *const () {
+        if let Some(&ptr) = proc_addresses.get(addr) {
+            return ptr as *const ();
+        }
+
+        let ptr = self.context.get_proc_address(addr);
+        if !ptr.is_null() {
+            proc_addresses.insert(addr.to_owned(), ptr as usize);
+        }
+        ptr
+    }
+
+    /// Returns the function stored in `cache`, loading `name` into it the
+    /// first time.
+    fn cached_gl_fn(
+        &self,
+        cache: &Mutex<Option<extern "system" fn()>>,
+        name: &str,
+    ) -> Result<extern "system" fn(), ContextError> {
+        let mut cache = cache.lock().unwrap();
+        if let Some(f) = *cache {
+            return Ok(f);
+        }
+        let f = unsafe { load_gl!(self, name, extern "system" fn()) };
+        *cache = Some(f);
+        Ok(f)
+    }
+
+    /// Destroys the underlying OpenGL context and creates a new one in place,
+    /// with the same config, attributes and share group.
+    ///
+    /// This is useful to recover when the driver lost the context, for
+    /// example after the application got suspended on mobile. All the OpenGL
+    /// objects of the old context are lost, except those owned by other
+    /// contexts of its share group. If the old context was current on this
+    /// thread, the new one is made current.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Only supported by EGL. Other backends, as well as contexts adopted
+    ///   through `RawContextExt` or sharing with one, return an error.
+    /// - On Android, windowed contexts return an error.
+    pub fn reinitialize(&mut self) -> Result<(), ContextError> {
+        self.context.reinitialize()
+    }
+
+    /// Returns whether the context was lost because of a GPU reset, through
+    /// `glGetGraphicsResetStatus`.
+    ///
+    /// The context must be current. Resets are only reported for contexts
+    /// created with `Robustness::RobustLoseContextOnReset`, and if the
+    /// function isn't available `ResetStatus::NoError` is returned.
+    pub unsafe fn reset_status(&self) -> ResetStatus {
+        const GUILTY_CONTEXT_RESET: u32 = 0x8253;
+        const INNOCENT_CONTEXT_RESET: u32 = 0x8254;
+        const UNKNOWN_CONTEXT_RESET: u32 = 0x8255;
+
+        let ptr = [
+            "glGetGraphicsResetStatus",
+            "glGetGraphicsResetStatusKHR",
+            "glGetGraphicsResetStatusARB",
+            "glGetGraphicsResetStatusEXT",
+        ]
+        .iter()
+        .map(|name| self.get_proc_address(name))
+        .find(|ptr| !ptr.is_null());
+        let get_reset_status = match ptr {
+            Some(ptr) => std::mem::transmute::<
+                *const (),
+                extern "system" fn() -> u32,
+            >(ptr),
+            None => return ResetStatus::NoError,
+        };
+
+        match get_reset_status() {
+            GUILTY_CONTEXT_RESET => ResetStatus::Guilty,
+            INNOCENT_CONTEXT_RESET => ResetStatus::Innocent,
+            UNKNOWN_CONTEXT_RESET => ResetStatus::Unknown,
+            _ => ResetStatus::NoError,
+        }
+    }
+
+    /// Routes the messages of `GL_KHR_debug` into the given closure, replacing
+    /// the previous one.
+    ///
+    /// The context must be current. Debug output is enabled and made
+    /// synchronous, so the closure is called on the thread that issued the
+    /// faulty command. Most drivers only report messages for contexts created
+    /// with the debug flag, see `ContextBuilder::with_gl_debug_flag`.
+    pub unsafe fn set_debug_callback<F>(
+        &self,
+        callback: F,
+    ) -> Result<(), ContextError>
+    where
+        F: FnMut(DebugMessage) + Send + 'static,
+    {
+        const DEBUG_OUTPUT: u32 = 0x92E0;
+        const DEBUG_OUTPUT_SYNCHRONOUS: u32 = 0x8242;
+
+        type DebugProc = extern "system" fn(
+            u32,
+            u32,
+            u32,
+            u32,
+            i32,
+            *const c_char,
+            *mut c_void,
+        );
+
+        extern "system" fn debug_proc(
+            source: u32,
+            ty: u32,
+            id: u32,
+            severity: u32,
+            length: i32,
+            message: *const c_char,
+            user_param: *mut c_void,
+        ) {
+            let message = unsafe {
+                if length < 0 {
+                    CStr::from_ptr(message).to_string_lossy().into_owned()
+                } else {
+                    let bytes = std::slice::from_raw_parts(
+                        message as *const u8,
+                        length as usize,
+                    );
+                    String::from_utf8_lossy(bytes).into_owned()
+                }
+            };
+            let callback = unsafe {
+                &mut *(user_param as *mut Box<FnMut(DebugMessage) + Send>)
+            };
+
+            // unwinding into the driver is undefined behavior
+            let _ =
+                std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                    callback(DebugMessage {
+                        source,
+                        ty,
+                        id,
+                        severity,
+                        message,
+                    })
+                }));
+        }
+
+        let ptr = ["glDebugMessageCallback", "glDebugMessageCallbackKHR"]
+            .iter()
+            .map(|name| self.get_proc_address(name))
+            .find(|ptr| !ptr.is_null());
+        let debug_message_callback = match ptr {
+            Some(ptr) => std::mem::transmute::<
+                *const (),
+                extern "system" fn(DebugProc, *mut c_void),
+            >(ptr),
+            None => {
+                return Err(ContextError::OsError(
+                    "glDebugMessageCallback isn't available".to_string(),
+                ));
+            }
+        };
+        let enable = self.get_proc_address("glEnable");
+        if enable.is_null() {
+            return Err(ContextError::OsError(
+                "glEnable isn't available".to_string(),
+            ));
+        }
+        let enable =
+            std::mem::transmute::<*const (), extern "system" fn(u32)>(enable);
+
+        let mut debug_callback = self.debug_callback.lock().unwrap();
+        let mut callback: DebugCallback = Box::new(Box::new(callback));
+        debug_message_callback(
+            debug_proc,
+            &mut *callback as *mut Box<_> as *mut c_void,
+        );
+        // the previous closure can only be freed now that OpenGL forgot it
+        *debug_callback = Some(callback);
+
+        enable(DEBUG_OUTPUT);
+        enable(DEBUG_OUTPUT_SYNCHRONOUS);
+
+        Ok(())
+    }
+
+    /// Calls `glFlush`, so that the commands issued so far are executed in
+    /// finite time.
+    ///
+    /// Returns `ContextError::NotCurrentOnThisThread` if the context isn't
+    /// current.
+    pub fn flush(&self) -> Result<(), ContextError> {
+        if !self.is_current() {
+            return Err(ContextError::NotCurrentOnThisThread);
+        }
+        let flush = self.cached_gl_fn(&self.flush_fn, "glFlush")?;
+        flush();
+        Ok(())
+    }
+
+    /// Calls `glFinish`, which blocks until the commands issued so far are
+    /// complete, for example before handing a buffer to another API.
+    ///
+    /// Returns `ContextError::NotCurrentOnThisThread` if the context isn't
+    /// current.
+    pub fn finish(&self) -> Result<(), ContextError> {
+        if !self.is_current() {
+            return Err(ContextError::NotCurrentOnThisThread);
+        }
+        let finish = self.cached_gl_fn(&self.finish_fn, "glFinish")?;
+        finish();
+        Ok(())
+    }
+
+    /// Returns whether the context has the *forward-compatible* flag, as
+    /// reported by `GL_CONTEXT_FLAGS`. See
+    /// `ContextBuilder::with_forward_compatible`.
+    ///
+    /// The flag can't be queried before OpenGL 3.0, in which case this
+    /// returns `false`. Returns `ContextError::NotCurrentOnThisThread` if the
+    /// context isn't current.
+    pub fn is_forward_compatible(&self) -> Result<bool, ContextError> {
+        const CONTEXT_FLAGS: u32 = 0x821E;
+        const CONTEXT_FLAG_FORWARD_COMPATIBLE_BIT: i32 = 0x1;
+
+        if !self.is_current() {
+            return Err(ContextError::NotCurrentOnThisThread);
+        }
+        if self.get_api() != Api::OpenGl {
+            return Ok(false);
+        }
+        unsafe {
+            let get_integer = load_gl!(
+                self,
+                "glGetIntegerv",
+                extern "system" fn(u32, *mut i32)
+            );
+            let mut flags = 0;
+            get_integer(CONTEXT_FLAGS, &mut flags);
+            Ok(flags & CONTEXT_FLAG_FORWARD_COMPATIBLE_BIT != 0)
+        }
+    }
+
+    /// Returns the actual size of the surface the context renders to.
+    ///
+    /// With EGL, this is queried through `EGL_WIDTH` and `EGL_HEIGHT` every
+    /// time, so it reflects what the window system allocated. On Wayland,
+    /// it can lag behind the size of the window after a resize, and this is
+    /// the size the viewport should match.
+    ///
+    /// ```no_run
+    /// # extern crate glutin;
+    /// # use glutin::ContextTrait;
+    /// # fn main() {
+    /// # let el = glutin::EventsLoop::new();
+    /// # let context = glutin::ContextBuilder::new()
+    /// #     .build_combined(glutin::WindowBuilder::new(), &el)
+    /// #     .unwrap();
+    /// unsafe { context.make_current().unwrap() };
+    /// if let Some((width, height)) = context.context().framebuffer_size() {
+    ///     // call `glViewport(0, 0, width, height)`
+    /// #   let _ = (width, height);
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Returns `None` with GLX, WGL, MacOS, iOS and Emscripten, which
+    ///   don't expose it, and for EGL contexts without a surface. OsMesa
+    ///   returns the size of its buffer.
+    pub fn framebuffer_size(&self) -> Option<(u32, u32)> {
+        self.context.framebuffer_size()
+    }
+
+    /// Reads the pixels of the given region of the framebuffer, through
+    /// `glReadPixels`.
+    ///
+    /// The context must be current. `x` and `y` are the coordinates of the
+    /// bottom-left corner of the region. The returned bytes are tightly
+    /// packed, with the bottom row first.
+    pub unsafe fn read_pixels(
+        &self,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+        format: ReadFormat,
+    ) -> Result<Vec<u8>, ContextError> {
+        const PACK_ALIGNMENT: u32 = 0x0D05;
+        const UNSIGNED_BYTE: u32 = 0x1401;
+
+        let pixel_store =
+            load_gl!(self, "glPixelStorei", extern "system" fn(u32, i32));
+        let read_pixels = load_gl!(
+            self,
+            "glReadPixels",
+            extern "system" fn(i32, i32, i32, i32, u32, u32, *mut u8)
+        );
+        let get_error =
+            load_gl!(self, "glGetError", extern "system" fn() -> u32);
+
+        let (gl_format, bytes_per_pixel) = match format {
+            ReadFormat::Rgba => (0x1908, 4),
+            ReadFormat::Rgb => (0x1907, 3),
+        };
+        let mut pixels =
+            vec![0u8; width as usize * height as usize * bytes_per_pixel];
+
+        pixel_store(PACK_ALIGNMENT, 1);
+        read_pixels(
+            x,
+            y,
+            width as i32,
+            height as i32,
+            gl_format,
+            UNSIGNED_BYTE,
+            pixels.as_mut_ptr(),
+        );
+
+        let error = get_error();
+        if error != 0 {
+            return Err(ContextError::OsError(format!(
+                "glReadPixels failed with OpenGL error 0x{:x}",
+                error
+            )));
+        }
+
+        Ok(pixels)
+    }
+
+    /// Reads the whole framebuffer of the context, which must be current.
+    ///
+    /// The pixels are the same whatever the backend: with OsMesa they are
+    /// copied from the buffer it renders to, otherwise they are read with
+    /// `glReadPixels`. Rendering is finished first in both cases.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Only supported by EGL contexts that have a surface and by OsMesa,
+    ///   since the size of the framebuffer can't be known otherwise.
+    pub fn capture_framebuffer(&self) -> Result<Image, ContextError> {
+        self.finish()?;
+
+        let (width, height) = match self.context.framebuffer_size() {
+            Some(size) => size,
+            None => {
+                return Err(ContextError::OsError(format!(
+                    "The size of the framebuffer is unknown"
+                )));
+            }
+        };
+
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "dragonfly",
+            target_os = "freebsd",
+            target_os = "netbsd",
+            target_os = "openbsd",
+            all(
+                feature = "osmesa",
+                any(target_os = "macos", target_os = "windows")
+            )
+        ))]
+        {
+            if let Some((buffer, row_length)) =
+                self.context.osmesa_framebuffer()
+            {
+                // the pixels are stored as RGBA bytes, in padded rows
+                let data = unsafe {
+                    std::slice::from_raw_parts(
+                        buffer.as_ptr() as *const u8,
+                        buffer.len() * 4,
+                    )
+                };
+                let data = data
+                    .chunks(row_length as usize * 4)
+                    .flat_map(|row| row[..width as usize * 4].iter().cloned())
+                    .collect();
+                return Ok(Image {
+                    width,
+                    height,
+                    format: ReadFormat::Rgba,
+                    data,
+                });
+            }
+        }
+
+        let data = unsafe {
+            self.read_pixels(0, 0, width, height, ReadFormat::Rgba)?
+        };
+        Ok(Image {
+            width,
+            height,
+            format: ReadFormat::Rgba,
+            data,
+        })
+    }
+
+    /// Checks that the context actually renders, by making it current,
+    /// clearing its framebuffer to magenta and reading back a pixel.
+    ///
+    /// This tells apart a context that was created successfully but doesn't
+    /// render anything, for example because of a broken driver, from a
+    /// working one. The context stays current afterwards and the content of
+    /// its framebuffer is lost.
+    pub unsafe fn smoke_test(&self) -> Result<(), ContextError> {
+        const COLOR_BUFFER_BIT: u32 = 0x4000;
+
+        self.make_current()?;
+
+        let clear_color = load_gl!(
+            self,
+            "glClearColor",
+            extern "system" fn(f32, f32, f32, f32)
+        );
+        let clear = load_gl!(self, "glClear", extern "system" fn(u32));
+        let finish = load_gl!(self, "glFinish", extern "system" fn());
+
+        clear_color(1.0, 0.0, 1.0, 1.0);
+        clear(COLOR_BUFFER_BIT);
+        finish();
+
+        let pixel = self.read_pixels(0, 0, 1, 1, ReadFormat::Rgba)?;
+        if pixel[..3] != [255, 0, 255] {
+            return Err(ContextError::OsError(format!(
+                "Smoke test read back {:?} instead of the clear color",
+                pixel
+            )));
+        }
+
+        Ok(())
     }
 }
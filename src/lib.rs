@@ -25,6 +25,12 @@
 //! You can, of course, create an OpenGL `Context` separately from an existing
 //! window, however that may result in an suboptimal configuration of the window
 //! on some platforms. In that case use "SeparatedContext".
+//!
+//! # Headless builds
+//!
+//! On Unix-like platforms, the default `winit` feature can be disabled for
+//! applications that never create a window. Only the OsMesa and EGL device
+//! contexts are available then, through `os::unix`.
 
 #[macro_use]
 extern crate lazy_static;
@@ -68,6 +74,15 @@ extern crate libloading;
     target_os = "openbsd"
 ))]
 extern crate wayland_client;
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+extern crate wayland_protocols;
+#[cfg(feature = "winit")]
 extern crate winit;
 #[cfg(any(
     target_os = "linux",
@@ -78,19 +93,41 @@ extern crate winit;
 ))]
 extern crate x11_dl;
 
+#[cfg(all(
+    not(feature = "winit"),
+    not(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))
+))]
+compile_error!(
+    "The `winit` feature can only be disabled on Unix-like platforms"
+);
+
 mod api;
+#[cfg(feature = "winit")]
 mod combined;
 mod context;
 mod platform;
+mod registry;
+#[cfg(feature = "winit")]
 mod separated;
 
 pub mod os;
 
+#[cfg(feature = "winit")]
 pub use combined::CombinedContext;
 pub use context::Context;
+#[cfg(feature = "debug_contexts")]
+pub use registry::{debug_context_backends, debug_live_contexts, Backend};
+#[cfg(feature = "winit")]
 pub use separated::SeparatedContext;
 
 use std::io;
+#[cfg(feature = "winit")]
 pub use winit::{
     dpi, AvailableMonitorsIter, AxisId, ButtonId, ControlFlow,
     CreationError as WindowCreationError, DeviceEvent, DeviceId, ElementState,
@@ -112,12 +149,26 @@ where
     fn is_current(&self) -> bool;
 
     /// Returns the address of an OpenGL function.
+    ///
+    /// Addresses are cached by the context, so looking up the same function
+    /// again is cheap. See `Context::get_proc_address_uncached` to bypass
+    /// the cache.
     fn get_proc_address(&self, addr: &str) -> *const ();
 
     /// Returns the OpenGL API being used.
     fn get_api(&self) -> Api;
 }
 
+/// Returns true if an OpenGL context is current on the calling thread.
+///
+/// Contexts that weren't created by glutin are taken into account too, as
+/// long as they were created with one of the backends glutin uses on this
+/// platform. This is useful to check that calling `make_current` won't
+/// clobber an existing binding.
+pub fn has_current_context() -> bool {
+    platform::has_current_context()
+}
+
 /// Object that allows you to build `Context`s.
 pub struct ContextBuilder<'a> {
     /// The attributes to use to create the context.
@@ -149,6 +200,83 @@ impl<'a> ContextBuilder<'a> {
         self
     }
 
+    /// Requests a core profile OpenGL context of the given version. This is a
+    /// shortcut for `with_gl` and `with_gl_profile`.
+    #[inline]
+    pub fn with_gl_core(self, major: u8, minor: u8) -> Self {
+        self.with_gl(GlRequest::Specific(Api::OpenGl, (major, minor)))
+            .with_gl_profile(GlProfile::Core)
+    }
+
+    /// Requests an OpenGL ES context of the given version. This is a shortcut
+    /// for `with_gl`.
+    ///
+    /// Creation fails if OpenGL ES isn't available, it never falls back to
+    /// desktop OpenGL. Use `GlRequest::GlThenGles` for that.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # extern crate glutin;
+    /// # use glutin::ContextTrait;
+    /// # fn main() {
+    /// # let el = glutin::EventsLoop::new();
+    /// let context = glutin::ContextBuilder::new()
+    ///     .with_gles(3, 0)
+    ///     .build_combined(glutin::WindowBuilder::new(), &el)
+    ///     .unwrap();
+    /// assert_eq!(context.get_api(), glutin::Api::OpenGlEs);
+    /// # }
+    /// ```
+    ///
+    /// Desktop OpenGL and OpenGL ES contexts can be used on the same thread,
+    /// glutin binds the right API with EGL:
+    ///
+    /// ```no_run
+    /// # extern crate glutin;
+    /// # use glutin::ContextTrait;
+    /// # fn main() {
+    /// # let el = glutin::EventsLoop::new();
+    /// let gl = glutin::ContextBuilder::new()
+    ///     .with_gl_core(3, 3)
+    ///     .build_headless(&el)
+    ///     .unwrap();
+    /// let gles = glutin::ContextBuilder::new()
+    ///     .with_gles(3, 0)
+    ///     .build_headless(&el)
+    ///     .unwrap();
+    ///
+    /// unsafe { gl.make_current().unwrap() };
+    /// assert!(gl.is_current());
+    /// unsafe { gles.make_current().unwrap() };
+    /// assert!(gles.is_current());
+    /// unsafe { gl.make_current().unwrap() };
+    /// assert!(gl.is_current());
+    /// # }
+    /// ```
+    #[inline]
+    pub fn with_gles(self, major: u8, minor: u8) -> Self {
+        self.with_gl(GlRequest::Specific(Api::OpenGlEs, (major, minor)))
+    }
+
+    /// Requests a core profile OpenGL 3.3 context.
+    #[inline]
+    pub fn with_gl33_core(self) -> Self {
+        self.with_gl_core(3, 3)
+    }
+
+    /// Requests a core profile OpenGL 4.6 context.
+    #[inline]
+    pub fn with_gl46_core(self) -> Self {
+        self.with_gl_core(4, 6)
+    }
+
+    /// Requests an OpenGL ES 3.0 context.
+    #[inline]
+    pub fn with_gles3(self) -> Self {
+        self.with_gles(3, 0)
+    }
+
     /// Sets the *debug* flag for the OpenGL context.
     ///
     /// The default value for this flag is `cfg!(debug_assertions)`, which means
@@ -160,6 +288,65 @@ impl<'a> ContextBuilder<'a> {
         self
     }
 
+    /// Sets the *forward-compatible* flag of the OpenGL context, so that the
+    /// functionality deprecated in OpenGL 3.0 is removed and legacy usage is
+    /// caught early.
+    ///
+    /// This only applies to desktop OpenGL 3.0 and above, usually along with
+    /// a core profile, and is ignored for OpenGL ES or older versions. On
+    /// macOS, core profiles are always forward-compatible, so this only
+    /// makes requesting a version that would get the legacy profile fail.
+    ///
+    /// The default value for this flag is `false`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # extern crate glutin;
+    /// # use glutin::ContextTrait;
+    /// # fn main() {
+    /// # let el = glutin::EventsLoop::new();
+    /// let context = glutin::ContextBuilder::new()
+    ///     .with_gl_core(3, 3)
+    ///     .with_forward_compatible(true)
+    ///     .build_combined(glutin::WindowBuilder::new(), &el)
+    ///     .unwrap();
+    /// unsafe { context.make_current().unwrap() };
+    /// assert!(context.context().is_forward_compatible().unwrap());
+    /// # }
+    /// ```
+    #[inline]
+    pub fn with_forward_compatible(mut self, flag: bool) -> Self {
+        self.gl_attr.forward_compatible = flag;
+        self
+    }
+
+    /// Sets whether building the context fails if the driver gives a lower
+    /// version than the one requested, which EGL allows.
+    ///
+    /// Only honored with EGL. The default is `false`.
+    #[inline]
+    pub fn with_strict_version(mut self, strict: bool) -> Self {
+        self.gl_attr.strict_version = strict;
+        self
+    }
+
+    /// Sets whether the context skips checking for errors, which is faster
+    /// but makes invalid OpenGL calls undefined behavior.
+    ///
+    /// No-error contexts can't be debug or robust contexts, so building
+    /// the context fails if this is combined with the debug flag, which is
+    /// set by default in debug builds, or with any robustness other than
+    /// `NotRobust` or `NoError`. It also fails if the driver doesn't support
+    /// no-error contexts, unlike `Robustness::NoError`.
+    ///
+    /// Only honored with EGL. The default is `false`.
+    #[inline]
+    pub fn with_no_error(mut self, no_error: bool) -> Self {
+        self.gl_attr.no_error = no_error;
+        self
+    }
+
     /// Sets the robustness of the OpenGL context. See the docs of `Robustness`.
     #[inline]
     pub fn with_gl_robustness(mut self, robustness: Robustness) -> Self {
@@ -176,7 +363,38 @@ impl<'a> ContextBuilder<'a> {
         self
     }
 
+    /// Requests that `glFinish` is called on the context when it gets dropped,
+    /// before it is destroyed. This guarantees that the last frame has been
+    /// completely rendered at shutdown.
+    ///
+    /// If the context isn't current on the thread that drops it, it is made
+    /// current temporarily.
+    ///
+    /// By default, the context isn't flushed on destroy.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **macOS / iOS / Emscripten:** Unsupported, the flag is ignored.
+    #[inline]
+    pub fn with_flush_on_destroy(mut self, flush: bool) -> Self {
+        self.gl_attr.flush_on_destroy = flush;
+        self
+    }
+
     /// Share the display lists with the given `Context`.
+    ///
+    /// With EGL, if the pixel format requirements are the same as those
+    /// `other` was built with, the config of `other` is reused instead of
+    /// being chosen again. This saves a call to `eglChooseConfig` per window
+    /// and guarantees that the two contexts are compatible for sharing.
+    ///
+    /// Several contexts can be created concurrently from different threads,
+    /// all sharing with the same `other`, for example one per worker thread.
+    /// `Context` is `Sync`, and the state that the backends keep across
+    /// contexts, like EGL displays, is synchronized internally. As usual with
+    /// OpenGL, each context must only be current on one thread at a time,
+    /// and access to the shared objects must be synchronized by the
+    /// application, for example with fences.
     #[inline]
     pub fn with_shared_lists(mut self, other: &'a Context) -> Self {
         self.gl_attr.sharing = Some(other);
@@ -220,10 +438,44 @@ impl<'a> ContextBuilder<'a> {
     pub fn with_pixel_format(mut self, color_bits: u8, alpha_bits: u8) -> Self {
         self.pf_reqs.color_bits = Some(color_bits);
         self.pf_reqs.alpha_bits = Some(alpha_bits);
+        self.pf_reqs.exact_color_bits = None;
+        self
+    }
+
+    /// Sets the exact number of bits of each component of the color buffer,
+    /// for example `(10, 10, 10, 2)` for a 10-bit color buffer.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Only EGL picks a config with exactly these sizes, failing with
+    ///   `NoMatchingConfig` if there is none. Other backends use the sums as
+    ///   minimums, like `with_pixel_format`.
+    #[inline]
+    pub fn with_color_bits(
+        mut self,
+        red: u8,
+        green: u8,
+        blue: u8,
+        alpha: u8,
+    ) -> Self {
+        self.pf_reqs.color_bits =
+            Some(red.saturating_add(green).saturating_add(blue));
+        self.pf_reqs.alpha_bits = Some(alpha);
+        self.pf_reqs.exact_color_bits = Some((red, green, blue, alpha));
         self
     }
 
     /// Request the backend to be stereoscopic.
+    ///
+    /// Creation fails if there is no stereoscopic config, so you can fall
+    /// back to a regular one.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Supported by GLX, WGL and MacOS. GLX fails with `NoMatchingConfig`,
+    ///   the others with `NoAvailablePixelFormat`.
+    /// - EGL has no stereoscopic configs and always fails with
+    ///   `NoAvailablePixelFormat`.
     #[inline]
     pub fn with_stereoscopy(mut self) -> Self {
         self.pf_reqs.stereoscopy = true;
@@ -239,6 +491,20 @@ impl<'a> ContextBuilder<'a> {
         self
     }
 
+    /// Requests a luminance color buffer with the given number of bits
+    /// instead of an RGB one. The color bits requirement is ignored.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Only supported by EGL. Other backends fail with
+    ///   `NoAvailablePixelFormat`, except for iOS, Emscripten and OsMesa
+    ///   which ignore it.
+    #[inline]
+    pub fn with_luminance_buffer(mut self, bits: u8) -> Self {
+        self.pf_reqs.luminance_bits = Some(bits);
+        self
+    }
+
     /// Sets whether double buffering should be enabled.
     ///
     /// The default value is `None`.
@@ -250,13 +516,17 @@ impl<'a> ContextBuilder<'a> {
     ///   * MacOS
     ///   * Linux using GLX with X
     ///   * Windows using WGL
+    ///   * EGL, by requesting a single-buffered window surface. The window
+    ///     system may ignore it, check `PixelFormat::double_buffer`. On a
+    ///     single-buffered surface, `swap_buffers` only flushes.
     #[inline]
     pub fn with_double_buffer(mut self, double_buffer: Option<bool>) -> Self {
         self.pf_reqs.double_buffer = double_buffer;
         self
     }
 
-    /// Sets whether hardware acceleration is required.
+    /// Sets whether hardware acceleration is required. `Some(false)`
+    /// requires a software renderer instead, and `None` means "don't care".
     ///
     /// The default value is `Some(true)`
     ///
@@ -268,6 +538,10 @@ impl<'a> ContextBuilder<'a> {
     ///   * Linux using EGL with either X or Wayland
     ///   * Windows using EGL or WGL
     ///   * Android using EGL
+    ///
+    /// With EGL, the software rasterizers of Mesa, like llvmpipe, are
+    /// recognized through `EGL_MESA_query_driver` when the display supports
+    /// it. Otherwise only the configs marked as slow count as software.
     #[inline]
     pub fn with_hardware_acceleration(
         mut self,
@@ -277,7 +551,118 @@ impl<'a> ContextBuilder<'a> {
         self
     }
 
+    /// Sets the behavior of the driver when the context stops being current.
+    ///
+    /// The default value is `ReleaseBehavior::Flush`.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - `ReleaseBehavior::None` needs `EGL_KHR_context_flush_control`,
+    ///   `GLX_ARB_context_flush_control` or `WGL_ARB_context_flush_control`.
+    ///   With EGL, creation fails with `NotSupported` when it is missing.
+    /// - MacOS doesn't support `ReleaseBehavior::None`.
+    #[inline]
+    pub fn with_release_behavior(mut self, behavior: ReleaseBehavior) -> Self {
+        self.pf_reqs.release_behavior = behavior;
+        self
+    }
+
+    /// Sets whether offline renderers, like external GPUs, can be chosen.
+    ///
+    /// The default value is `true`.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Only supported on MacOS. Other platforms ignore it.
+    #[inline]
+    pub fn with_offline_renderers(mut self, allow: bool) -> Self {
+        self.pf_reqs.allow_offline_renderers = allow;
+        self
+    }
+
+    /// Sets whether the config must be usable by surfaces that are recorded,
+    /// like the input surface of a `MediaCodec` encoder.
+    ///
+    /// The default value is `false`.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Only supported on Android, through `EGL_ANDROID_recordable`. Other
+    ///   platforms ignore it.
+    #[inline]
+    pub fn with_recordable(mut self, recordable: bool) -> Self {
+        self.pf_reqs.recordable = recordable;
+        self
+    }
+
+    /// Sets which caveated configs can be chosen.
+    ///
+    /// The default value is `None`, in which case the caveat is only chosen
+    /// according to `with_hardware_acceleration`.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Only supported by EGL, where it takes precedence over
+    ///   `with_hardware_acceleration`. Other backends ignore it.
+    #[inline]
+    pub fn with_caveat_policy(mut self, policy: Option<CaveatPolicy>) -> Self {
+        self.pf_reqs.caveat_policy = policy;
+        self
+    }
+
+    /// Tries to build a context with each of `requests` in turn, in place of
+    /// the one set with `with_gl`, and returns the first one that succeeds.
+    ///
+    /// `build` is called with a copy of this builder for each request, and
+    /// is typically one of the `build_*` methods. If every attempt fails,
+    /// `CreationError::AllFallbacksFailed` holds the errors in the order of
+    /// `requests`. A fallback that isn't a `GlRequest`, like OsMesa, can be
+    /// tried on that error.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # extern crate glutin;
+    /// # use glutin::{Api, GlRequest};
+    /// # fn main() {
+    /// # let el = glutin::EventsLoop::new();
+    /// let context = glutin::ContextBuilder::new()
+    ///     .with_gl_profile(glutin::GlProfile::Core)
+    ///     .build_with_fallbacks(
+    ///         &[
+    ///             GlRequest::Specific(Api::OpenGl, (4, 6)),
+    ///             GlRequest::Specific(Api::OpenGl, (3, 3)),
+    ///             GlRequest::Specific(Api::OpenGlEs, (3, 0)),
+    ///         ],
+    ///         |cb| cb.build_combined(glutin::WindowBuilder::new(), &el),
+    ///     )
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub fn build_with_fallbacks<T, F>(
+        self,
+        requests: &[GlRequest],
+        mut build: F,
+    ) -> Result<T, CreationError>
+    where
+        F: FnMut(ContextBuilder<'a>) -> Result<T, CreationError>,
+    {
+        let mut errors = Vec::with_capacity(requests.len());
+        for &request in requests {
+            let cb = ContextBuilder {
+                gl_attr: self.gl_attr.clone(),
+                pf_reqs: self.pf_reqs.clone(),
+            };
+            match build(cb.with_gl(request)) {
+                Ok(built) => return Ok(built),
+                Err(err) => errors.push(err),
+            }
+        }
+        Err(CreationError::AllFallbacksFailed(errors))
+    }
+
     /// Builds a headless context.
+    #[cfg(feature = "winit")]
     pub fn build_headless(
         self,
         el: &EventsLoop,
@@ -286,6 +671,7 @@ impl<'a> ContextBuilder<'a> {
     }
 
     /// Builds a context and it's associated window.
+    #[cfg(feature = "winit")]
     pub fn build_combined(
         self,
         wb: WindowBuilder,
@@ -295,6 +681,7 @@ impl<'a> ContextBuilder<'a> {
     }
 
     /// Builds a separated context.
+    #[cfg(feature = "winit")]
     pub fn build_separated(
         self,
         win: &Window,
@@ -313,11 +700,44 @@ pub enum CreationError {
     NoBackendAvailable(Box<std::error::Error + Send>),
     RobustnessNotSupported,
     OpenGlVersionNotSupported,
+    /// No pixel format (or config) matches the requested
+    /// `PixelFormatRequirements`.
     NoAvailablePixelFormat,
+    /// The backend was asked for configs matching `requested` and returned
+    /// none. Unlike `NoAvailablePixelFormat`, the requirements are kept so
+    /// that the caller can tell which ones to relax.
+    NoMatchingConfig {
+        requested: PixelFormatRequirements,
+    },
+    /// The display connection of the backend could not be obtained or
+    /// initialized.
+    DisplayUnavailable,
+    /// The backend refused to create the OpenGL context.
+    ContextCreationFailed,
+    /// EGL, which is the only way to render with the GPU on Wayland,
+    /// couldn't be loaded or initialized.
+    ///
+    /// Software rendering is still possible, for example by rendering with
+    /// OsMesa and presenting the frames through `wl_shm`, which glutin
+    /// doesn't do for you.
+    NoHardwareAcceleration,
+    /// The backend ran out of memory while allocating the context or its
+    /// surface.
+    ///
+    /// Unlike the other errors, this may succeed if retried after freeing
+    /// some memory.
+    OutOfMemory,
+    /// The requested feature requires an extension the backend doesn't
+    /// expose. Contains the name of the missing extension.
+    ExtensionMissing(&'static str),
     PlatformSpecific(String),
+    #[cfg(feature = "winit")]
     Window(WindowCreationError),
     /// We received two errors, instead of one.
     CreationErrorPair(Box<CreationError>, Box<CreationError>),
+    /// Every request given to `ContextBuilder::build_with_fallbacks` failed.
+    /// Contains the error of each attempt, in order.
+    AllFallbacksFailed(Vec<CreationError>),
 }
 
 impl CreationError {
@@ -335,13 +755,33 @@ impl CreationError {
             CreationError::NoAvailablePixelFormat => {
                 "Couldn't find any pixel format that matches the criteria."
             }
-            CreationError::PlatformSpecific(ref text) => &text,
-            CreationError::Window(ref err) => {
-                std::error::Error::description(err)
+            CreationError::NoMatchingConfig { .. } => {
+                "No config matches the requested pixel format"
             }
+            CreationError::DisplayUnavailable => {
+                "Couldn't obtain or initialize the display."
+            }
+            CreationError::ContextCreationFailed => {
+                "The OpenGL context could not be created."
+            }
+            CreationError::NoHardwareAcceleration => {
+                "EGL is not available, so there is no hardware acceleration."
+            }
+            CreationError::OutOfMemory => {
+                "Not enough memory to create the context or its surface."
+            }
+            CreationError::ExtensionMissing(_) => {
+                "A required extension is not supported."
+            }
+            CreationError::PlatformSpecific(ref text) => &text,
+            #[cfg(feature = "winit")]
+            CreationError::Window(_) => "The window could not be created.",
             CreationError::CreationErrorPair(ref _err1, ref _err2) => {
                 "Received two errors."
             }
+            CreationError::AllFallbacksFailed(_) => {
+                "None of the requested OpenGL versions could be created."
+            }
         }
     }
 }
@@ -362,11 +802,22 @@ impl std::fmt::Display for CreationError {
             write!(formatter, "\"")?;
         }
 
+        if let CreationError::AllFallbacksFailed(ref errors) = *self {
+            for (i, err) in errors.iter().enumerate() {
+                write!(formatter, " Error {}: \"", i + 1)?;
+                err.fmt(formatter)?;
+                write!(formatter, "\"")?;
+            }
+        }
+
         if let &CreationError::NotSupported(msg) = self {
             write!(formatter, ": {}", msg)?;
         }
-        if let Some(err) = std::error::Error::source(self) {
-            write!(formatter, ": {}", err)?;
+        if let &CreationError::ExtensionMissing(ext) = self {
+            write!(formatter, ": {}", ext)?;
+        }
+        if let CreationError::NoMatchingConfig { ref requested } = *self {
+            write!(formatter, ": {}", requested.summary())?;
         }
         Ok(())
     }
@@ -377,15 +828,17 @@ impl std::error::Error for CreationError {
         self.to_string()
     }
 
-    fn cause(&self) -> Option<&std::error::Error> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match *self {
             CreationError::NoBackendAvailable(ref err) => Some(&**err),
+            #[cfg(feature = "winit")]
             CreationError::Window(ref err) => Some(err),
             _ => None,
         }
     }
 }
 
+#[cfg(feature = "winit")]
 impl From<WindowCreationError> for CreationError {
     fn from(err: WindowCreationError) -> Self {
         CreationError::Window(err)
@@ -398,7 +851,26 @@ pub enum ContextError {
     /// General platform error.
     OsError(String),
     IoError(io::Error),
+    /// The context was lost, usually because of a GPU reset or a driver
+    /// restart, and can't be used anymore.
+    ///
+    /// This is recoverable: call `reinitialize` to create a new context in
+    /// place, or drop the context and build a new one, then recreate all the
+    /// OpenGL objects that it owned.
     ContextLost,
+    /// The operation needs the context to be current on the calling thread,
+    /// but it isn't, for example because it was made current on another
+    /// thread.
+    NotCurrentOnThisThread,
+    /// The backend ran out of memory while allocating a surface or its
+    /// buffers. The context is still usable, and the operation may succeed
+    /// if retried after freeing some memory.
+    OutOfMemory,
+    /// The context can't use a surface, because the surface was created with
+    /// a config that isn't compatible with the one of the context. This is
+    /// returned when the surface is bound to the context, or by
+    /// `make_current`. The string describes both configs.
+    ConfigMismatch(String),
 }
 
 impl ContextError {
@@ -408,6 +880,11 @@ impl ContextError {
             ContextError::OsError(ref string) => string,
             ContextError::IoError(ref err) => err.description(),
             ContextError::ContextLost => "Context lost",
+            ContextError::NotCurrentOnThisThread => {
+                "Context not current on this thread"
+            }
+            ContextError::OutOfMemory => "Out of memory",
+            ContextError::ConfigMismatch(ref string) => string,
         }
     }
 }
@@ -425,10 +902,17 @@ impl std::error::Error for ContextError {
     fn description(&self) -> &str {
         self.to_string()
     }
+
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            ContextError::IoError(ref err) => Some(err),
+            _ => None,
+        }
+    }
 }
 
 /// All APIs related to OpenGL that you can possibly get while using glutin.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Api {
     /// The classical OpenGL. Available on Windows, Linux, OS/X.
     OpenGl,
@@ -439,7 +923,7 @@ pub enum Api {
 }
 
 /// Describes the requested OpenGL context profiles.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum GlProfile {
     /// Include all the immediate more functions and definitions.
     Compatibility,
@@ -453,17 +937,30 @@ pub enum GlProfile {
 pub enum GlRequest {
     /// Request the latest version of the "best" API of this platform.
     ///
-    /// On desktop, will try OpenGL.
+    /// On desktop, will try OpenGL. With EGL, desktop OpenGL contexts are
+    /// created with the highest version the driver supports, trying every
+    /// version from 4.6 down to 3.0. The result is remembered for the
+    /// display, so only the first context pays for the probing. Use
+    /// `os::unix::ContextExt::gl_version` to know which version was picked.
     Latest,
 
     /// Request a specific version of a specific API.
     ///
     /// Example: `GlRequest::Specific(Api::OpenGl, (3, 3))`.
+    ///
+    /// The request is strict: if the API isn't available, creation fails
+    /// instead of falling back to another one. With EGL, `Api::OpenGlEs`
+    /// binds `EGL_OPENGL_ES_API` and only considers configs that can render
+    /// with the requested major version, through `EGL_RENDERABLE_TYPE`.
     Specific(Api, (u8, u8)),
 
     /// If OpenGL is available, create an OpenGL context with the specified
     /// `opengl_version`. Else if OpenGL ES or WebGL is available, create a
     /// context with the specified `opengles_version`.
+    ///
+    /// With EGL, OpenGL ES is also used if there is no config for OpenGL or
+    /// if creating the OpenGL context fails. Use `get_api` to know which API
+    /// was picked.
     GlThenGles {
         /// The version to use for OpenGL.
         opengl_version: (u8, u8),
@@ -484,6 +981,53 @@ impl GlRequest {
             _ => None,
         }
     }
+
+    /// Returns a human-readable description of the request, suitable for
+    /// logging.
+    ///
+    /// ```
+    /// # use glutin::{Api, GlRequest};
+    /// let request = GlRequest::GlThenGles {
+    ///     opengl_version: (4, 6),
+    ///     opengles_version: (3, 2),
+    /// };
+    /// assert_eq!(
+    ///     request.describe(),
+    ///     "OpenGL 4.6 (fallback to OpenGL ES 3.2)"
+    /// );
+    /// assert_eq!(
+    ///     GlRequest::Specific(Api::WebGl, (2, 0)).describe(),
+    ///     "WebGL 2.0"
+    /// );
+    /// ```
+    pub fn describe(&self) -> String {
+        fn api_name(api: Api) -> &'static str {
+            match api {
+                Api::OpenGl => "OpenGL",
+                Api::OpenGlEs => "OpenGL ES",
+                Api::WebGl => "WebGL",
+            }
+        }
+
+        match *self {
+            GlRequest::Latest => "Latest available version".to_owned(),
+            GlRequest::Specific(api, (major, minor)) => {
+                format!("{} {}.{}", api_name(api), major, minor)
+            }
+            GlRequest::GlThenGles {
+                opengl_version: (gl_major, gl_minor),
+                opengles_version: (es_major, es_minor),
+            } => format!(
+                "{} {}.{} (fallback to {} {}.{})",
+                api_name(Api::OpenGl),
+                gl_major,
+                gl_minor,
+                api_name(Api::OpenGlEs),
+                es_major,
+                es_minor
+            ),
+        }
+    }
 }
 
 /// The minimum core profile GL context. Useful for getting the minimum
@@ -494,7 +1038,7 @@ pub static GL_CORE: GlRequest = GlRequest::Specific(Api::OpenGl, (3, 2));
 /// Specifies the tolerance of the OpenGL context to faults. If you accept raw
 /// OpenGL commands and/or raw shader code from an untrusted source, you should
 /// definitely care about this.
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Robustness {
     /// Not everything is checked. Your application can crash if you do
     /// something wrong with your shaders.
@@ -506,7 +1050,10 @@ pub enum Robustness {
     ///
     /// Since this option is purely an optimization, no error will be returned
     /// if the backend doesn't support it. Instead it will automatically
-    /// fall back to `NotRobust`.
+    /// fall back to `NotRobust`. This is also the case when the debug flag
+    /// is set, see `ContextBuilder::with_gl_debug_flag`, as no-error
+    /// contexts can't be debug contexts. Use `ContextBuilder::with_no_error`
+    /// to get an error instead.
     NoError,
 
     /// Everything is checked to avoid any crash. The driver will attempt to
@@ -520,8 +1067,7 @@ pub enum Robustness {
 
     /// Everything is checked to avoid any crash. If a problem occurs, the
     /// context will enter a "context lost" state. It must then be
-    /// recreated. For the moment, glutin doesn't provide a way to recreate
-    /// a context with the same window :-/
+    /// recreated, see `Context::reset_status` and `Context::reinitialize`.
     RobustLoseContextOnReset,
 
     /// Same as `RobustLoseContextOnReset` but the context creation doesn't
@@ -529,6 +1075,83 @@ pub enum Robustness {
     TryRobustLoseContextOnReset,
 }
 
+impl Robustness {
+    /// Returns true if the backend may silently ignore this robustness.
+    #[allow(dead_code)]
+    pub(crate) fn is_optional(&self) -> bool {
+        match *self {
+            Robustness::RobustNoResetNotification
+            | Robustness::RobustLoseContextOnReset => false,
+            _ => true,
+        }
+    }
+}
+
+/// Whether a context was lost because of a GPU reset, see
+/// `Context::reset_status`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ResetStatus {
+    /// No reset happened since the context was created.
+    NoError,
+
+    /// A reset happened and was caused by this context.
+    Guilty,
+
+    /// A reset happened but was caused by another context.
+    Innocent,
+
+    /// A reset happened but its cause is unknown.
+    Unknown,
+}
+
+/// A message reported by the driver through `GL_KHR_debug`, see
+/// `Context::set_debug_callback`.
+///
+/// The fields hold the raw `GLenum` values, like `GL_DEBUG_SOURCE_API` or
+/// `GL_DEBUG_SEVERITY_HIGH`.
+#[derive(Debug, Clone)]
+pub struct DebugMessage {
+    /// The source of the message.
+    pub source: u32,
+    /// The type of the message.
+    pub ty: u32,
+    /// The ID of the message, which is specific to the source and type.
+    pub id: u32,
+    /// The severity of the message.
+    pub severity: u32,
+    /// The human-readable text of the message.
+    pub message: String,
+}
+
+/// The format of the pixels returned by `Context::read_pixels`, with one
+/// byte per component.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ReadFormat {
+    /// Red, green, blue and alpha components.
+    Rgba,
+
+    /// Red, green and blue components. OpenGL ES implementations aren't
+    /// required to support it.
+    Rgb,
+}
+
+/// The content of a framebuffer, as returned by
+/// `Context::capture_framebuffer`.
+#[derive(Debug, Clone)]
+pub struct Image {
+    /// The width of the framebuffer, in pixels.
+    pub width: u32,
+    /// The height of the framebuffer, in pixels.
+    pub height: u32,
+    /// Always `ReadFormat::Rgba`.
+    pub format: ReadFormat,
+    /// The pixels, `width * height * 4` bytes of red, green, blue and alpha
+    /// in this order, one byte each. The rows are tightly packed, without
+    /// padding, and stored bottom to top as with OpenGL: the first row is
+    /// the bottom one, so images have to be flipped to be saved top first.
+    pub data: Vec<u8>,
+}
+
 /// The behavior of the driver when you change the current context.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum ReleaseBehavior {
@@ -540,11 +1163,56 @@ pub enum ReleaseBehavior {
     Flush,
 }
 
+/// The caveat of a config, which tells whether it has drawbacks.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ConfigCaveat {
+    /// The config has no caveat.
+    None,
+
+    /// Rendering with the config may be slow, usually because it isn't
+    /// hardware-accelerated.
+    Slow,
+
+    /// The config doesn't pass the conformance tests of its API.
+    NonConformant,
+}
+
+/// Which caveated configs can be chosen, see
+/// `ContextBuilder::with_caveat_policy`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CaveatPolicy {
+    /// Any config can be chosen. Configs without caveat are still preferred.
+    AcceptAny,
+
+    /// Slow configs are never chosen.
+    RejectSlow,
+
+    /// Non-conformant configs are never chosen.
+    RejectNonConformant,
+
+    /// Only configs without any caveat can be chosen.
+    RejectAll,
+}
+
+impl CaveatPolicy {
+    #[inline]
+    pub(crate) fn accepts(&self, caveat: ConfigCaveat) -> bool {
+        match (*self, caveat) {
+            (_, ConfigCaveat::None) | (CaveatPolicy::AcceptAny, _) => true,
+            (CaveatPolicy::RejectSlow, ConfigCaveat::NonConformant) => true,
+            (CaveatPolicy::RejectNonConformant, ConfigCaveat::Slow) => true,
+            _ => false,
+        }
+    }
+}
+
 /// Describes a possible format. Unused.
 #[allow(missing_docs)]
 #[derive(Debug, Clone)]
 pub struct PixelFormat {
     pub hardware_accelerated: bool,
+    /// Always `ConfigCaveat::None` on backends that don't have caveats.
+    pub caveat: ConfigCaveat,
     pub color_bits: u8,
     pub alpha_bits: u8,
     pub depth_bits: u8,
@@ -553,17 +1221,34 @@ pub struct PixelFormat {
     pub double_buffer: bool,
     pub multisampling: Option<u16>,
     pub srgb: bool,
+    /// Number of bits of the luminance buffer, or 0 if the color buffer is
+    /// an RGB one.
+    pub luminance_bits: u8,
+    /// Number of bits of the accumulation buffer, summed over all of its
+    /// channels, or 0 if there is none. EGL configs never have one.
+    pub accum_bits: u8,
+    /// The ID of the native visual that matches this format, like the X11
+    /// visual ID, if the backend has one. Use it to create a window that is
+    /// compatible with the context.
+    pub native_visual_id: Option<u32>,
+    /// Whether surfaces using this format can be recorded. Always `false`
+    /// outside of Android.
+    pub recordable: bool,
 }
 
 /// Describes how the backend should choose a pixel format.
 // TODO: swap method? (swap, copy)
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct PixelFormatRequirements {
     /// If true, only hardware-accelerated formats will be considered. If
     /// false, only software renderers. `None` means "don't care". Default
     /// is `Some(true)`.
     pub hardware_accelerated: Option<bool>,
 
+    /// Which caveated configs can be chosen. `None` means that only
+    /// `hardware_accelerated` is taken into account. Default is `None`.
+    pub caveat_policy: Option<CaveatPolicy>,
+
     /// Minimum number of bits for the color buffer, excluding alpha. `None`
     /// means "don't care". The default is `Some(24)`.
     pub color_bits: Option<u8>,
@@ -575,6 +1260,11 @@ pub struct PixelFormatRequirements {
     /// 1.0]` range.
     pub float_color_buffer: bool,
 
+    /// If `Some`, the exact number of bits of the red, green, blue and alpha
+    /// components, which overrides `color_bits` and `alpha_bits` with EGL.
+    /// The default is `None`.
+    pub exact_color_bits: Option<(u8, u8, u8, u8)>,
+
     /// Minimum number of bits for the alpha in the color buffer. `None` means
     /// "don't care". The default is `Some(8)`.
     pub alpha_bits: Option<u8>,
@@ -605,21 +1295,94 @@ pub struct PixelFormatRequirements {
     /// care. The default is `false`.
     pub srgb: bool,
 
+    /// If `Some`, only luminance color buffers with at least this number of
+    /// bits will be considered, and `color_bits` is ignored. The default is
+    /// `None`.
+    pub luminance_bits: Option<u8>,
+
     /// The behavior when changing the current context. Default is `Flush`.
     pub release_behavior: ReleaseBehavior,
 
+    /// MacOS only: if true, offline renderers like external GPUs can be
+    /// chosen. The default is `true`.
+    pub allow_offline_renderers: bool,
+
+    /// Android only: if true, only configs that can be recorded are chosen.
+    /// The default is `false`.
+    pub recordable: bool,
+
     /// X11 only: set internally to insure a certain visual xid is used when
     /// choosing the fbconfig.
     pub(crate) x11_visual_xid: Option<std::os::raw::c_ulong>,
 }
 
+impl PixelFormatRequirements {
+    /// Describes the requirements that restrict the choice of a config, like
+    /// `24 color bits, 8x MSAA, sRGB`, for error messages.
+    fn summary(&self) -> String {
+        let mut parts = vec![];
+        match (self.exact_color_bits, self.luminance_bits) {
+            (Some((r, g, b, a)), _) => {
+                parts.push(format!("exactly RGBA {}/{}/{}/{}", r, g, b, a))
+            }
+            (None, Some(bits)) => {
+                parts.push(format!("{} luminance bits", bits))
+            }
+            (None, None) => {
+                if let Some(bits) = self.color_bits {
+                    parts.push(format!("{} color bits", bits));
+                }
+                if let Some(bits) = self.alpha_bits {
+                    parts.push(format!("{} alpha bits", bits));
+                }
+            }
+        }
+        if let Some(bits) = self.depth_bits {
+            parts.push(format!("{} depth bits", bits));
+        }
+        if let Some(bits) = self.stencil_bits {
+            parts.push(format!("{} stencil bits", bits));
+        }
+        match self.multisampling {
+            Some(0) => parts.push("no MSAA".to_owned()),
+            Some(samples) => parts.push(format!("{}x MSAA", samples)),
+            None => (),
+        }
+        if self.float_color_buffer {
+            parts.push("float color buffer".to_owned());
+        }
+        if self.srgb {
+            parts.push("sRGB".to_owned());
+        }
+        if self.stereoscopy {
+            parts.push("stereo".to_owned());
+        }
+        match self.double_buffer {
+            Some(true) => parts.push("double buffered".to_owned()),
+            Some(false) => parts.push("single buffered".to_owned()),
+            None => (),
+        }
+        match self.hardware_accelerated {
+            Some(true) => parts.push("hardware accelerated".to_owned()),
+            Some(false) => parts.push("software".to_owned()),
+            None => (),
+        }
+        if self.recordable {
+            parts.push("recordable".to_owned());
+        }
+        parts.join(", ")
+    }
+}
+
 impl Default for PixelFormatRequirements {
     #[inline]
     fn default() -> PixelFormatRequirements {
         PixelFormatRequirements {
             hardware_accelerated: Some(true),
+            caveat_policy: None,
             color_bits: Some(24),
             float_color_buffer: false,
+            exact_color_bits: None,
             alpha_bits: Some(8),
             depth_bits: Some(24),
             stencil_bits: Some(8),
@@ -627,7 +1390,10 @@ impl Default for PixelFormatRequirements {
             multisampling: None,
             stereoscopy: false,
             srgb: false,
+            luminance_bits: None,
             release_behavior: ReleaseBehavior::Flush,
+            allow_offline_renderers: true,
+            recordable: false,
             x11_visual_xid: None,
         }
     }
@@ -658,6 +1424,15 @@ pub struct GlAttributes<S> {
     /// The default is `true` in debug mode and `false` in release mode.
     pub debug: bool,
 
+    /// Whether to set the *forward-compatible* flag of the context, which
+    /// removes the functionality deprecated in OpenGL 3.0.
+    ///
+    /// Only applies to desktop OpenGL 3.0 and above, and is ignored for
+    /// OpenGL ES.
+    ///
+    /// The default is `false`.
+    pub forward_compatible: bool,
+
     /// How the OpenGL context should detect errors.
     ///
     /// The default is `NotRobust` because this is what is typically expected
@@ -671,6 +1446,26 @@ pub struct GlAttributes<S> {
     ///
     /// The default is `false`.
     pub vsync: bool,
+
+    /// Whether to call `glFinish` on the context right before destroying it,
+    /// so that all the commands that have been submitted complete.
+    ///
+    /// The default is `false`.
+    pub flush_on_destroy: bool,
+
+    /// Whether creating the context fails if the driver gives a lower
+    /// version than requested, instead of a context that reports it through
+    /// `os::unix::ContextExt::was_downgraded`.
+    ///
+    /// Only honored with EGL. The default is `false`.
+    pub strict_version: bool,
+
+    /// Whether to ask for a context that doesn't check for errors, like
+    /// `Robustness::NoError` does, but failing if the driver doesn't
+    /// support it.
+    ///
+    /// Only honored with EGL. The default is `false`.
+    pub no_error: bool,
 }
 
 impl<S> GlAttributes<S> {
@@ -685,8 +1480,12 @@ impl<S> GlAttributes<S> {
             version: self.version,
             profile: self.profile,
             debug: self.debug,
+            forward_compatible: self.forward_compatible,
             robustness: self.robustness,
             vsync: self.vsync,
+            flush_on_destroy: self.flush_on_destroy,
+            strict_version: self.strict_version,
+            no_error: self.no_error,
         }
     }
 }
@@ -699,8 +1498,12 @@ impl<S> Default for GlAttributes<S> {
             version: GlRequest::Latest,
             profile: None,
             debug: cfg!(debug_assertions),
+            forward_compatible: false,
             robustness: Robustness::NotRobust,
             vsync: false,
+            flush_on_destroy: false,
+            strict_version: false,
+            no_error: false,
         }
     }
 }
@@ -3,9 +3,11 @@
 pub use winit::os::android::{WindowBuilderExt, WindowExt};
 
 pub use api::egl::ffi::EGLContext;
+pub use api::egl::supports_surfaceless;
 
-use os::ContextTraitExt;
-use Context;
+use os::{ContextTraitExt, RawContextExt};
+use platform;
+use {Context, CreationError};
 
 use std::os::raw;
 
@@ -21,4 +23,26 @@ impl ContextTraitExt for Context {
     unsafe fn get_egl_display(&self) -> Option<*const raw::c_void> {
         Some(self.context.get_egl_display())
     }
+
+    #[inline]
+    unsafe fn get_egl_surface(&self) -> Option<*const raw::c_void> {
+        Some(self.context.get_egl_surface())
+    }
+
+    #[inline]
+    unsafe fn get_egl_config(&self) -> Option<*const raw::c_void> {
+        Some(self.context.get_egl_config())
+    }
+}
+
+impl RawContextExt for Context {
+    #[inline]
+    unsafe fn new_raw_egl(
+        display: *const raw::c_void,
+        context: *const raw::c_void,
+        config: *const raw::c_void,
+    ) -> Result<Self, CreationError> {
+        platform::Context::new_raw_egl(display, context, config)
+            .map(|context| Context::from_platform(context, None))
+    }
 }
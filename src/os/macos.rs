@@ -5,11 +5,108 @@ pub use winit::os::macos::MonitorIdExt;
 pub use winit::os::macos::WindowBuilderExt;
 pub use winit::os::macos::WindowExt;
 
+#[cfg(feature = "osmesa")]
+pub use api::osmesa::OsMesaColorFormat;
+#[cfg(feature = "osmesa")]
+pub use os::OsMesaContextExt;
+
 use os::ContextTraitExt;
-use Context;
+use {Context, ContextError};
 
 use std::os::raw::c_void;
 
+/// A color space in which the content of a window is interpreted.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ColorSpace {
+    /// The standard sRGB color space.
+    Srgb,
+    /// The wide-gamut color space of recent Apple displays.
+    DisplayP3,
+    /// The generic RGB color space, with linear gamma.
+    GenericRgbLinear,
+}
+
+/// Additional methods on `Context` that are specific to MacOS.
+pub trait ContextExt {
+    /// Returns the ID of the renderer that is currently used by the context,
+    /// which tells which GPU it runs on, or `None` if it couldn't be queried.
+    ///
+    /// See `ContextBuilder::with_offline_renderers` to allow external GPUs.
+    fn renderer_id(&self) -> Option<u32>;
+
+    /// Sets the number of screen refreshes to wait for before swapping the
+    /// buffers. `0` disables vsync and `1` enables it.
+    ///
+    /// This overrides the value chosen with `ContextBuilder::with_vsync`.
+    fn set_swap_interval(&self, interval: i32);
+
+    /// Enables or disables the multithreaded OpenGL engine, which moves the
+    /// submission of OpenGL commands to a separate thread.
+    ///
+    /// This can improve throughput for applications that issue many
+    /// commands, but makes the ones that read back data slower.
+    fn enable_multithreaded_engine(
+        &self,
+        enable: bool,
+    ) -> Result<(), ContextError>;
+
+    /// Sets the color space in which the window the context draws into is
+    /// interpreted, which matters on wide-gamut displays.
+    ///
+    /// Returns an error for headless contexts.
+    fn set_colorspace(&self, space: ColorSpace) -> Result<(), ContextError>;
+
+    /// Returns the `NSView` the context draws into, e.g. to overlay AppKit
+    /// controls on top of it.
+    ///
+    /// This is the view of the window, which the context is attached to
+    /// with `-[NSOpenGLContext setView:]`. Returns `None` for headless
+    /// contexts.
+    unsafe fn raw_view(&self) -> Option<*mut c_void>;
+
+    /// Returns the `CALayer` of the view the context draws into.
+    ///
+    /// glutin doesn't render through a `CAOpenGLLayer`, so this is only
+    /// `Some` if the view was made layer-backed, e.g. with
+    /// `-[NSView setWantsLayer:]`. Returns `None` for headless contexts.
+    unsafe fn raw_layer(&self) -> Option<*mut c_void>;
+}
+
+impl ContextExt for Context {
+    #[inline]
+    fn renderer_id(&self) -> Option<u32> {
+        self.context.renderer_id()
+    }
+
+    #[inline]
+    fn set_swap_interval(&self, interval: i32) {
+        self.context.set_swap_interval(interval)
+    }
+
+    #[inline]
+    fn enable_multithreaded_engine(
+        &self,
+        enable: bool,
+    ) -> Result<(), ContextError> {
+        self.context.enable_multithreaded_engine(enable)
+    }
+
+    #[inline]
+    fn set_colorspace(&self, space: ColorSpace) -> Result<(), ContextError> {
+        self.context.set_colorspace(space)
+    }
+
+    #[inline]
+    unsafe fn raw_view(&self) -> Option<*mut c_void> {
+        self.context.raw_view()
+    }
+
+    #[inline]
+    unsafe fn raw_layer(&self) -> Option<*mut c_void> {
+        self.context.raw_layer()
+    }
+}
+
 impl ContextTraitExt for Context {
     type Handle = *mut c_void;
 
@@ -22,4 +119,14 @@ impl ContextTraitExt for Context {
     unsafe fn get_egl_display(&self) -> Option<*const c_void> {
         None
     }
+
+    #[inline]
+    unsafe fn get_egl_surface(&self) -> Option<*const c_void> {
+        None
+    }
+
+    #[inline]
+    unsafe fn get_egl_config(&self) -> Option<*const c_void> {
+        None
+    }
 }
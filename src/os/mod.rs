@@ -17,6 +17,20 @@ pub mod windows;
 
 use std::os::raw;
 
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    all(
+        feature = "osmesa",
+        any(target_os = "macos", target_os = "windows")
+    )
+))]
+use platform;
+use {ContextError, CreationError};
+
 /// Platform-specific extensions for OpenGL contexts.
 pub trait ContextTraitExt {
     /// Raw context handle.
@@ -31,4 +45,236 @@ pub trait ContextTraitExt {
     /// Return `None` if the context doesn't use EGL.
     // The pointer will become invalid when the context is destroyed.
     unsafe fn get_egl_display(&self) -> Option<*const raw::c_void>;
+
+    /// Returns a pointer to the `EGLSurface` object of EGL that is used by this
+    /// context.
+    ///
+    /// Return `None` if the context doesn't use EGL. Headless contexts that
+    /// were created without a surface return `EGL_NO_SURFACE`.
+    // The pointer will become invalid when the context is destroyed.
+    unsafe fn get_egl_surface(&self) -> Option<*const raw::c_void>;
+
+    /// Returns a pointer to the `EGLConfig` object of EGL that was used to
+    /// create this context.
+    ///
+    /// An `EGLConfig` isn't a resource owned by the context, so the pointer
+    /// can be copied freely and stays valid for as long as the display is
+    /// initialized. Copies are interchangeable: contexts created with the
+    /// same config can always share their lists.
+    ///
+    /// Return `None` if the context doesn't use EGL.
+    unsafe fn get_egl_config(&self) -> Option<*const raw::c_void>;
+}
+
+/// Platform-specific extensions to adopt OpenGL contexts that were created
+/// outside of glutin.
+pub trait RawContextExt
+where
+    Self: Sized,
+{
+    /// Wraps an existing `EGLContext`, created from the given `EGLDisplay`
+    /// and `EGLConfig`, so that it can be used through glutin.
+    ///
+    /// The returned context doesn't take ownership of the handles: dropping
+    /// it neither destroys the context nor terminates the display, which stay
+    /// the responsibility of their creator. It has no surface of its own,
+    /// so `swap_buffers` will fail and `make_current` needs
+    /// `EGL_KHR_surfaceless_context`, see `supports_surfaceless`.
+    ///
+    /// The handles must stay valid for as long as the returned value is
+    /// alive.
+    unsafe fn new_raw_egl(
+        display: *const raw::c_void,
+        context: *const raw::c_void,
+        config: *const raw::c_void,
+    ) -> Result<Self, CreationError>;
+}
+
+/// Builds headless contexts with OsMesa, the software renderer of Mesa.
+///
+/// This is available on Unix-like platforms, and on MacOS and Windows when
+/// the `osmesa` feature is enabled. The OsMesa library is loaded at runtime,
+/// so a missing library only makes `new_osmesa` fail with
+/// `CreationError::NoBackendAvailable`. It is looked up as `osmesa.dll` on
+/// Windows, `libOSMesa.dylib` on MacOS and `libOSMesa.so` everywhere else.
+///
+/// OsMesa contexts can be sent to and shared with other threads, but they
+/// render on the thread they are current on, into the buffer they were made
+/// current with. `resize_osmesa` may reallocate the buffer of the context,
+/// so the context must not be current on another thread when it is called.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    all(
+        feature = "osmesa",
+        any(target_os = "macos", target_os = "windows")
+    )
+))]
+pub trait OsMesaContextExt {
+    fn new_osmesa(cb: ::ContextBuilder) -> Result<Self, CreationError>
+    where
+        Self: Sized;
+
+    /// Changes the size of the buffer an OsMesa context renders to, which
+    /// is 1x1 when the context is created.
+    ///
+    /// The buffer is only reallocated when it grows, and its content is lost.
+    /// If the context is current, it is made current again so that OsMesa
+    /// renders to the resized buffer. Returns an error if the context isn't
+    /// an OsMesa context.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # extern crate glutin;
+    /// # use glutin::os::unix::OsMesaContextExt;
+    /// # use glutin::ContextTrait;
+    /// # fn main() {
+    /// let mut context =
+    ///     glutin::Context::new_osmesa(glutin::ContextBuilder::new()).unwrap();
+    /// unsafe { context.make_current().unwrap() };
+    ///
+    /// context.resize_osmesa(64, 64).unwrap();
+    /// context.resize_osmesa(16, 16).unwrap();
+    /// let image = context.capture_framebuffer().unwrap();
+    /// assert_eq!((image.width, image.height), (16, 16));
+    ///
+    /// context.resize_osmesa(128, 32).unwrap();
+    /// let image = context.capture_framebuffer().unwrap();
+    /// assert_eq!(image.data.len(), 128 * 32 * 4);
+    /// # }
+    /// ```
+    fn resize_osmesa(
+        &mut self,
+        width: u32,
+        height: u32,
+    ) -> Result<(), ContextError>;
+
+    /// Sets the number of pixels between the starts of two rows of the
+    /// buffers an OsMesa context renders to, so that rows can be padded, for
+    /// example to the alignment a texture upload requires. `0`, the default,
+    /// packs them tightly.
+    ///
+    /// This applies to the buffer of the context, which is reallocated and
+    /// whose content is lost, and to the buffers of
+    /// `make_current_raw_buffer` that are narrower than `pixels`.
+    /// `capture_framebuffer` skips the padding. Returns an error if the
+    /// context isn't an OsMesa context, or if `pixels` isn't `0` and is
+    /// smaller than the width of the buffer of the context.
+    fn set_osmesa_row_length(
+        &mut self,
+        pixels: u32,
+    ) -> Result<(), ContextError>;
+
+    /// Makes an OsMesa context current, rendering directly to the `width`
+    /// by `height` buffer at `ptr` instead of its own, which avoids a copy
+    /// when the pixels are needed elsewhere, like in shared memory.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to `row_length * height * format.bytes_per_pixel()`
+    /// writable bytes, where `row_length` is the one set with
+    /// `set_osmesa_row_length` if it is larger than `width`, and `width`
+    /// otherwise. It must be aligned for the channel type of `format`.
+    ///
+    /// OsMesa keeps the pointer, so the memory must stay valid until the
+    /// context is made current with another buffer, another context is made
+    /// current on this thread, or the context is dropped. The memory must not
+    /// be read or written by anything else while OsMesa may render to it:
+    /// call `glFinish` before touching it.
+    ///
+    /// Rows are stored bottom to top. `capture_framebuffer` keeps returning
+    /// the buffer of the context, and `make_current` and `resize_osmesa` go
+    /// back to rendering to it. Returns an error if the context isn't an
+    /// OsMesa context, or if OsMesa rejects the buffer.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # extern crate glutin;
+    /// # use glutin::os::unix::{OsMesaColorFormat, OsMesaContextExt};
+    /// # fn main() {
+    /// let context =
+    ///     glutin::Context::new_osmesa(glutin::ContextBuilder::new()).unwrap();
+    ///
+    /// let format = OsMesaColorFormat::Rgba8;
+    /// let mut pixels = vec![0u8; 64 * 64 * format.bytes_per_pixel()];
+    /// unsafe {
+    ///     context
+    ///         .make_current_raw_buffer(pixels.as_mut_ptr(), 64, 64, format)
+    ///         .unwrap();
+    /// }
+    /// // render, call `glFinish`, then make another buffer current before
+    /// // `pixels` is dropped
+    /// # }
+    /// ```
+    unsafe fn make_current_raw_buffer(
+        &self,
+        ptr: *mut u8,
+        width: u32,
+        height: u32,
+        format: ::api::osmesa::OsMesaColorFormat,
+    ) -> Result<(), ContextError>;
+}
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    all(
+        feature = "osmesa",
+        any(target_os = "macos", target_os = "windows")
+    )
+))]
+impl OsMesaContextExt for ::Context {
+    /// Builds the given OsMesa context.
+    ///
+    /// Errors can occur if the OpenGL context could not be created. This
+    /// generally happens because the underlying platform doesn't support a
+    /// requested feature.
+    #[inline]
+    fn new_osmesa(cb: ::ContextBuilder) -> Result<Self, CreationError>
+    where
+        Self: Sized,
+    {
+        let ::ContextBuilder { pf_reqs, gl_attr } = cb;
+        let sharing = gl_attr.sharing;
+        let gl_attr = gl_attr.map_sharing(|ctx| &ctx.context);
+        platform::Context::new_osmesa(&pf_reqs, &gl_attr)
+            .map(|context| ::Context::from_platform(context, sharing))
+    }
+
+    #[inline]
+    fn resize_osmesa(
+        &mut self,
+        width: u32,
+        height: u32,
+    ) -> Result<(), ContextError> {
+        self.context.resize_osmesa(width, height)
+    }
+
+    #[inline]
+    fn set_osmesa_row_length(
+        &mut self,
+        pixels: u32,
+    ) -> Result<(), ContextError> {
+        self.context.set_row_length_osmesa(pixels)
+    }
+
+    #[inline]
+    unsafe fn make_current_raw_buffer(
+        &self,
+        ptr: *mut u8,
+        width: u32,
+        height: u32,
+        format: ::api::osmesa::OsMesaColorFormat,
+    ) -> Result<(), ContextError> {
+        self.context
+            .make_current_raw_buffer_osmesa(ptr, width, height, format)
+    }
 }
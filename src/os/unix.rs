@@ -7,21 +7,447 @@
 ))]
 
 pub use api::egl::ffi::EGLContext;
+pub use api::egl::{
+    device_by_pci_id, supports_surfaceless, Device as EglDevice,
+    Display as EglDisplay, DmabufHandle, EglImage,
+    NativeDisplay as EglNativeDisplay, OutputLayer, OutputMode, OutputPort,
+    Rect, RenderToTexture,
+};
+#[cfg(feature = "winit")]
 pub use api::glx::ffi::GLXContext;
-pub use platform::RawHandle;
+pub use api::osmesa::{
+    LoadingError as OsMesaLoadingError, OsMesaColorFormat,
+};
+pub use os::OsMesaContextExt;
+#[cfg(feature = "winit")]
+pub use platform::is_egl_available;
+pub use platform::{
+    EglDeviceContextExt, EglImageContextExt, EglSurfaceContextExt,
+    PreviousCurrent, RawHandle,
+};
 
+#[cfg(feature = "winit")]
 pub use winit::os::unix::EventsLoopExt;
+#[cfg(feature = "winit")]
 pub use winit::os::unix::MonitorIdExt;
+#[cfg(feature = "winit")]
 pub use winit::os::unix::WindowBuilderExt;
+#[cfg(feature = "winit")]
 pub use winit::os::unix::WindowExt;
+#[cfg(feature = "winit")]
 pub use winit::os::unix::XNotSupported;
+#[cfg(feature = "winit")]
 pub use winit::os::unix::XWindowType;
 
-use os::ContextTraitExt;
-use Context;
+use os::{ContextTraitExt, RawContextExt};
+use platform;
+use {Context, ContextError, CreationError};
 
+use std::ops::RangeInclusive;
 use std::os::raw;
 
+/// Additional methods on `Context` that are specific to Unix.
+pub trait ContextExt {
+    /// Returns the largest width and height of the offscreen buffers the
+    /// context can render to, so that oversized render targets can be
+    /// rejected up front.
+    ///
+    /// With EGL, this is the maximum pbuffer size of the config of the
+    /// context. The config also caps the number of pixels, usually to less
+    /// than both maximums multiplied, which `resize_pbuffer` and the
+    /// creation of headless contexts check, failing with a message naming
+    /// `EGL_MAX_PBUFFER_PIXELS`. With OsMesa, this is the maximum buffer
+    /// size, which can only be queried while the context is current. Returns
+    /// `None` with GLX, or if the size couldn't be queried.
+    fn max_buffer_size(&self) -> Option<(u32, u32)>;
+
+    /// Returns whether the context was created with robust buffer access,
+    /// which the `TryRobust*` variants of `Robustness` don't guarantee.
+    ///
+    /// Returns `None` with GLX, and `Some(false)` for EGL contexts created
+    /// outside of glutin.
+    fn is_robust(&self) -> Option<bool>;
+
+    /// Changes the size of the pbuffer a headless EGL context renders to,
+    /// like the ones created with `new_egl_device`, which are 1x1.
+    ///
+    /// EGL can't resize pbuffers, so a new one is created with the same
+    /// config and the old one is destroyed, along with its content. If the
+    /// context is current, it is made current again with the new pbuffer.
+    /// Returns an error if the context doesn't render to such a pbuffer, or
+    /// if the pbuffer couldn't be created.
+    fn resize_pbuffer(
+        &mut self,
+        width: u32,
+        height: u32,
+    ) -> Result<(), ContextError>;
+
+    /// Returns the OpenGL or OpenGL ES version an EGL context was created
+    /// with, as reported by the driver.
+    ///
+    /// With `GlRequest::Latest` and desktop OpenGL, EGL contexts are created
+    /// with the highest version the driver supports, starting from 4.6, and
+    /// this tells which one it was. Returns `None` with GLX and OsMesa.
+    ///
+    /// ```no_run
+    /// # extern crate glutin;
+    /// # use glutin::os::unix::ContextExt;
+    /// # fn main() {
+    /// let el = glutin::EventsLoop::new();
+    /// let context = glutin::ContextBuilder::new()
+    ///     .with_gl(glutin::GlRequest::Latest)
+    ///     .build_headless(&el)
+    ///     .unwrap();
+    /// if let Some((major, minor)) = context.gl_version() {
+    ///     println!("OpenGL {}.{}", major, minor);
+    /// }
+    /// # }
+    /// ```
+    fn gl_version(&self) -> Option<(u8, u8)>;
+
+    /// Swaps the buffers, telling the compositor that only `rects` changed
+    /// since the previous frame so that it can skip redrawing the rest,
+    /// through `EGL_KHR_swap_buffers_with_damage` or
+    /// `EGL_EXT_swap_buffers_with_damage`.
+    ///
+    /// The origin of the rectangles is the bottom-left corner of the window,
+    /// as usual with OpenGL. They are clamped to the size of the window, and
+    /// the ones left without any area are dropped. If none is left, the
+    /// whole window is damaged. Without either extension, or with GLX, the
+    /// whole window is swapped as with `swap_buffers`.
+    ///
+    /// ```no_run
+    /// # extern crate glutin;
+    /// # use glutin::os::unix::{ContextExt, Rect};
+    /// # use glutin::ContextTrait;
+    /// # fn main() {
+    /// # let el = glutin::EventsLoop::new();
+    /// # let context = glutin::ContextBuilder::new()
+    /// #     .build_combined(glutin::WindowBuilder::new(), &el)
+    /// #     .unwrap();
+    /// unsafe { context.make_current().unwrap() };
+    /// // a cursor and a status bar changed
+    /// let cursor = Rect::from_xywh(100, 200, 16, 16);
+    /// let status = Rect::from_xywh(0, 0, 800, 24);
+    /// context.context().swap_buffers_with_damage(&[cursor, status]).unwrap();
+    /// # }
+    /// ```
+    fn swap_buffers_with_damage(
+        &self,
+        rects: &[Rect],
+    ) -> Result<(), ContextError>;
+
+    /// Like `swap_buffers_with_damage`, but first merges the rectangles that
+    /// overlap or share an edge into their union. If more than 16 are still
+    /// left, their bounding box is passed instead, since EGL doesn't tell
+    /// how many a driver handles well.
+    ///
+    /// `swap_buffers_with_damage` passes the rectangles as given.
+    fn swap_buffers_with_damage_coalesced(
+        &self,
+        rects: &[Rect],
+    ) -> Result<(), ContextError>;
+
+    /// Returns whether `swap_buffers_with_damage` actually passes the damage
+    /// on to the compositor, so that computing precise damage is worth it.
+    ///
+    /// This requires a double-buffered EGL window surface and
+    /// `EGL_KHR_swap_buffers_with_damage` or
+    /// `EGL_EXT_swap_buffers_with_damage`. Returns false otherwise, in which
+    /// case the damage is ignored and the whole window is swapped.
+    fn damage_is_effective(&self) -> bool;
+
+    /// Returns whether the driver created an EGL context with a lower
+    /// version than requested, which EGL allows without
+    /// `EGL_KHR_create_context` and for the minor version of OpenGL ES.
+    ///
+    /// `ContextBuilder::with_strict_version` turns such a downgrade into a
+    /// creation error instead. Returns `None` with GLX and OsMesa.
+    ///
+    /// ```no_run
+    /// # extern crate glutin;
+    /// # use glutin::os::unix::ContextExt;
+    /// # fn main() {
+    /// let el = glutin::EventsLoop::new();
+    /// let context = glutin::ContextBuilder::new()
+    ///     .with_gl(glutin::GlRequest::Specific(glutin::Api::OpenGlEs, (3, 1)))
+    ///     .build_headless(&el)
+    ///     .unwrap();
+    /// if context.was_downgraded() == Some(true) {
+    ///     println!("Only got OpenGL ES {:?}", context.gl_version());
+    /// }
+    /// # }
+    /// ```
+    fn was_downgraded(&self) -> Option<bool>;
+
+    /// Returns the smallest and largest swap intervals the config of the
+    /// context supports, through `EGL_MIN_SWAP_INTERVAL` and
+    /// `EGL_MAX_SWAP_INTERVAL`.
+    ///
+    /// EGL silently clamps intervals outside of this range, so this tells
+    /// which vsync options can actually be offered. Returns `None` with GLX
+    /// and OsMesa.
+    fn swap_interval_range(&self) -> Option<RangeInclusive<i32>>;
+
+    /// Sets the number of screen refreshes to wait for before swapping the
+    /// buffers of an EGL context. `0` disables vsync and `1` enables it.
+    ///
+    /// The context must be current. Returns an error if `interval` is
+    /// outside of `swap_interval_range`, or if the context isn't an EGL
+    /// context.
+    ///
+    /// ```no_run
+    /// # extern crate glutin;
+    /// # use glutin::os::unix::ContextExt;
+    /// # use glutin::ContextTrait;
+    /// # fn main() {
+    /// # let el = glutin::EventsLoop::new();
+    /// # let context = glutin::ContextBuilder::new()
+    /// #     .build_combined(glutin::WindowBuilder::new(), &el)
+    /// #     .unwrap();
+    /// unsafe { context.make_current().unwrap() };
+    /// let context = context.context();
+    /// if let Some(range) = context.swap_interval_range() {
+    ///     let interval = 2.max(*range.start()).min(*range.end());
+    ///     context.set_swap_interval(interval).unwrap();
+    /// }
+    /// # }
+    /// ```
+    fn set_swap_interval(&self, interval: i32) -> Result<(), ContextError>;
+
+    /// Returns the display of the context, which tells which EGL
+    /// implementation and driver it runs on.
+    ///
+    /// The returned display stays initialized even once the context is
+    /// dropped, unless the context was created outside of glutin. Returns
+    /// `None` if the context isn't an EGL context.
+    ///
+    /// ```no_run
+    /// # extern crate glutin;
+    /// # use glutin::os::unix::ContextExt;
+    /// # fn main() {
+    /// let el = glutin::EventsLoop::new();
+    /// let context = glutin::ContextBuilder::new()
+    ///     .build_headless(&el)
+    ///     .unwrap();
+    /// if let Some(display) = context.egl_display() {
+    ///     println!("EGL vendor: {:?}", display.vendor());
+    ///     println!("Driver: {:?}", display.driver_name());
+    /// }
+    /// # }
+    /// ```
+    fn egl_display(&self) -> Option<EglDisplay>;
+
+    /// Releases the context from the calling thread, and returns what was
+    /// current before, so that it can be made current again with
+    /// `PreviousCurrent::restore`.
+    ///
+    /// This captures the EGL and OsMesa contexts that are current on the
+    /// thread, whether they belong to this context or not, and does nothing
+    /// else if this context isn't current. Returns an error with GLX, whose
+    /// current context can't be captured.
+    ///
+    /// ```no_run
+    /// # extern crate glutin;
+    /// # use glutin::os::unix::{ContextExt, OsMesaContextExt};
+    /// # use glutin::ContextTrait;
+    /// # fn main() {
+    /// let context =
+    ///     glutin::Context::new_osmesa(glutin::ContextBuilder::new()).unwrap();
+    /// unsafe {
+    ///     context.make_current().unwrap();
+    ///     let previous = context.make_not_current().unwrap();
+    ///     assert!(!context.is_current());
+    ///
+    ///     // hand the thread over to the host, then later:
+    ///     previous.restore().unwrap();
+    /// }
+    /// assert!(context.is_current());
+    /// # }
+    /// ```
+    unsafe fn make_not_current(&self) -> Result<PreviousCurrent, ContextError>;
+
+    /// Crops the window to `src`, given as `(x, y, width, height)`, and has
+    /// the compositor scale it to `dst_size`, so that the render resolution
+    /// can differ from the size the window is presented at.
+    ///
+    /// This goes through `wp_viewporter` and takes effect on the next call
+    /// to `swap_buffers`. Passing `None` as `src` uses the whole buffer.
+    /// Returns an error if the window isn't a Wayland window, if the
+    /// compositor doesn't support `wp_viewporter`, if a size of `dst_size`
+    /// is 0 or above `i32::MAX`, or if `src` has a negative origin or an
+    /// empty size, in which case the surface is left as is. The viewport is
+    /// removed when the context is dropped.
+    fn set_viewport(
+        &self,
+        src: Option<(f64, f64, f64, f64)>,
+        dst_size: (u32, u32),
+    ) -> Result<(), ContextError>;
+
+    /// Has `callback` run once the compositor is ready for the next frame,
+    /// through a `wl_surface.frame` callback, so that rendering can be paced
+    /// to the display instead of relying on vsync in `swap_buffers`.
+    ///
+    /// The request only reaches the compositor with the next call to
+    /// `swap_buffers`, so call this before swapping. The callback is run
+    /// while winit dispatches Wayland events, that is from within
+    /// `EventsLoop::poll_events` or `EventsLoop::run_forever` on the thread
+    /// of the events loop. It doesn't generate a winit event by itself: use
+    /// an `EventsLoopProxy` to wake up `run_forever` if needed. The callback
+    /// is never run if the window is hidden or fully covered.
+    ///
+    /// Returns an error if the window isn't a Wayland window.
+    fn request_frame_callback<F>(&self, callback: F) -> Result<(), ContextError>
+    where
+        F: FnOnce() + Send + 'static;
+
+    /// Returns the pairs of DRM fourcc and format modifier the compositor
+    /// accepts dmabufs with, as advertised by `zwp_linux_dmabuf_v1`.
+    ///
+    /// Returns an error if the context isn't on Wayland, or if the
+    /// compositor doesn't support version 3 of `zwp_linux_dmabuf_v1`, which
+    /// is the first one to advertise modifiers.
+    fn wayland_dmabuf_formats(&self) -> Result<Vec<(u32, u64)>, ContextError>;
+
+    /// Picks the modifier to allocate dmabufs of the DRM fourcc `format`
+    /// with, so that the compositor can display them and EGL can render to
+    /// them without a copy or a conversion.
+    ///
+    /// The modifier is taken among the ones advertised by the compositor
+    /// that EGL can import with `EglImageContextExt::create_image_from_dmabuf`,
+    /// as given by `EglDisplay::dmabuf_modifiers`. Tiled or compressed
+    /// modifiers are preferred over `DRM_FORMAT_MOD_LINEAR`, which is
+    /// preferred over `DRM_FORMAT_MOD_INVALID`.
+    ///
+    /// This is meant for buffers that are allocated by the application, for
+    /// instance with GBM, and attached to `wl_buffer`s. The buffers of the
+    /// window itself are allocated by the EGL implementation, which already
+    /// negotiates their modifiers.
+    ///
+    /// ```no_run
+    /// # extern crate glutin;
+    /// # use glutin::os::unix::ContextExt;
+    /// # fn main() {
+    /// # let el = glutin::EventsLoop::new();
+    /// # let context = glutin::ContextBuilder::new()
+    /// #     .build_combined(glutin::WindowBuilder::new(), &el)
+    /// #     .unwrap();
+    /// // DRM_FORMAT_XRGB8888
+    /// let format = 0x3432_5258;
+    /// match context.context().negotiate_dmabuf_modifier(format) {
+    ///     Ok(modifier) => println!("Allocating with {:#x}", modifier),
+    ///     Err(err) => println!("No modifier in common: {}", err),
+    /// }
+    /// # }
+    /// ```
+    fn negotiate_dmabuf_modifier(
+        &self,
+        format: u32,
+    ) -> Result<u64, ContextError>;
+}
+
+impl ContextExt for Context {
+    #[inline]
+    fn max_buffer_size(&self) -> Option<(u32, u32)> {
+        self.context.max_buffer_size()
+    }
+
+    #[inline]
+    fn is_robust(&self) -> Option<bool> {
+        self.context.is_robust()
+    }
+
+    #[inline]
+    fn resize_pbuffer(
+        &mut self,
+        width: u32,
+        height: u32,
+    ) -> Result<(), ContextError> {
+        self.context.resize_pbuffer(width, height)
+    }
+
+    #[inline]
+    fn gl_version(&self) -> Option<(u8, u8)> {
+        self.context.get_gl_version()
+    }
+
+    #[inline]
+    fn swap_buffers_with_damage(
+        &self,
+        rects: &[Rect],
+    ) -> Result<(), ContextError> {
+        self.context.swap_buffers_with_damage(rects)
+    }
+
+    #[inline]
+    fn swap_buffers_with_damage_coalesced(
+        &self,
+        rects: &[Rect],
+    ) -> Result<(), ContextError> {
+        self.context.swap_buffers_with_damage_coalesced(rects)
+    }
+
+    #[inline]
+    fn damage_is_effective(&self) -> bool {
+        self.context.damage_is_effective()
+    }
+
+    #[inline]
+    fn was_downgraded(&self) -> Option<bool> {
+        self.context.was_downgraded()
+    }
+
+    #[inline]
+    fn swap_interval_range(&self) -> Option<RangeInclusive<i32>> {
+        self.context.swap_interval_range()
+    }
+
+    #[inline]
+    fn set_swap_interval(&self, interval: i32) -> Result<(), ContextError> {
+        self.context.set_swap_interval(interval)
+    }
+
+    #[inline]
+    unsafe fn make_not_current(&self) -> Result<PreviousCurrent, ContextError> {
+        self.context.make_not_current()
+    }
+
+    #[inline]
+    fn egl_display(&self) -> Option<EglDisplay> {
+        self.context.egl_display()
+    }
+
+    #[inline]
+    fn set_viewport(
+        &self,
+        src: Option<(f64, f64, f64, f64)>,
+        dst_size: (u32, u32),
+    ) -> Result<(), ContextError> {
+        self.context.set_viewport(src, dst_size)
+    }
+
+    #[inline]
+    fn request_frame_callback<F>(&self, callback: F) -> Result<(), ContextError>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.context.request_frame_callback(Box::new(callback))
+    }
+
+    #[inline]
+    fn wayland_dmabuf_formats(&self) -> Result<Vec<(u32, u64)>, ContextError> {
+        self.context.dmabuf_formats()
+    }
+
+    #[inline]
+    fn negotiate_dmabuf_modifier(
+        &self,
+        format: u32,
+    ) -> Result<u64, ContextError> {
+        self.context.negotiate_dmabuf_modifier(format)
+    }
+}
+
 impl ContextTraitExt for Context {
     type Handle = RawHandle;
 
@@ -34,4 +460,26 @@ impl ContextTraitExt for Context {
     unsafe fn get_egl_display(&self) -> Option<*const raw::c_void> {
         self.context.get_egl_display()
     }
+
+    #[inline]
+    unsafe fn get_egl_surface(&self) -> Option<*const raw::c_void> {
+        self.context.get_egl_surface()
+    }
+
+    #[inline]
+    unsafe fn get_egl_config(&self) -> Option<*const raw::c_void> {
+        self.context.get_egl_config()
+    }
+}
+
+impl RawContextExt for Context {
+    #[inline]
+    unsafe fn new_raw_egl(
+        display: *const raw::c_void,
+        context: *const raw::c_void,
+        config: *const raw::c_void,
+    ) -> Result<Self, CreationError> {
+        platform::Context::new_raw_egl(display, context, config)
+            .map(|context| Context::from_platform(context, None))
+    }
 }
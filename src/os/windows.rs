@@ -6,12 +6,17 @@ pub use winit::os::windows::{
 };
 
 pub use api::egl::ffi::EGLContext;
+#[cfg(feature = "osmesa")]
+pub use api::osmesa::OsMesaColorFormat;
+#[cfg(feature = "osmesa")]
+pub use os::OsMesaContextExt;
 pub use platform::RawHandle;
 
 use std::os::raw;
 
-use os::ContextTraitExt;
-use Context;
+use os::{ContextTraitExt, RawContextExt};
+use platform;
+use {Context, CreationError};
 
 impl ContextTraitExt for Context {
     type Handle = RawHandle;
@@ -25,4 +30,26 @@ impl ContextTraitExt for Context {
     unsafe fn get_egl_display(&self) -> Option<*const raw::c_void> {
         self.context.get_egl_display()
     }
+
+    #[inline]
+    unsafe fn get_egl_surface(&self) -> Option<*const raw::c_void> {
+        self.context.get_egl_surface()
+    }
+
+    #[inline]
+    unsafe fn get_egl_config(&self) -> Option<*const raw::c_void> {
+        self.context.get_egl_config()
+    }
+}
+
+impl RawContextExt for Context {
+    #[inline]
+    unsafe fn new_raw_egl(
+        display: *const raw::c_void,
+        context: *const raw::c_void,
+        config: *const raw::c_void,
+    ) -> Result<Self, CreationError> {
+        platform::Context::new_raw_egl(display, context, config)
+            .map(|context| Context::from_platform(context, None))
+    }
 }
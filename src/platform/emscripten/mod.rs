@@ -1,14 +1,24 @@
 #![cfg(target_os = "emscripten")]
 
 use std::ffi::CString;
+use std::fmt;
 
-use {Api, ContextError, CreationError, GlAttributes, GlRequest};
+use {
+    Api, ConfigCaveat, ContextError, CreationError, GlAttributes, GlRequest,
+};
 use {PixelFormat, PixelFormatRequirements};
 
+use registry::{self, Backend};
+
 use winit;
 
 mod ffi;
 
+#[inline]
+pub fn has_current_context() -> bool {
+    unsafe { ffi::emscripten_webgl_get_current_context() > 0 }
+}
+
 pub enum Context {
     Window(ffi::EMSCRIPTEN_WEBGL_CONTEXT_HANDLE),
     WindowedContext(winit::Window, ffi::EMSCRIPTEN_WEBGL_CONTEXT_HANDLE),
@@ -61,6 +71,7 @@ impl Context {
 
         // TODO: emscripten_set_webglcontextrestored_callback
 
+        registry::register(Backend::WebGl);
         Ok((window, Context::Window(context)))
     }
 
@@ -96,6 +107,13 @@ impl Context {
         }
     }
 
+    #[inline]
+    pub fn reinitialize(&mut self) -> Result<(), ContextError> {
+        Err(ContextError::OsError(
+            "Context reinitialization is only supported with EGL".to_string(),
+        ))
+    }
+
     #[inline]
     pub unsafe fn make_current(&self) -> Result<(), ContextError> {
         // TOOD: check if == EMSCRIPTEN_RESULT
@@ -132,11 +150,17 @@ impl Context {
         Api::WebGl
     }
 
+    #[inline]
+    pub fn framebuffer_size(&self) -> Option<(u32, u32)> {
+        None
+    }
+
     #[inline]
     pub fn get_pixel_format(&self) -> PixelFormat {
         // FIXME: this is a dummy pixel format
         PixelFormat {
             hardware_accelerated: true,
+            caveat: ConfigCaveat::None,
             color_bits: 24,
             alpha_bits: 8,
             depth_bits: 24,
@@ -145,6 +169,10 @@ impl Context {
             double_buffer: true,
             multisampling: None,
             srgb: true,
+            luminance_bits: 0,
+            accum_bits: 0,
+            native_visual_id: None,
+            recordable: false,
         }
     }
 
@@ -157,8 +185,17 @@ impl Context {
     }
 }
 
+impl fmt::Debug for Context {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WebGl")
+            .field("api", &Api::WebGl)
+            .finish()
+    }
+}
+
 impl Drop for Context {
     fn drop(&mut self) {
+        registry::unregister(Backend::WebGl);
         unsafe {
             ffi::emscripten_webgl_destroy_context(self.raw_handle());
         }
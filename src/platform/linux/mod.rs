@@ -6,42 +6,151 @@
     target_os = "openbsd"
 ))]
 
+#[cfg(feature = "winit")]
 use self::x11::X11Context;
 use api::egl;
+#[cfg(feature = "winit")]
 use api::glx;
-use {
-    ContextError, CreationError, GlAttributes, PixelFormat,
-    PixelFormatRequirements,
-};
+#[cfg(feature = "winit")]
+use PixelFormat;
+use {ContextError, CreationError, GlAttributes, PixelFormatRequirements};
 
+#[cfg(feature = "winit")]
 use winit;
+#[cfg(feature = "winit")]
 use winit::os::unix::EventsLoopExt;
 
+#[cfg(feature = "winit")]
 mod wayland;
+#[cfg(feature = "winit")]
 mod x11;
 use api::osmesa;
 
+use std::fmt;
+use std::ops::RangeInclusive;
 use std::os::raw;
+use std::os::unix::io::RawFd;
 
 /// Context handles available on Unix-like platforms.
 #[derive(Clone, Debug)]
 pub enum RawHandle {
+    #[cfg(feature = "winit")]
     Glx(glx::ffi::GLXContext),
     Egl(egl::ffi::EGLContext),
 }
 
+/// Returns true if a GLX, EGL or OsMesa context is current on the calling
+/// thread.
+#[inline]
+pub fn has_current_context() -> bool {
+    #[cfg(feature = "winit")]
+    {
+        if glx::has_current_context() {
+            return true;
+        }
+    }
+
+    egl::has_current_context() || osmesa::has_current_context()
+}
+
+/// The EGL and OsMesa contexts that were current on a thread, as returned by
+/// `ContextExt::make_not_current`.
+///
+/// Unlike a guard, it doesn't borrow anything, so it can be stored or handed
+/// over an FFI boundary. Nothing happens when it is dropped: call `restore`
+/// to make the contexts current again.
+///
+/// It is neither `Send` nor `Sync`: restoring it on another thread would
+/// bind the OsMesa buffer there, while the context may still be current on
+/// the thread it was captured on.
+#[derive(Debug)]
+pub struct PreviousCurrent {
+    egl: Option<egl::CurrentBinding>,
+    osmesa: Option<osmesa::CurrentBinding>,
+}
+
+impl PreviousCurrent {
+    fn get() -> PreviousCurrent {
+        PreviousCurrent {
+            egl: egl::CurrentBinding::get(),
+            osmesa: osmesa::CurrentBinding::get(),
+        }
+    }
+
+    /// Makes the contexts current again on the calling thread, along with
+    /// the surfaces or buffers they were current with.
+    ///
+    /// The contexts and surfaces must still be alive, and the contexts must
+    /// not be current on another thread.
+    pub unsafe fn restore(self) -> Result<(), ContextError> {
+        if let Some(ref egl) = self.egl {
+            egl.restore()?;
+        }
+        if let Some(ref osmesa) = self.osmesa {
+            osmesa.restore()?;
+        }
+        Ok(())
+    }
+}
+
 pub enum ContextType {
+    #[cfg(feature = "winit")]
     X11,
+    #[cfg(feature = "winit")]
     Wayland,
     OsMesa,
+    Egl,
 }
 
 pub enum Context {
+    #[cfg(feature = "winit")]
     WindowedX11(x11::Context),
+    #[cfg(feature = "winit")]
     HeadlessX11(winit::Window, x11::Context),
+    #[cfg(feature = "winit")]
     WindowedWayland(wayland::Context),
+    #[cfg(feature = "winit")]
     HeadlessWayland(winit::Window, wayland::Context),
     OsMesa(osmesa::OsMesaContext),
+    /// An EGL context that isn't tied to any window system, either created
+    /// from an EGL device or outside of glutin.
+    Egl(egl::Context),
+}
+
+/// Returns true if EGL can be loaded and initialized on the display of `el`.
+///
+/// Contexts can't be created on Wayland without EGL, and creating one
+/// returns `CreationError::NoHardwareAcceleration` instead. This lets the
+/// caller pick a software path, like OsMesa, up front. On Wayland, the
+/// default display is probed, since the events loop doesn't expose its own.
+#[cfg(feature = "winit")]
+pub fn is_egl_available(el: &winit::EventsLoop) -> bool {
+    if el.is_wayland() {
+        wayland_client::egl::is_available()
+            && egl::is_available(egl::NativeDisplay::Wayland(None))
+    } else {
+        match el.get_xlib_xconnection() {
+            Some(xconn) => egl::is_available(egl::NativeDisplay::X11(Some(
+                xconn.display as *const _,
+            ))),
+            None => false,
+        }
+    }
+}
+
+impl fmt::Debug for Context {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            #[cfg(feature = "winit")]
+            Context::WindowedX11(ref ctx)
+            | Context::HeadlessX11(_, ref ctx) => ctx.fmt(f),
+            #[cfg(feature = "winit")]
+            Context::WindowedWayland(ref ctx)
+            | Context::HeadlessWayland(_, ref ctx) => ctx.fmt(f),
+            Context::OsMesa(ref ctx) => ctx.fmt(f),
+            Context::Egl(ref ctx) => ctx.fmt(f),
+        }
+    }
 }
 
 impl Context {
@@ -58,6 +167,7 @@ impl Context {
                         return Err(CreationError::PlatformSpecific(msg.into()));
                     }
                 },
+                #[cfg(feature = "winit")]
                 ContextType::X11 => match *c {
                     Context::WindowedX11(_) | Context::HeadlessX11(_, _) => {
                         Ok(())
@@ -67,6 +177,7 @@ impl Context {
                         return Err(CreationError::PlatformSpecific(msg.into()));
                     }
                 },
+                #[cfg(feature = "winit")]
                 ContextType::Wayland => match *c {
                     Context::WindowedWayland(_)
                     | Context::HeadlessWayland(_, _) => Ok(()),
@@ -75,6 +186,13 @@ impl Context {
                         return Err(CreationError::PlatformSpecific(msg.into()));
                     }
                 },
+                ContextType::Egl => match *c {
+                    Context::Egl(_) => Ok(()),
+                    _ => {
+                        let msg = "Cannot share an EGL device context with a context of another kind";
+                        return Err(CreationError::PlatformSpecific(msg.into()));
+                    }
+                },
             }
         } else {
             Ok(())
@@ -82,6 +200,7 @@ impl Context {
     }
 
     #[inline]
+    #[cfg(feature = "winit")]
     pub fn new(
         wb: winit::WindowBuilder,
         el: &winit::EventsLoop,
@@ -113,6 +232,7 @@ impl Context {
     }
 
     #[inline]
+    #[cfg(feature = "winit")]
     pub fn new_context(
         el: &winit::EventsLoop,
         pf_reqs: &PixelFormatRequirements,
@@ -143,6 +263,7 @@ impl Context {
     }
 
     #[inline]
+    #[cfg(feature = "winit")]
     pub fn new_separated(
         window: &winit::Window,
         el: &winit::EventsLoop,
@@ -172,74 +293,186 @@ impl Context {
     }
 
     #[inline]
+    pub unsafe fn new_raw_egl(
+        display: *const raw::c_void,
+        context: *const raw::c_void,
+        config: *const raw::c_void,
+    ) -> Result<Self, CreationError> {
+        egl::Context::from_raw(display, context, config).map(Context::Egl)
+    }
+
+    #[inline]
+    #[cfg(feature = "winit")]
     pub fn resize(&self, width: u32, height: u32) {
         match *self {
-            Context::WindowedX11(_) => (),
+            Context::WindowedX11(_) | Context::Egl(_) => (),
             Context::WindowedWayland(ref ctx) => ctx.resize(width, height),
             _ => unreachable!(),
         }
     }
 
+    #[inline]
+    pub fn reinitialize(&mut self) -> Result<(), ContextError> {
+        match *self {
+            #[cfg(feature = "winit")]
+            Context::WindowedX11(ref mut ctx)
+            | Context::HeadlessX11(_, ref mut ctx) => ctx.reinitialize(),
+            #[cfg(feature = "winit")]
+            Context::WindowedWayland(ref mut ctx)
+            | Context::HeadlessWayland(_, ref mut ctx) => ctx.reinitialize(),
+            Context::Egl(ref mut ctx) => ctx.reinitialize(),
+            Context::OsMesa(_) => Err(ContextError::OsError(
+                "Context reinitialization is only supported with EGL"
+                    .to_string(),
+            )),
+        }
+    }
+
     #[inline]
     pub unsafe fn make_current(&self) -> Result<(), ContextError> {
         match *self {
+            #[cfg(feature = "winit")]
             Context::WindowedX11(ref ctx)
             | Context::HeadlessX11(_, ref ctx) => ctx.make_current(),
+            #[cfg(feature = "winit")]
             Context::WindowedWayland(ref ctx)
             | Context::HeadlessWayland(_, ref ctx) => ctx.make_current(),
             Context::OsMesa(ref ctx) => ctx.make_current(),
+            Context::Egl(ref ctx) => ctx.make_current(),
         }
     }
 
     #[inline]
     pub fn is_current(&self) -> bool {
         match *self {
+            #[cfg(feature = "winit")]
             Context::WindowedX11(ref ctx)
             | Context::HeadlessX11(_, ref ctx) => ctx.is_current(),
+            #[cfg(feature = "winit")]
             Context::WindowedWayland(ref ctx)
             | Context::HeadlessWayland(_, ref ctx) => ctx.is_current(),
             Context::OsMesa(ref ctx) => ctx.is_current(),
+            Context::Egl(ref ctx) => ctx.is_current(),
         }
     }
 
+    pub unsafe fn make_not_current(
+        &self,
+    ) -> Result<PreviousCurrent, ContextError> {
+        let previous = PreviousCurrent::get();
+        match *self {
+            #[cfg(feature = "winit")]
+            Context::WindowedX11(ref ctx)
+            | Context::HeadlessX11(_, ref ctx) => ctx.make_not_current(),
+            #[cfg(feature = "winit")]
+            Context::WindowedWayland(ref ctx)
+            | Context::HeadlessWayland(_, ref ctx) => ctx.make_not_current(),
+            Context::OsMesa(ref ctx) => ctx.make_not_current(),
+            Context::Egl(ref ctx) => ctx.make_not_current(),
+        }?;
+        Ok(previous)
+    }
+
     #[inline]
     pub fn get_proc_address(&self, addr: &str) -> *const () {
         match *self {
+            #[cfg(feature = "winit")]
             Context::WindowedX11(ref ctx)
             | Context::HeadlessX11(_, ref ctx) => ctx.get_proc_address(addr),
+            #[cfg(feature = "winit")]
             Context::WindowedWayland(ref ctx)
             | Context::HeadlessWayland(_, ref ctx) => {
                 ctx.get_proc_address(addr)
             }
             Context::OsMesa(ref ctx) => ctx.get_proc_address(addr),
+            Context::Egl(ref ctx) => ctx.get_proc_address(addr),
         }
     }
 
+    #[cfg(feature = "winit")]
     #[inline]
     pub fn swap_buffers(&self) -> Result<(), ContextError> {
         match *self {
             Context::WindowedX11(ref ctx) => ctx.swap_buffers(),
             Context::WindowedWayland(ref ctx) => ctx.swap_buffers(),
+            Context::Egl(ref ctx) => ctx.swap_buffers(),
             _ => unreachable!(),
         }
     }
 
+    #[inline]
+    pub fn swap_buffers_with_damage(
+        &self,
+        rects: &[egl::Rect],
+    ) -> Result<(), ContextError> {
+        match *self {
+            #[cfg(feature = "winit")]
+            Context::WindowedX11(ref ctx) => {
+                ctx.swap_buffers_with_damage(rects)
+            }
+            #[cfg(feature = "winit")]
+            Context::WindowedWayland(ref ctx) => {
+                ctx.swap_buffers_with_damage(rects)
+            }
+            Context::Egl(ref ctx) => ctx.swap_buffers_with_damage(rects),
+            _ => unreachable!(),
+        }
+    }
+
+    #[inline]
+    pub fn swap_buffers_with_damage_coalesced(
+        &self,
+        rects: &[egl::Rect],
+    ) -> Result<(), ContextError> {
+        match *self {
+            #[cfg(feature = "winit")]
+            Context::WindowedX11(ref ctx) => {
+                ctx.swap_buffers_with_damage_coalesced(rects)
+            }
+            #[cfg(feature = "winit")]
+            Context::WindowedWayland(ref ctx) => {
+                ctx.swap_buffers_with_damage_coalesced(rects)
+            }
+            Context::Egl(ref ctx) => {
+                ctx.swap_buffers_with_damage_coalesced(rects)
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[inline]
+    pub fn damage_is_effective(&self) -> bool {
+        match *self {
+            #[cfg(feature = "winit")]
+            Context::WindowedX11(ref ctx) => ctx.damage_is_effective(),
+            #[cfg(feature = "winit")]
+            Context::WindowedWayland(ref ctx) => ctx.damage_is_effective(),
+            Context::Egl(ref ctx) => ctx.damage_is_effective(),
+            _ => false,
+        }
+    }
+
     #[inline]
     pub fn get_api(&self) -> ::Api {
         match *self {
+            #[cfg(feature = "winit")]
             Context::WindowedX11(ref ctx)
             | Context::HeadlessX11(_, ref ctx) => ctx.get_api(),
+            #[cfg(feature = "winit")]
             Context::WindowedWayland(ref ctx)
             | Context::HeadlessWayland(_, ref ctx) => ctx.get_api(),
             Context::OsMesa(ref ctx) => ctx.get_api(),
+            Context::Egl(ref ctx) => ctx.get_api(),
         }
     }
 
+    #[cfg(feature = "winit")]
     #[inline]
     pub fn get_pixel_format(&self) -> PixelFormat {
         match *self {
             Context::WindowedX11(ref ctx) => ctx.get_pixel_format(),
             Context::WindowedWayland(ref ctx) => ctx.get_pixel_format(),
+            Context::Egl(ref ctx) => ctx.get_pixel_format(),
             _ => unreachable!(),
         }
     }
@@ -247,33 +480,81 @@ impl Context {
     #[inline]
     pub unsafe fn raw_handle(&self) -> RawHandle {
         match *self {
+            #[cfg(feature = "winit")]
             Context::WindowedX11(ref ctx)
             | Context::HeadlessX11(_, ref ctx) => match *ctx.raw_handle() {
                 X11Context::Glx(ref ctx) => RawHandle::Glx(ctx.raw_handle()),
                 X11Context::Egl(ref ctx) => RawHandle::Egl(ctx.raw_handle()),
                 X11Context::None => panic!(),
             },
+            #[cfg(feature = "winit")]
             Context::WindowedWayland(ref ctx)
             | Context::HeadlessWayland(_, ref ctx) => {
                 RawHandle::Egl(ctx.raw_handle())
             }
             Context::OsMesa(ref ctx) => RawHandle::Egl(ctx.raw_handle()),
+            Context::Egl(ref ctx) => RawHandle::Egl(ctx.raw_handle()),
         }
     }
 
     #[inline]
     pub unsafe fn get_egl_display(&self) -> Option<*const raw::c_void> {
         match *self {
+            #[cfg(feature = "winit")]
             Context::WindowedX11(ref ctx)
             | Context::HeadlessX11(_, ref ctx) => ctx.get_egl_display(),
+            #[cfg(feature = "winit")]
             Context::WindowedWayland(ref ctx)
             | Context::HeadlessWayland(_, ref ctx) => ctx.get_egl_display(),
+            Context::Egl(ref ctx) => Some(ctx.get_egl_display()),
             _ => None,
         }
     }
 
     #[inline]
-    fn new_osmesa(
+    pub fn egl_display(&self) -> Option<egl::Display> {
+        match *self {
+            #[cfg(feature = "winit")]
+            Context::WindowedX11(ref ctx)
+            | Context::HeadlessX11(_, ref ctx) => ctx.egl_display(),
+            #[cfg(feature = "winit")]
+            Context::WindowedWayland(ref ctx)
+            | Context::HeadlessWayland(_, ref ctx) => ctx.egl_display(),
+            Context::Egl(ref ctx) => Some(ctx.display()),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub unsafe fn get_egl_surface(&self) -> Option<*const raw::c_void> {
+        match *self {
+            #[cfg(feature = "winit")]
+            Context::WindowedX11(ref ctx)
+            | Context::HeadlessX11(_, ref ctx) => ctx.get_egl_surface(),
+            #[cfg(feature = "winit")]
+            Context::WindowedWayland(ref ctx)
+            | Context::HeadlessWayland(_, ref ctx) => ctx.get_egl_surface(),
+            Context::Egl(ref ctx) => Some(ctx.get_egl_surface()),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub unsafe fn get_egl_config(&self) -> Option<*const raw::c_void> {
+        match *self {
+            #[cfg(feature = "winit")]
+            Context::WindowedX11(ref ctx)
+            | Context::HeadlessX11(_, ref ctx) => ctx.get_egl_config(),
+            #[cfg(feature = "winit")]
+            Context::WindowedWayland(ref ctx)
+            | Context::HeadlessWayland(_, ref ctx) => ctx.get_egl_config(),
+            Context::Egl(ref ctx) => Some(ctx.get_egl_config()),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn new_osmesa(
         pf_reqs: &PixelFormatRequirements,
         gl_attr: &GlAttributes<&Context>,
     ) -> Result<Self, CreationError> {
@@ -285,28 +566,727 @@ impl Context {
         osmesa::OsMesaContext::new((1, 1), pf_reqs, &gl_attr)
             .map(|context| Context::OsMesa(context))
     }
+
+    pub fn resize_osmesa(
+        &mut self,
+        width: u32,
+        height: u32,
+    ) -> Result<(), ContextError> {
+        match *self {
+            Context::OsMesa(ref mut ctx) => {
+                ctx.resize(width, height);
+                Ok(())
+            }
+            _ => Err(ContextError::OsError(format!(
+                "Only OsMesa contexts can be resized this way"
+            ))),
+        }
+    }
+
+    pub fn resize_pbuffer(
+        &mut self,
+        width: u32,
+        height: u32,
+    ) -> Result<(), ContextError> {
+        match *self {
+            Context::Egl(ref mut ctx) => ctx.resize_pbuffer(width, height),
+            _ => Err(ContextError::OsError(format!(
+                "Only pbuffer contexts can be resized this way"
+            ))),
+        }
+    }
+
+    pub fn set_row_length_osmesa(
+        &mut self,
+        pixels: u32,
+    ) -> Result<(), ContextError> {
+        match *self {
+            Context::OsMesa(ref mut ctx) => ctx.set_row_length(pixels),
+            _ => Err(ContextError::OsError(format!(
+                "Only OsMesa contexts have a row length"
+            ))),
+        }
+    }
+
+    pub unsafe fn make_current_raw_buffer_osmesa(
+        &self,
+        ptr: *mut u8,
+        width: u32,
+        height: u32,
+        format: osmesa::OsMesaColorFormat,
+    ) -> Result<(), ContextError> {
+        match *self {
+            Context::OsMesa(ref ctx) => {
+                ctx.make_current_raw_buffer(ptr, width, height, format)
+            }
+            _ => Err(ContextError::OsError(format!(
+                "Only OsMesa contexts can render to a raw buffer"
+            ))),
+        }
+    }
+
+    #[inline]
+    fn new_egl_device(
+        device: &egl::Device,
+        pf_reqs: &PixelFormatRequirements,
+        gl_attr: &GlAttributes<&Context>,
+    ) -> Result<Self, CreationError> {
+        Context::is_compatible(&gl_attr.sharing, ContextType::Egl)?;
+        let gl_attr = gl_attr.clone().map_sharing(|ctx| match ctx {
+            &Context::Egl(ref ctx) => ctx,
+            _ => unreachable!(),
+        });
+        let native_display = egl::NativeDisplay::Device(device.raw_device());
+        egl::Context::new(pf_reqs, &gl_attr, native_display)
+            .and_then(|p| p.finish_pbuffer((1, 1)))
+            .map(|context| Context::Egl(context))
+    }
+
+    fn create_image_from_dmabuf(
+        &self,
+        fd: RawFd,
+        format: u32,
+        width: u32,
+        height: u32,
+        stride: u32,
+        modifier: Option<u64>,
+    ) -> Result<egl::EglImage, ContextError> {
+        let display = match unsafe { self.get_egl_display() } {
+            Some(display) => display,
+            None => {
+                return Err(ContextError::OsError(format!(
+                    "dmabuf import requires an EGL context"
+                )))
+            }
+        };
+        unsafe {
+            egl::EglImage::from_dmabuf(
+                display, fd, format, width, height, stride, modifier,
+            )
+        }
+    }
+
+    pub fn is_robust(&self) -> Option<bool> {
+        match *self {
+            #[cfg(feature = "winit")]
+            Context::WindowedX11(ref ctx)
+            | Context::HeadlessX11(_, ref ctx) => ctx.is_robust(),
+            #[cfg(feature = "winit")]
+            Context::WindowedWayland(ref ctx)
+            | Context::HeadlessWayland(_, ref ctx) => Some(ctx.is_robust()),
+            Context::Egl(ref ctx) => Some(ctx.is_robust()),
+            Context::OsMesa(_) => Some(false),
+        }
+    }
+
+    #[inline]
+    pub fn get_gl_version(&self) -> Option<(u8, u8)> {
+        match *self {
+            #[cfg(feature = "winit")]
+            Context::WindowedX11(ref ctx)
+            | Context::HeadlessX11(_, ref ctx) => ctx.get_gl_version(),
+            #[cfg(feature = "winit")]
+            Context::WindowedWayland(ref ctx)
+            | Context::HeadlessWayland(_, ref ctx) => ctx.get_gl_version(),
+            Context::Egl(ref ctx) => ctx.get_gl_version(),
+            Context::OsMesa(_) => None,
+        }
+    }
+
+    #[inline]
+    pub fn was_downgraded(&self) -> Option<bool> {
+        match *self {
+            #[cfg(feature = "winit")]
+            Context::WindowedX11(ref ctx)
+            | Context::HeadlessX11(_, ref ctx) => ctx.was_downgraded(),
+            #[cfg(feature = "winit")]
+            Context::WindowedWayland(ref ctx)
+            | Context::HeadlessWayland(_, ref ctx) => {
+                Some(ctx.was_downgraded())
+            }
+            Context::Egl(ref ctx) => Some(ctx.was_downgraded()),
+            Context::OsMesa(_) => None,
+        }
+    }
+
+    #[cfg_attr(not(feature = "winit"), allow(unused_variables))]
+    pub fn set_viewport(
+        &self,
+        src: Option<(f64, f64, f64, f64)>,
+        dst_size: (u32, u32),
+    ) -> Result<(), ContextError> {
+        match *self {
+            #[cfg(feature = "winit")]
+            Context::WindowedWayland(ref ctx) => {
+                ctx.set_viewport(src, dst_size)
+            }
+            _ => Err(ContextError::OsError(format!(
+                "Viewports are only supported on Wayland windows"
+            ))),
+        }
+    }
+
+    pub fn dmabuf_formats(&self) -> Result<Vec<(u32, u64)>, ContextError> {
+        match *self {
+            #[cfg(feature = "winit")]
+            Context::WindowedWayland(ref ctx)
+            | Context::HeadlessWayland(_, ref ctx) => ctx.dmabuf_formats(),
+            _ => Err(ContextError::OsError(format!(
+                "dmabuf formats can only be queried on Wayland"
+            ))),
+        }
+    }
+
+    #[cfg_attr(not(feature = "winit"), allow(unused_variables))]
+    pub fn negotiate_dmabuf_modifier(
+        &self,
+        format: u32,
+    ) -> Result<u64, ContextError> {
+        match *self {
+            #[cfg(feature = "winit")]
+            Context::WindowedWayland(ref ctx)
+            | Context::HeadlessWayland(_, ref ctx) => {
+                ctx.negotiate_dmabuf_modifier(format)
+            }
+            _ => Err(ContextError::OsError(format!(
+                "dmabuf modifiers can only be negotiated on Wayland"
+            ))),
+        }
+    }
+
+    #[cfg_attr(not(feature = "winit"), allow(unused_variables))]
+    pub fn request_frame_callback(
+        &self,
+        callback: Box<FnOnce() + Send>,
+    ) -> Result<(), ContextError> {
+        match *self {
+            #[cfg(feature = "winit")]
+            Context::WindowedWayland(ref ctx) => {
+                ctx.request_frame_callback(callback)
+            }
+            _ => Err(ContextError::OsError(format!(
+                "Frame callbacks are only supported on Wayland windows"
+            ))),
+        }
+    }
+
+    pub fn framebuffer_size(&self) -> Option<(u32, u32)> {
+        match *self {
+            #[cfg(feature = "winit")]
+            Context::WindowedX11(ref ctx)
+            | Context::HeadlessX11(_, ref ctx) => ctx.framebuffer_size(),
+            #[cfg(feature = "winit")]
+            Context::WindowedWayland(ref ctx)
+            | Context::HeadlessWayland(_, ref ctx) => ctx.framebuffer_size(),
+            Context::Egl(ref ctx) => ctx.surface_size(),
+            Context::OsMesa(ref ctx) => Some(ctx.get_dimensions()),
+        }
+    }
+
+    pub fn create_render_to_texture<'a>(
+        &'a self,
+        width: u32,
+        height: u32,
+        largest: bool,
+    ) -> Result<egl::RenderToTexture<'a>, ContextError> {
+        unsafe {
+            match (self.raw_handle(), self.get_egl_config()) {
+                (RawHandle::Egl(context), Some(config)) => {
+                    egl::RenderToTexture::new(
+                        self.get_egl_display().unwrap(),
+                        context,
+                        config,
+                        width,
+                        height,
+                        largest,
+                    )
+                }
+                _ => Err(ContextError::OsError(format!(
+                    "Rendering to a texture requires an EGL context"
+                ))),
+            }
+        }
+    }
+
+    pub fn swap_behavior(&self) -> Result<bool, ContextError> {
+        match *self {
+            #[cfg(feature = "winit")]
+            Context::WindowedX11(ref ctx)
+            | Context::HeadlessX11(_, ref ctx) => ctx.swap_behavior(),
+            #[cfg(feature = "winit")]
+            Context::WindowedWayland(ref ctx)
+            | Context::HeadlessWayland(_, ref ctx) => ctx.swap_behavior(),
+            Context::Egl(ref ctx) => ctx.swap_behavior(),
+            Context::OsMesa(_) => Err(ContextError::OsError(format!(
+                "The swap behavior can only be queried with EGL"
+            ))),
+        }
+    }
+
+    pub fn set_swap_behavior(
+        &self,
+        preserved: bool,
+    ) -> Result<(), ContextError> {
+        match *self {
+            #[cfg(feature = "winit")]
+            Context::WindowedX11(ref ctx)
+            | Context::HeadlessX11(_, ref ctx) => {
+                ctx.set_swap_behavior(preserved)
+            }
+            #[cfg(feature = "winit")]
+            Context::WindowedWayland(ref ctx)
+            | Context::HeadlessWayland(_, ref ctx) => {
+                ctx.set_swap_behavior(preserved)
+            }
+            Context::Egl(ref ctx) => ctx.set_swap_behavior(preserved),
+            Context::OsMesa(_) => Err(ContextError::OsError(format!(
+                "The swap behavior can only be set with EGL"
+            ))),
+        }
+    }
+
+    #[inline]
+    pub fn osmesa_framebuffer(&self) -> Option<(&[u32], u32)> {
+        match *self {
+            Context::OsMesa(ref ctx) => {
+                Some((ctx.get_framebuffer(), ctx.get_row_length()))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn max_buffer_size(&self) -> Option<(u32, u32)> {
+        match *self {
+            Context::OsMesa(ref ctx) => ctx.max_buffer_size(),
+            _ => unsafe {
+                match (self.get_egl_display(), self.get_egl_config()) {
+                    (Some(display), Some(config)) => {
+                        egl::max_pbuffer_size(display, config)
+                    }
+                    _ => None,
+                }
+            },
+        }
+    }
+
+    pub fn swap_interval_range(&self) -> Option<RangeInclusive<i32>> {
+        unsafe {
+            match (self.get_egl_display(), self.get_egl_config()) {
+                (Some(display), Some(config)) => {
+                    egl::swap_interval_range(display, config)
+                }
+                _ => None,
+            }
+        }
+    }
+
+    pub fn set_swap_interval(&self, interval: i32) -> Result<(), ContextError> {
+        unsafe {
+            match (self.get_egl_display(), self.get_egl_config()) {
+                (Some(display), Some(config)) => {
+                    if !self.is_current() {
+                        return Err(ContextError::NotCurrentOnThisThread);
+                    }
+                    egl::set_swap_interval(display, config, interval)
+                }
+                _ => Err(ContextError::OsError(format!(
+                    "The swap interval can only be set with EGL"
+                ))),
+            }
+        }
+    }
+
+    unsafe fn bind_surface(
+        &mut self,
+        native_window: *const raw::c_void,
+    ) -> Result<(), ContextError> {
+        match *self {
+            Context::Egl(ref mut ctx) => ctx.bind_surface(native_window),
+            _ => Err(ContextError::OsError(format!(
+                "Only contexts that aren't tied to a window can bind surfaces"
+            ))),
+        }
+    }
+
+    unsafe fn bind_client_buffer(
+        &mut self,
+        buffer_type: u32,
+        buffer: *const raw::c_void,
+        size: Option<(u32, u32)>,
+    ) -> Result<(), ContextError> {
+        match *self {
+            Context::Egl(ref mut ctx) => {
+                ctx.bind_client_buffer(buffer_type, buffer, size)
+            }
+            _ => Err(ContextError::OsError(format!(
+                "Only contexts that aren't tied to a window can bind surfaces"
+            ))),
+        }
+    }
+
+    unsafe fn unbind_surface(&mut self) -> Result<(), ContextError> {
+        match *self {
+            Context::Egl(ref mut ctx) => ctx.unbind_surface(),
+            _ => Err(ContextError::OsError(format!(
+                "Only contexts that aren't tied to a window can unbind surfaces"
+            ))),
+        }
+    }
+
+    fn export_texture_as_dmabuf(
+        &self,
+        texture: u32,
+    ) -> Result<egl::DmabufHandle, ContextError> {
+        let display = match unsafe { self.get_egl_display() } {
+            Some(display) => display,
+            None => {
+                return Err(ContextError::OsError(format!(
+                    "dmabuf export requires an EGL context"
+                )))
+            }
+        };
+        let context = match unsafe { self.raw_handle() } {
+            RawHandle::Egl(context) => context,
+            #[cfg(feature = "winit")]
+            RawHandle::Glx(_) => unreachable!(),
+        };
+        unsafe {
+            egl::EglImage::from_texture_2d(display, context, texture)?
+                .export_dmabuf()
+        }
+    }
 }
 
-pub trait OsMesaContextExt {
-    fn new_osmesa(cb: crate::ContextBuilder) -> Result<Self, CreationError>
+pub trait EglDeviceContextExt {
+    /// Builds a headless context on the given EGL device, without going
+    /// through any display server.
+    ///
+    /// The context renders into a 1x1 pbuffer, so you will want to render
+    /// into your own framebuffer objects.
+    ///
+    /// # Example
+    ///
+    /// Creating one context per worker thread, all sharing with a root
+    /// context:
+    ///
+    /// ```no_run
+    /// # extern crate glutin;
+    /// # use glutin::ContextTrait;
+    /// # use glutin::os::unix::{EglDevice, EglDeviceContextExt};
+    /// # use std::sync::Arc;
+    /// # use std::thread;
+    /// # fn main() {
+    /// let device = EglDevice::query_all().into_iter().next().unwrap();
+    /// let root = Arc::new(
+    ///     glutin::Context::new_egl_device(
+    ///         glutin::ContextBuilder::new(),
+    ///         &device,
+    ///     )
+    ///     .unwrap(),
+    /// );
+    ///
+    /// let workers: Vec<_> = (0..16)
+    ///     .map(|_| {
+    ///         let root = root.clone();
+    ///         let device = device.clone();
+    ///         thread::spawn(move || {
+    ///             let cb =
+    ///                 glutin::ContextBuilder::new().with_shared_lists(&root);
+    ///             let context =
+    ///                 glutin::Context::new_egl_device(cb, &device).unwrap();
+    ///             unsafe { context.make_current().unwrap() };
+    ///             assert!(context.is_current());
+    ///             assert!(context.shares_with(&root));
+    ///         })
+    ///     })
+    ///     .collect();
+    /// for worker in workers {
+    ///     worker.join().unwrap();
+    /// }
+    /// # }
+    /// ```
+    fn new_egl_device(
+        cb: crate::ContextBuilder,
+        device: &egl::Device,
+    ) -> Result<Self, CreationError>
     where
         Self: Sized;
 }
 
-impl OsMesaContextExt for crate::Context {
-    /// Builds the given OsMesa context.
-    ///
-    /// Errors can occur if the OpenGL context could not be created. This
-    /// generally happens because the underlying platform doesn't support a
-    /// requested feature.
+impl EglDeviceContextExt for crate::Context {
     #[inline]
-    fn new_osmesa(cb: crate::ContextBuilder) -> Result<Self, CreationError>
+    fn new_egl_device(
+        cb: crate::ContextBuilder,
+        device: &egl::Device,
+    ) -> Result<Self, CreationError>
     where
         Self: Sized,
     {
         let crate::ContextBuilder { pf_reqs, gl_attr } = cb;
+        let sharing = gl_attr.sharing;
         let gl_attr = gl_attr.map_sharing(|ctx| &ctx.context);
-        Context::new_osmesa(&pf_reqs, &gl_attr)
-            .map(|context| crate::Context { context })
+        Context::new_egl_device(device, &pf_reqs, &gl_attr)
+            .map(|context| crate::Context::from_platform(context, sharing))
+    }
+}
+
+pub trait EglImageContextExt {
+    fn create_image_from_dmabuf(
+        &self,
+        fd: RawFd,
+        format: u32,
+        width: u32,
+        height: u32,
+        stride: u32,
+        modifier: Option<u64>,
+    ) -> Result<egl::EglImage, ContextError>;
+
+    fn export_texture_as_dmabuf(
+        &self,
+        texture: u32,
+    ) -> Result<egl::DmabufHandle, ContextError>;
+}
+
+impl EglImageContextExt for crate::Context {
+    /// Imports a single-plane dmabuf, like a frame from a hardware video
+    /// decoder, as an `EglImage` without copying it.
+    ///
+    /// `format` is a DRM fourcc code and `stride` is in bytes. Bind the
+    /// image to a texture with `EglImage::bind_to_texture_2d`.
+    ///
+    /// Fails if this isn't an EGL context or if the display doesn't support
+    /// `EGL_EXT_image_dma_buf_import`. Passing a `modifier` also requires
+    /// `EGL_EXT_image_dma_buf_import_modifiers`.
+    #[inline]
+    fn create_image_from_dmabuf(
+        &self,
+        fd: RawFd,
+        format: u32,
+        width: u32,
+        height: u32,
+        stride: u32,
+        modifier: Option<u64>,
+    ) -> Result<egl::EglImage, ContextError> {
+        self.context.create_image_from_dmabuf(
+            fd, format, width, height, stride, modifier,
+        )
+    }
+
+    /// Exports `texture`, a `GL_TEXTURE_2D` of this context, as dmabufs that
+    /// can be handed to a hardware encoder or to another process without a
+    /// copy.
+    ///
+    /// The returned `DmabufHandle` owns the file descriptors and closes them
+    /// when dropped; use `DmabufHandle::into_fds` to take ownership of them
+    /// instead. The texture must be complete, and rendering to it must be
+    /// finished, e.g. with `glFinish`, before the buffer is read elsewhere.
+    ///
+    /// Fails if this isn't an EGL context or if the display doesn't support
+    /// `EGL_KHR_gl_texture_2D_image` and `EGL_MESA_image_dma_buf_export`.
+    #[inline]
+    fn export_texture_as_dmabuf(
+        &self,
+        texture: u32,
+    ) -> Result<egl::DmabufHandle, ContextError> {
+        self.context.export_texture_as_dmabuf(texture)
+    }
+}
+
+/// Lets an EGL context outlive the windows it renders to.
+///
+/// This is only available for contexts that aren't tied to a window, like
+/// the ones created with `RawContextExt::new_raw_egl` or
+/// `EglDeviceContextExt::new_egl_device`. Such a context can be made
+/// current without any surface if `EGL_KHR_surfaceless_context` is
+/// supported, so a window can be destroyed and recreated, possibly from
+/// another thread, while the context and its GL objects stay alive.
+pub trait EglSurfaceContextExt {
+    /// Creates a window surface for `native_window`, like a `wl_egl_window`
+    /// or an X11 window, and renders to it from now on. The previous
+    /// surface of the context, if any, is destroyed.
+    ///
+    /// The window must be on the display of the context and must outlive
+    /// the surface.
+    unsafe fn bind_surface(
+        &mut self,
+        native_window: *const raw::c_void,
+    ) -> Result<(), ContextError>;
+
+    /// Creates a pbuffer surface that renders into `buffer`, a buffer
+    /// allocated by another client API, through
+    /// `eglCreatePbufferFromClientBuffer`, and renders to it from now on.
+    /// The previous surface of the context, if any, is destroyed.
+    ///
+    /// `buffer_type` tells what `buffer` is, like `EGL_OPENVG_IMAGE` or a
+    /// type added by an extension. `size` is passed as `EGL_WIDTH` and
+    /// `EGL_HEIGHT`, which some of these types require. Returns an error if
+    /// the display doesn't support the buffer type, or if the buffer doesn't
+    /// match the config of the context.
+    ///
+    /// The buffer must outlive the surface.
+    ///
+    /// ```no_run
+    /// # extern crate glutin;
+    /// # use glutin::os::unix::{
+    /// #     EglDevice, EglDeviceContextExt, EglSurfaceContextExt,
+    /// # };
+    /// # use std::os::raw::c_void;
+    /// # fn main() {
+    /// # let device = EglDevice::query_all().into_iter().next().unwrap();
+    /// # let cb = glutin::ContextBuilder::new();
+    /// # let mut context =
+    /// #     glutin::Context::new_egl_device(cb, &device).unwrap();
+    /// # let vg_image: *const c_void = std::ptr::null();
+    /// const EGL_OPENVG_IMAGE: u32 = 0x3096;
+    /// unsafe {
+    ///     context
+    ///         .bind_client_buffer(EGL_OPENVG_IMAGE, vg_image, None)
+    ///         .unwrap();
+    /// }
+    /// # }
+    /// ```
+    unsafe fn bind_client_buffer(
+        &mut self,
+        buffer_type: u32,
+        buffer: *const raw::c_void,
+        size: Option<(u32, u32)>,
+    ) -> Result<(), ContextError>;
+
+    /// Destroys the surface of the context, e.g. before its window is
+    /// destroyed. The context stays current without a surface if it was
+    /// current and `EGL_KHR_surfaceless_context` is supported.
+    unsafe fn unbind_surface(&mut self) -> Result<(), ContextError>;
+
+    /// Returns whether the content of the back buffer is preserved by
+    /// `swap_buffers`, as given by `EGL_SWAP_BEHAVIOR`.
+    fn swap_behavior(&self) -> Result<bool, ContextError>;
+
+    /// Sets whether the content of the back buffer is preserved by
+    /// `swap_buffers`, e.g. to only redraw the regions that changed since
+    /// the previous frame. By default, EGL is free to destroy it.
+    ///
+    /// Preserving it fails unless the config of the context has
+    /// `EGL_SWAP_BEHAVIOR_PRESERVED_BIT` in its `EGL_SURFACE_TYPE`. Returns
+    /// an error if the context isn't an EGL context with a surface.
+    fn set_swap_behavior(&self, preserved: bool) -> Result<(), ContextError>;
+
+    /// Creates a `width` by `height` pbuffer that can be rendered to and then
+    /// sampled as a texture of this context, through `eglBindTexImage`.
+    ///
+    /// The context must be current, and its config must support
+    /// `EGL_BIND_TO_TEXTURE_RGBA`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # extern crate glutin;
+    /// # use glutin::ContextTrait;
+    /// # use glutin::os::unix::EglSurfaceContextExt;
+    /// # fn main() {
+    /// # let el = glutin::EventsLoop::new();
+    /// # let context = glutin::ContextBuilder::new()
+    /// #     .build_combined(glutin::WindowBuilder::new(), &el)
+    /// #     .unwrap();
+    /// unsafe { context.make_current().unwrap() };
+    /// let target =
+    ///     context.context().create_render_to_texture(256, 256).unwrap();
+    /// target
+    ///     .with_current(|| {
+    ///         // render the offscreen pass
+    ///     })
+    ///     .unwrap();
+    /// // sample `target.texture_id()` while rendering to the window
+    /// # }
+    /// ```
+    fn create_render_to_texture<'a>(
+        &'a self,
+        width: u32,
+        height: u32,
+    ) -> Result<egl::RenderToTexture<'a>, ContextError>;
+
+    /// Like `create_render_to_texture`, but creates the largest available
+    /// pbuffer through `EGL_LARGEST_PBUFFER` instead of failing when the
+    /// requested size can't be allocated.
+    ///
+    /// The pbuffer is never larger than requested. Its actual size is
+    /// returned by `RenderToTexture::dimensions`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # extern crate glutin;
+    /// # use glutin::ContextTrait;
+    /// # use glutin::os::unix::EglSurfaceContextExt;
+    /// # fn main() {
+    /// # let el = glutin::EventsLoop::new();
+    /// # let context = glutin::ContextBuilder::new()
+    /// #     .build_combined(glutin::WindowBuilder::new(), &el)
+    /// #     .unwrap();
+    /// unsafe { context.make_current().unwrap() };
+    /// let target = context
+    ///     .context()
+    ///     .create_render_to_texture_largest(16384, 16384)
+    ///     .unwrap();
+    /// let (width, height) = target.dimensions();
+    /// assert!(width <= 16384 && height <= 16384);
+    /// # }
+    /// ```
+    fn create_render_to_texture_largest<'a>(
+        &'a self,
+        width: u32,
+        height: u32,
+    ) -> Result<egl::RenderToTexture<'a>, ContextError>;
+}
+
+impl EglSurfaceContextExt for crate::Context {
+    #[inline]
+    unsafe fn bind_surface(
+        &mut self,
+        native_window: *const raw::c_void,
+    ) -> Result<(), ContextError> {
+        self.context.bind_surface(native_window)
+    }
+
+    #[inline]
+    unsafe fn bind_client_buffer(
+        &mut self,
+        buffer_type: u32,
+        buffer: *const raw::c_void,
+        size: Option<(u32, u32)>,
+    ) -> Result<(), ContextError> {
+        self.context.bind_client_buffer(buffer_type, buffer, size)
+    }
+
+    #[inline]
+    unsafe fn unbind_surface(&mut self) -> Result<(), ContextError> {
+        self.context.unbind_surface()
+    }
+
+    #[inline]
+    fn swap_behavior(&self) -> Result<bool, ContextError> {
+        self.context.swap_behavior()
+    }
+
+    #[inline]
+    fn set_swap_behavior(&self, preserved: bool) -> Result<(), ContextError> {
+        self.context.set_swap_behavior(preserved)
+    }
+
+    #[inline]
+    fn create_render_to_texture<'a>(
+        &'a self,
+        width: u32,
+        height: u32,
+    ) -> Result<egl::RenderToTexture<'a>, ContextError> {
+        self.context.create_render_to_texture(width, height, false)
+    }
+
+    #[inline]
+    fn create_render_to_texture_largest<'a>(
+        &'a self,
+        width: u32,
+        height: u32,
+    ) -> Result<egl::RenderToTexture<'a>, ContextError> {
+        self.context.create_render_to_texture(width, height, true)
     }
 }
@@ -1,7 +1,20 @@
 use api::egl::{self, ffi, Context as EglContext};
+use std::fmt;
 use std::os::raw;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use wayland_client::egl as wegl;
+use wayland_client::protocol::wl_surface::RequestsTrait as SurfaceRequests;
+use wayland_client::protocol::{wl_callback, wl_display, wl_surface};
+use wayland_client::{Display, EventQueue, GlobalManager, Proxy};
+use wayland_protocols::unstable::linux_dmabuf::v1::client::zwp_linux_dmabuf_v1::{
+    self, RequestsTrait as DmabufRequests, ZwpLinuxDmabufV1,
+};
+use wayland_protocols::viewporter::client::wp_viewport::{
+    RequestsTrait as ViewportRequests, WpViewport,
+};
+use wayland_protocols::viewporter::client::wp_viewporter::{
+    RequestsTrait as ViewporterRequests, WpViewporter,
+};
 use winit;
 use winit::os::unix::WindowExt;
 use {
@@ -12,6 +25,31 @@ use {
 pub struct Context {
     egl_surface: Arc<wegl::WlEglSurface>,
     context: EglContext,
+    display: Proxy<wl_display::WlDisplay>,
+    surface: Proxy<wl_surface::WlSurface>,
+    viewport: Mutex<Option<Viewport>>,
+}
+
+struct Viewport {
+    viewport: Proxy<WpViewport>,
+    // The registry lives on this queue, so it must outlive the viewport.
+    _event_queue: EventQueue,
+}
+
+// The event queue is only ever touched behind the mutex of the context.
+unsafe impl Send for Viewport {}
+
+impl Drop for Viewport {
+    fn drop(&mut self) {
+        // Also resets the crop and scale of the surface.
+        self.viewport.destroy();
+    }
+}
+
+impl fmt::Debug for Context {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Wayland").field(&self.context).finish()
+    }
 }
 
 impl Context {
@@ -43,6 +81,9 @@ impl Context {
                 return Err(CreationError::NotSupported("Wayland not found"));
             }
         };
+        if egl::EGL.is_none() || !wegl::is_available() {
+            return Err(CreationError::NoHardwareAcceleration);
+        }
         let egl_surface = unsafe {
             wegl::WlEglSurface::new_from_raw(
                 surface as *mut _,
@@ -50,17 +91,26 @@ impl Context {
                 h as i32,
             )
         };
+        let display = window.get_wayland_display().unwrap();
         let context = {
             let gl_attr = gl_attr.clone().map_sharing(|c| &c.context);
-            let native_display = egl::NativeDisplay::Wayland(Some(
-                window.get_wayland_display().unwrap() as *const _,
-            ));
+            let native_display =
+                egl::NativeDisplay::Wayland(Some(display as *const _));
             EglContext::new(pf_reqs, &gl_attr, native_display)
+                .map_err(|err| match err {
+                    CreationError::DisplayUnavailable => {
+                        CreationError::NoHardwareAcceleration
+                    }
+                    err => err,
+                })
                 .and_then(|p| p.finish(egl_surface.ptr() as *const _))?
         };
         let context = Context {
             egl_surface: Arc::new(egl_surface),
             context: context,
+            display: unsafe { Proxy::from_c_ptr(display as *mut _) },
+            surface: unsafe { Proxy::from_c_ptr(surface as *mut _) },
+            viewport: Mutex::new(None),
         };
         Ok(context)
     }
@@ -70,11 +120,199 @@ impl Context {
         self.egl_surface.resize(width as i32, height as i32, 0, 0);
     }
 
+    /// Crops the surface to `src`, given as `(x, y, width, height)` in
+    /// surface coordinates, and has the compositor scale it to `dst_size`,
+    /// through `wp_viewporter`.
+    ///
+    /// The viewporter is bound on the first call. Like the rest of the
+    /// surface state, the viewport only applies on the next swap, and it is
+    /// destroyed along with the context.
+    pub fn set_viewport(
+        &self,
+        src: Option<(f64, f64, f64, f64)>,
+        dst_size: (u32, u32),
+    ) -> Result<(), ContextError> {
+        check_viewport(src, dst_size)?;
+        let mut viewport = self.viewport.lock().unwrap();
+        if viewport.is_none() {
+            *viewport = Some(self.create_viewport()?);
+        }
+        let viewport = &viewport.as_ref().unwrap().viewport;
+
+        let (x, y, width, height) = src.unwrap_or((-1.0, -1.0, -1.0, -1.0));
+        viewport.set_source(x, y, width, height);
+        viewport.set_destination(dst_size.0 as i32, dst_size.1 as i32);
+        Ok(())
+    }
+
+    /// Has `callback` run once the compositor is ready for a new frame,
+    /// through a `wl_surface.frame` callback.
+    ///
+    /// The request is sent along with the next swap. The callback is
+    /// dispatched by the event queue of winit, so it runs on the thread of
+    /// the events loop while it polls for events.
+    pub fn request_frame_callback(
+        &self,
+        callback: Box<FnOnce() + Send>,
+    ) -> Result<(), ContextError> {
+        let mut callback = Some(callback);
+        self.surface
+            .frame(move |cb| {
+                cb.implement(
+                    move |wl_callback::Event::Done { .. }, _| {
+                        if let Some(callback) = callback.take() {
+                            callback();
+                        }
+                    },
+                    (),
+                )
+            })
+            .map(|_| ())
+            .map_err(|_| {
+                ContextError::OsError(format!("The surface was destroyed"))
+            })
+    }
+
+    fn create_viewport(&self) -> Result<Viewport, ContextError> {
+        // Globals are bound on a queue of our own so that the event queue of
+        // winit isn't dispatched behind its back.
+        let (display, mut event_queue) = unsafe {
+            Display::from_external_display(self.display.c_ptr() as *mut _)
+        };
+        let globals = GlobalManager::new(&display);
+        event_queue.sync_roundtrip().map_err(ContextError::IoError)?;
+
+        let viewporter = globals
+            .instantiate_exact::<WpViewporter, _>(1, |v| {
+                v.implement(|_, _| {}, ())
+            })
+            .map_err(|_| {
+                ContextError::OsError(format!(
+                    "wp_viewporter is not supported by the compositor"
+                ))
+            })?;
+        let viewport = viewporter
+            .get_viewport(&self.surface, |v| v.implement(|_, _| {}, ()))
+            .map_err(|_| {
+                ContextError::OsError(format!("wp_viewporter was destroyed"))
+            });
+        viewporter.destroy();
+
+        Ok(Viewport {
+            viewport: viewport?,
+            _event_queue: event_queue,
+        })
+    }
+
+    /// Returns the pairs of DRM fourcc and modifier the compositor accepts
+    /// dmabufs with, as advertised by version 3 of `zwp_linux_dmabuf_v1`.
+    pub fn dmabuf_formats(&self) -> Result<Vec<(u32, u64)>, ContextError> {
+        // like for the viewporter, on a queue of our own
+        let (display, mut event_queue) = unsafe {
+            Display::from_external_display(self.display.c_ptr() as *mut _)
+        };
+        let globals = GlobalManager::new(&display);
+        event_queue.sync_roundtrip().map_err(ContextError::IoError)?;
+
+        let formats = Arc::new(Mutex::new(Vec::new()));
+        let dmabuf = {
+            let formats = formats.clone();
+            globals
+                .instantiate_exact::<ZwpLinuxDmabufV1, _>(3, move |d| {
+                    d.implement(
+                        move |event, _| {
+                            if let zwp_linux_dmabuf_v1::Event::Modifier {
+                                format,
+                                modifier_hi,
+                                modifier_lo,
+                            } = event
+                            {
+                                let modifier = (modifier_hi as u64) << 32
+                                    | modifier_lo as u64;
+                                let mut formats = formats.lock().unwrap();
+                                formats.push((format, modifier));
+                            }
+                        },
+                        (),
+                    )
+                })
+                .map_err(|_| {
+                    ContextError::OsError(format!(
+                        "zwp_linux_dmabuf_v1 version 3 is not supported by the \
+                         compositor"
+                    ))
+                })?
+        };
+
+        // the formats are sent as soon as the global is bound
+        let ret = event_queue.sync_roundtrip();
+        dmabuf.destroy();
+        ret.map_err(ContextError::IoError)?;
+
+        let formats = formats.lock().unwrap().clone();
+        Ok(formats)
+    }
+
+    /// Picks the modifier to allocate dmabufs of the DRM fourcc `format`
+    /// with, among the ones both the compositor and EGL support.
+    ///
+    /// Explicit modifiers are preferred over `DRM_FORMAT_MOD_LINEAR`, which
+    /// is preferred over `DRM_FORMAT_MOD_INVALID`, that is the implicit
+    /// modifier of the driver.
+    pub fn negotiate_dmabuf_modifier(
+        &self,
+        format: u32,
+    ) -> Result<u64, ContextError> {
+        const DRM_FORMAT_MOD_LINEAR: u64 = 0;
+        const DRM_FORMAT_MOD_INVALID: u64 = 0x00ff_ffff_ffff_ffff;
+
+        let egl_modifiers = unsafe {
+            egl::Display::from_raw(self.context.get_egl_display())
+                .dmabuf_modifiers(format)
+        }
+        .ok_or_else(|| {
+            ContextError::OsError(format!(
+                "EGL_EXT_image_dma_buf_import_modifiers is not supported"
+            ))
+        })?;
+
+        self.dmabuf_formats()?
+            .into_iter()
+            .filter(|&(f, modifier)| {
+                f == format
+                    && (modifier == DRM_FORMAT_MOD_INVALID
+                        || egl_modifiers.contains(&modifier))
+            })
+            .map(|(_, modifier)| modifier)
+            .min_by_key(|&modifier| match modifier {
+                DRM_FORMAT_MOD_INVALID => 2,
+                DRM_FORMAT_MOD_LINEAR => 1,
+                _ => 0,
+            })
+            .ok_or_else(|| {
+                ContextError::OsError(format!(
+                    "The compositor and EGL have no modifier in common for \
+                     the format {:#010x}",
+                    format
+                ))
+            })
+    }
+
+    #[inline]
+    pub fn reinitialize(&mut self) -> Result<(), ContextError> {
+        self.context.reinitialize()
+    }
+
     #[inline]
     pub unsafe fn make_current(&self) -> Result<(), ContextError> {
         self.context.make_current()
     }
 
+    #[inline]
+    pub unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        self.context.make_not_current()
+    }
+
     #[inline]
     pub fn is_current(&self) -> bool {
         self.context.is_current()
@@ -90,11 +328,67 @@ impl Context {
         self.context.swap_buffers()
     }
 
+    #[inline]
+    pub fn swap_buffers_with_damage(
+        &self,
+        rects: &[egl::Rect],
+    ) -> Result<(), ContextError> {
+        self.context.swap_buffers_with_damage(rects)
+    }
+
+    #[inline]
+    pub fn swap_buffers_with_damage_coalesced(
+        &self,
+        rects: &[egl::Rect],
+    ) -> Result<(), ContextError> {
+        self.context.swap_buffers_with_damage_coalesced(rects)
+    }
+
+    /// The damage reaches the compositor through `wl_surface.damage`,
+    /// which every compositor supports.
+    #[inline]
+    pub fn damage_is_effective(&self) -> bool {
+        self.context.damage_is_effective()
+    }
+
     #[inline]
     pub fn get_api(&self) -> ::Api {
         self.context.get_api()
     }
 
+    #[inline]
+    pub fn framebuffer_size(&self) -> Option<(u32, u32)> {
+        self.context.surface_size()
+    }
+
+    #[inline]
+    pub fn swap_behavior(&self) -> Result<bool, ContextError> {
+        self.context.swap_behavior()
+    }
+
+    #[inline]
+    pub fn set_swap_behavior(
+        &self,
+        preserved: bool,
+    ) -> Result<(), ContextError> {
+        self.context.set_swap_behavior(preserved)
+    }
+
+    #[inline]
+    pub fn is_robust(&self) -> bool {
+        self.context.is_robust()
+    }
+
+    #[inline]
+    pub fn get_gl_version(&self) -> Option<(u8, u8)> {
+        self.context.get_gl_version()
+    }
+
+    #[inline]
+    pub fn was_downgraded(&self) -> bool {
+        self.context.was_downgraded()
+    }
+
     #[inline]
     pub fn get_pixel_format(&self) -> PixelFormat {
         self.context.get_pixel_format().clone()
@@ -109,4 +403,71 @@ impl Context {
     pub unsafe fn get_egl_display(&self) -> Option<*const raw::c_void> {
         Some(self.context.get_egl_display())
     }
+
+    #[inline]
+    pub fn egl_display(&self) -> Option<egl::Display> {
+        Some(self.context.display())
+    }
+
+    #[inline]
+    pub unsafe fn get_egl_surface(&self) -> Option<*const raw::c_void> {
+        Some(self.context.get_egl_surface())
+    }
+
+    #[inline]
+    pub unsafe fn get_egl_config(&self) -> Option<*const raw::c_void> {
+        Some(self.context.get_egl_config())
+    }
+}
+
+/// Returns an error for the values `wp_viewport` would raise a protocol
+/// error for, which would disconnect winit from the compositor.
+fn check_viewport(
+    src: Option<(f64, f64, f64, f64)>,
+    dst_size: (u32, u32),
+) -> Result<(), ContextError> {
+    let max = i32::max_value() as u32;
+    let valid = |size| size > 0 && size <= max;
+    if !valid(dst_size.0) || !valid(dst_size.1) {
+        return Err(ContextError::OsError(format!(
+            "The viewport destination size must be between 1 and {}, got {:?}",
+            max, dst_size
+        )));
+    }
+    if let Some((x, y, width, height)) = src {
+        if !(x >= 0.0 && y >= 0.0 && width > 0.0 && height > 0.0) {
+            return Err(ContextError::OsError(format!(
+                "The viewport source must have a non-negative origin and a \
+                 positive size, got {:?}",
+                (x, y, width, height)
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_viewport;
+
+    #[test]
+    fn check_viewport_sizes() {
+        let max = i32::max_value() as u32;
+        assert!(check_viewport(None, (1, 1)).is_ok());
+        assert!(check_viewport(None, (max, max)).is_ok());
+        assert!(check_viewport(None, (0, 1)).is_err());
+        assert!(check_viewport(None, (1, 0)).is_err());
+        assert!(check_viewport(None, (max + 1, 1)).is_err());
+        assert!(check_viewport(None, (1, u32::max_value())).is_err());
+    }
+
+    #[test]
+    fn check_viewport_sources() {
+        let src = |x, y, w, h| check_viewport(Some((x, y, w, h)), (1, 1));
+        assert!(src(0.0, 0.0, 0.5, 0.5).is_ok());
+        assert!(src(-1.0, 0.0, 1.0, 1.0).is_err());
+        assert!(src(0.0, 0.0, 0.0, 1.0).is_err());
+        assert!(src(0.0, 0.0, 1.0, -1.0).is_err());
+        assert!(src(0.0, ::std::f64::NAN, 1.0, 1.0).is_err());
+    }
 }
@@ -43,6 +43,20 @@ pub struct Context {
     context: X11Context,
 }
 
+impl fmt::Debug for Context {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.context {
+            X11Context::Glx(ref ctx) => {
+                f.debug_tuple("X11").field(ctx).finish()
+            }
+            X11Context::Egl(ref ctx) => {
+                f.debug_tuple("X11").field(ctx).finish()
+            }
+            X11Context::None => f.write_str("X11"),
+        }
+    }
+}
+
 unsafe impl Send for Context {}
 unsafe impl Sync for Context {}
 
@@ -158,7 +172,7 @@ impl Context {
             Prototype::Glx(ref p) => p.get_visual_infos().clone(),
             Prototype::Egl(ref p) => {
                 let mut template: ffi::XVisualInfo = unsafe { mem::zeroed() };
-                template.visualid = p.get_native_visual_id() as ffi::VisualID;
+                template.visualid = p.get_native_visual_id()? as ffi::VisualID;
 
                 let mut num_visuals = 0;
                 let vi = unsafe {
@@ -376,6 +390,18 @@ impl Context {
         Ok(context)
     }
 
+    #[inline]
+    pub fn reinitialize(&mut self) -> Result<(), ContextError> {
+        match self.context {
+            X11Context::Egl(ref mut ctx) => ctx.reinitialize(),
+            X11Context::Glx(_) => Err(ContextError::OsError(
+                "Context reinitialization is only supported with EGL"
+                    .to_string(),
+            )),
+            X11Context::None => Ok(()),
+        }
+    }
+
     #[inline]
     pub unsafe fn make_current(&self) -> Result<(), ContextError> {
         match self.context {
@@ -385,6 +411,17 @@ impl Context {
         }
     }
 
+    #[inline]
+    pub unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        match self.context {
+            X11Context::Egl(ref ctx) => ctx.make_not_current(),
+            X11Context::Glx(_) => Err(ContextError::OsError(format!(
+                "GLX contexts can't be restored after being made not current"
+            ))),
+            X11Context::None => Ok(()),
+        }
+    }
+
     #[inline]
     pub fn is_current(&self) -> bool {
         match self.context {
@@ -412,6 +449,41 @@ impl Context {
         }
     }
 
+    /// GLX has no notion of damage, so it swaps the whole window.
+    #[inline]
+    pub fn swap_buffers_with_damage(
+        &self,
+        rects: &[egl::Rect],
+    ) -> Result<(), ContextError> {
+        match self.context {
+            X11Context::Glx(ref ctx) => ctx.swap_buffers(),
+            X11Context::Egl(ref ctx) => ctx.swap_buffers_with_damage(rects),
+            X11Context::None => Ok(()),
+        }
+    }
+
+    #[inline]
+    pub fn swap_buffers_with_damage_coalesced(
+        &self,
+        rects: &[egl::Rect],
+    ) -> Result<(), ContextError> {
+        match self.context {
+            X11Context::Glx(ref ctx) => ctx.swap_buffers(),
+            X11Context::Egl(ref ctx) => {
+                ctx.swap_buffers_with_damage_coalesced(rects)
+            }
+            X11Context::None => Ok(()),
+        }
+    }
+
+    #[inline]
+    pub fn damage_is_effective(&self) -> bool {
+        match self.context {
+            X11Context::Egl(ref ctx) => ctx.damage_is_effective(),
+            _ => false,
+        }
+    }
+
     #[inline]
     pub fn get_api(&self) -> Api {
         match self.context {
@@ -421,6 +493,61 @@ impl Context {
         }
     }
 
+    #[inline]
+    pub fn framebuffer_size(&self) -> Option<(u32, u32)> {
+        match self.context {
+            X11Context::Egl(ref ctx) => ctx.surface_size(),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn swap_behavior(&self) -> Result<bool, ContextError> {
+        match self.context {
+            X11Context::Egl(ref ctx) => ctx.swap_behavior(),
+            _ => Err(ContextError::OsError(format!(
+                "The swap behavior can only be queried with EGL"
+            ))),
+        }
+    }
+
+    #[inline]
+    pub fn set_swap_behavior(
+        &self,
+        preserved: bool,
+    ) -> Result<(), ContextError> {
+        match self.context {
+            X11Context::Egl(ref ctx) => ctx.set_swap_behavior(preserved),
+            _ => Err(ContextError::OsError(format!(
+                "The swap behavior can only be set with EGL"
+            ))),
+        }
+    }
+
+    #[inline]
+    pub fn is_robust(&self) -> Option<bool> {
+        match self.context {
+            X11Context::Egl(ref ctx) => Some(ctx.is_robust()),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn get_gl_version(&self) -> Option<(u8, u8)> {
+        match self.context {
+            X11Context::Egl(ref ctx) => ctx.get_gl_version(),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn was_downgraded(&self) -> Option<bool> {
+        match self.context {
+            X11Context::Egl(ref ctx) => Some(ctx.was_downgraded()),
+            _ => None,
+        }
+    }
+
     #[inline]
     pub fn get_pixel_format(&self) -> PixelFormat {
         match self.context {
@@ -442,4 +569,28 @@ impl Context {
             _ => None,
         }
     }
+
+    #[inline]
+    pub fn egl_display(&self) -> Option<egl::Display> {
+        match self.context {
+            X11Context::Egl(ref ctx) => Some(ctx.display()),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub unsafe fn get_egl_surface(&self) -> Option<*const raw::c_void> {
+        match self.context {
+            X11Context::Egl(ref ctx) => Some(ctx.get_egl_surface()),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub unsafe fn get_egl_config(&self) -> Option<*const raw::c_void> {
+        match self.context {
+            X11Context::Egl(ref ctx) => Some(ctx.get_egl_config()),
+            _ => None,
+        }
+    }
 }
@@ -10,6 +10,27 @@ use ReleaseBehavior;
 pub fn get_gl_profile<T>(
     opengl: &GlAttributes<&T>,
     pf_reqs: &PixelFormatRequirements,
+) -> Result<NSOpenGLPFAOpenGLProfiles, CreationError> {
+    let profile = get_gl_profile_impl(opengl, pf_reqs)?;
+    // core profiles are always forward-compatible on macOS, and the legacy
+    // one never is
+    match profile {
+        NSOpenGLProfileVersionLegacy
+            if opengl.forward_compatible
+                && opengl
+                    .version
+                    .to_gl_version()
+                    .map_or(true, |v| v >= (3, 0)) =>
+        {
+            Err(CreationError::OpenGlVersionNotSupported)
+        }
+        profile => Ok(profile),
+    }
+}
+
+fn get_gl_profile_impl<T>(
+    opengl: &GlAttributes<&T>,
+    pf_reqs: &PixelFormatRequirements,
 ) -> Result<NSOpenGLPFAOpenGLProfiles, CreationError> {
     let version = opengl.version.to_gl_version();
     // first, compatibility profile support is strict
@@ -38,8 +59,10 @@ pub fn get_gl_profile<T>(
         // now, find the latest supported version automatically;
         let mut attributes: [u32; 6] = [0; 6];
         let mut current_idx = 0;
-        attributes[current_idx] = NSOpenGLPFAAllowOfflineRenderers as u32;
-        current_idx += 1;
+        if pf_reqs.allow_offline_renderers {
+            attributes[current_idx] = NSOpenGLPFAAllowOfflineRenderers as u32;
+            current_idx += 1;
+        }
 
         if let Some(true) = pf_reqs.hardware_accelerated {
             attributes[current_idx] = NSOpenGLPFAAccelerated as u32;
@@ -97,9 +120,12 @@ pub fn build_nsattributes(
         pf_reqs.depth_bits.unwrap_or(24) as u32,
         NSOpenGLPFAStencilSize as u32,
         pf_reqs.stencil_bits.unwrap_or(8) as u32,
-        NSOpenGLPFAAllowOfflineRenderers as u32,
     ];
 
+    if pf_reqs.allow_offline_renderers {
+        attributes.push(NSOpenGLPFAAllowOfflineRenderers as u32);
+    }
+
     if let Some(true) = pf_reqs.hardware_accelerated {
         attributes.push(NSOpenGLPFAAccelerated as u32);
     }
@@ -115,8 +141,12 @@ pub fn build_nsattributes(
         return Err(CreationError::NoAvailablePixelFormat);
     }
 
+    if pf_reqs.luminance_bits.is_some() {
+        return Err(CreationError::NoAvailablePixelFormat);
+    }
+
     if pf_reqs.stereoscopy {
-        unimplemented!(); // TODO:
+        attributes.push(NSOpenGLPFAStereo as u32);
     }
 
     if pf_reqs.float_color_buffer {
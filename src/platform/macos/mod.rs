@@ -2,6 +2,8 @@
 
 pub use winit::MonitorId;
 
+use ConfigCaveat;
+use os::macos::ColorSpace;
 use ContextError;
 use CreationError;
 use GlAttributes;
@@ -9,8 +11,13 @@ use PixelFormat;
 use PixelFormatRequirements;
 use Robustness;
 
+#[cfg(feature = "osmesa")]
+use api::osmesa;
+use registry::{self, Backend};
+
 use cgl::{
-    kCGLCECrashOnRemovedFunctions, kCGLCPSurfaceOpacity, CGLEnable,
+    kCGLCECrashOnRemovedFunctions, kCGLCEMPEngine, kCGLCPCurrentRendererID,
+    kCGLCPSurfaceOpacity, CGLDisable, CGLEnable, CGLGetParameter,
     CGLSetParameter,
 };
 use cocoa::appkit::{self, NSOpenGLContext, NSOpenGLPixelFormat};
@@ -20,20 +27,63 @@ use core_foundation::base::TCFType;
 use core_foundation::bundle::{
     CFBundleGetBundleWithIdentifier, CFBundleGetFunctionPointerForName,
 };
-use core_foundation::string::CFString;
+use core_foundation::string::{CFString, CFStringRef};
 use objc::runtime::{BOOL, NO};
 use winit;
 use winit::os::macos::WindowExt;
 
+use std::fmt;
 use std::ops::Deref;
 use std::os::raw::c_void;
 use std::str::FromStr;
 
 mod helpers;
 
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    static kCGColorSpaceSRGB: CFStringRef;
+    static kCGColorSpaceDisplayP3: CFStringRef;
+    static kCGColorSpaceGenericRGBLinear: CFStringRef;
+
+    fn CGColorSpaceCreateWithName(name: CFStringRef) -> *mut c_void;
+    fn CGColorSpaceRelease(space: *mut c_void);
+}
+
+#[inline]
+pub fn has_current_context() -> bool {
+    #[cfg(feature = "osmesa")]
+    {
+        if osmesa::has_current_context() {
+            return true;
+        }
+    }
+
+    unsafe {
+        let pool = NSAutoreleasePool::new(nil);
+        let current = NSOpenGLContext::currentContext(nil);
+        let _: () = msg_send![pool, release];
+        current != nil
+    }
+}
+
 pub enum Context {
     WindowedContext(WindowedContext),
     HeadlessContext(HeadlessContext),
+    #[cfg(feature = "osmesa")]
+    OsMesa(osmesa::OsMesaContext),
+}
+
+impl fmt::Debug for Context {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Context::WindowedContext(_) | Context::HeadlessContext(_) => f
+                .debug_struct("Cgl")
+                .field("api", &::Api::OpenGl)
+                .finish(),
+            #[cfg(feature = "osmesa")]
+            Context::OsMesa(ref c) => c.fmt(f),
+        }
+    }
 }
 
 pub struct WindowedContext {
@@ -90,11 +140,7 @@ impl Context {
             );
             let gl_context = match gl_context.non_nil() {
                 Some(gl_context) => gl_context,
-                None => {
-                    return Err(CreationError::NotSupported(
-                        "could not open gl context",
-                    ));
-                }
+                None => return Err(CreationError::ContextCreationFailed),
             };
 
             let pixel_format = {
@@ -114,6 +160,7 @@ impl Context {
                     hardware_accelerated: get_attr(
                         appkit::NSOpenGLPFAAccelerated,
                     ) != 0,
+                    caveat: ConfigCaveat::None,
                     color_bits: (get_attr(appkit::NSOpenGLPFAColorSize)
                         - get_attr(appkit::NSOpenGLPFAAlphaSize))
                         as u8,
@@ -132,6 +179,11 @@ impl Context {
                         None
                     },
                     srgb: true,
+                    luminance_bits: 0,
+                    accum_bits: get_attr(appkit::NSOpenGLPFAAccumSize)
+                        as u8,
+                    native_visual_id: None,
+                    recordable: false,
                 }
             };
 
@@ -160,6 +212,7 @@ impl Context {
                 context: gl_context,
                 pixel_format: pixel_format,
             };
+            registry::register(Backend::Cgl);
             Ok((window, Context::WindowedContext(context)))
         }
     }
@@ -176,16 +229,12 @@ impl Context {
             let pixelformat = NSOpenGLPixelFormat::alloc(nil)
                 .initWithAttributes_(&attributes);
             if pixelformat == nil {
-                return Err(CreationError::OsError(format!(
-                    "Could not create the pixel format"
-                )));
+                return Err(CreationError::NoAvailablePixelFormat);
             }
             let context = NSOpenGLContext::alloc(nil)
                 .initWithFormat_shareContext_(pixelformat, nil);
             if context == nil {
-                return Err(CreationError::OsError(format!(
-                    "Could not create the rendering context"
-                )));
+                return Err(CreationError::ContextCreationFailed);
             }
 
             IdRef::new(context)
@@ -193,9 +242,81 @@ impl Context {
 
         let headless = HeadlessContext { context };
 
+        registry::register(Backend::Cgl);
         Ok(Context::HeadlessContext(headless))
     }
 
+    #[cfg(feature = "osmesa")]
+    #[inline]
+    pub fn new_osmesa(
+        pf_reqs: &PixelFormatRequirements,
+        gl_attr: &GlAttributes<&Context>,
+    ) -> Result<Self, CreationError> {
+        let gl_attr = match gl_attr.sharing {
+            None | Some(&Context::OsMesa(_)) => {
+                gl_attr.clone().map_sharing(|ctx| match *ctx {
+                    Context::OsMesa(ref c) => c,
+                    _ => unreachable!(),
+                })
+            }
+            Some(_) => {
+                let msg =
+                    "Cannot share an OSMesa context with a non-OSMesa context";
+                return Err(CreationError::PlatformSpecific(msg.into()));
+            }
+        };
+        osmesa::OsMesaContext::new((1, 1), pf_reqs, &gl_attr)
+            .map(Context::OsMesa)
+    }
+
+    #[cfg(feature = "osmesa")]
+    pub fn resize_osmesa(
+        &mut self,
+        width: u32,
+        height: u32,
+    ) -> Result<(), ContextError> {
+        match *self {
+            Context::OsMesa(ref mut ctx) => {
+                ctx.resize(width, height);
+                Ok(())
+            }
+            _ => Err(ContextError::OsError(format!(
+                "Only OsMesa contexts can be resized this way"
+            ))),
+        }
+    }
+
+    #[cfg(feature = "osmesa")]
+    pub fn set_row_length_osmesa(
+        &mut self,
+        pixels: u32,
+    ) -> Result<(), ContextError> {
+        match *self {
+            Context::OsMesa(ref mut ctx) => ctx.set_row_length(pixels),
+            _ => Err(ContextError::OsError(format!(
+                "Only OsMesa contexts have a row length"
+            ))),
+        }
+    }
+
+    #[cfg(feature = "osmesa")]
+    pub unsafe fn make_current_raw_buffer_osmesa(
+        &self,
+        ptr: *mut u8,
+        width: u32,
+        height: u32,
+        format: osmesa::OsMesaColorFormat,
+    ) -> Result<(), ContextError> {
+        match *self {
+            Context::OsMesa(ref ctx) => {
+                ctx.make_current_raw_buffer(ptr, width, height, format)
+            }
+            _ => Err(ContextError::OsError(format!(
+                "Only OsMesa contexts can render to a raw buffer"
+            ))),
+        }
+    }
+
     /// See the docs in the crate root file.
     #[inline]
     pub fn new_separated(
@@ -214,6 +335,13 @@ impl Context {
         }
     }
 
+    #[inline]
+    pub fn reinitialize(&mut self) -> Result<(), ContextError> {
+        Err(ContextError::OsError(
+            "Context reinitialization is only supported with EGL".to_string(),
+        ))
+    }
+
     #[inline]
     pub unsafe fn make_current(&self) -> Result<(), ContextError> {
         match *self {
@@ -225,7 +353,168 @@ impl Context {
                 let _: () = msg_send![*c.context, update];
                 c.context.makeCurrentContext();
             }
+            #[cfg(feature = "osmesa")]
+            Context::OsMesa(ref c) => return c.make_current(),
+        }
+        Ok(())
+    }
+
+    /// Returns the ID of the renderer that is currently used by this context,
+    /// which tells which GPU it runs on.
+    #[inline]
+    pub fn renderer_id(&self) -> Option<u32> {
+        unsafe {
+            let context = match *self {
+                Context::WindowedContext(ref c) => *c.context,
+                Context::HeadlessContext(ref c) => *c.context,
+                #[cfg(feature = "osmesa")]
+                Context::OsMesa(_) => return None,
+            };
+
+            let mut renderer_id = 0;
+            let err = CGLGetParameter(
+                context.CGLContextObj() as *mut _,
+                kCGLCPCurrentRendererID,
+                &mut renderer_id,
+            );
+            if err == 0 {
+                Some(renderer_id as u32)
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Sets the number of screen refreshes to wait for in `swap_buffers`.
+    #[inline]
+    pub fn set_swap_interval(&self, interval: i32) {
+        unsafe {
+            let context = match *self {
+                Context::WindowedContext(ref c) => *c.context,
+                Context::HeadlessContext(ref c) => *c.context,
+                #[cfg(feature = "osmesa")]
+                Context::OsMesa(_) => return,
+            };
+
+            context.setValues_forParameter_(
+                &interval,
+                appkit::NSOpenGLContextParameter::NSOpenGLCPSwapInterval,
+            );
+        }
+    }
+
+    /// Enables or disables the multithreaded OpenGL engine of CGL.
+    #[inline]
+    pub fn enable_multithreaded_engine(
+        &self,
+        enable: bool,
+    ) -> Result<(), ContextError> {
+        unsafe {
+            let context = match *self {
+                Context::WindowedContext(ref c) => *c.context,
+                Context::HeadlessContext(ref c) => *c.context,
+                #[cfg(feature = "osmesa")]
+                Context::OsMesa(_) => {
+                    return Err(ContextError::OsError(
+                        "OsMesa has no multithreaded engine".to_string(),
+                    ));
+                }
+            };
+
+            let cgl_context = context.CGLContextObj() as *mut _;
+            let err = if enable {
+                CGLEnable(cgl_context, kCGLCEMPEngine)
+            } else {
+                CGLDisable(cgl_context, kCGLCEMPEngine)
+            };
+            if err != 0 {
+                return Err(ContextError::OsError(format!(
+                    "Couldn't change the multithreaded engine (CGLError {})",
+                    err
+                )));
+            }
+            Ok(())
+        }
+    }
+
+    /// Returns the `NSView` the `NSOpenGLContext` draws into, or `None` for
+    /// headless contexts.
+    pub unsafe fn raw_view(&self) -> Option<*mut c_void> {
+        let context = match *self {
+            Context::WindowedContext(ref c) => *c.context,
+            _ => return None,
+        };
+        let view: id = msg_send![context, view];
+        if view == nil {
+            None
+        } else {
+            Some(view as *mut _)
+        }
+    }
+
+    /// Returns the `CALayer` backing the view of the context, which only
+    /// exists if the view is layer-backed.
+    pub unsafe fn raw_layer(&self) -> Option<*mut c_void> {
+        let view = self.raw_view()? as id;
+        let layer: id = msg_send![view, layer];
+        if layer == nil {
+            None
+        } else {
+            Some(layer as *mut _)
+        }
+    }
+
+    /// Sets the color space of the window the context draws into.
+    pub fn set_colorspace(
+        &self,
+        space: ColorSpace,
+    ) -> Result<(), ContextError> {
+        let context = match *self {
+            Context::WindowedContext(ref c) => *c.context,
+            Context::HeadlessContext(_) => {
+                return Err(ContextError::OsError(
+                    "Headless contexts don't have a color space".to_string(),
+                ));
+            }
+            #[cfg(feature = "osmesa")]
+            Context::OsMesa(_) => {
+                return Err(ContextError::OsError(
+                    "Headless contexts don't have a color space".to_string(),
+                ));
+            }
+        };
+
+        unsafe {
+            let name = match space {
+                ColorSpace::Srgb => kCGColorSpaceSRGB,
+                ColorSpace::DisplayP3 => kCGColorSpaceDisplayP3,
+                ColorSpace::GenericRgbLinear => kCGColorSpaceGenericRGBLinear,
+            };
+            let cg_space = CGColorSpaceCreateWithName(name);
+            if cg_space.is_null() {
+                return Err(ContextError::OsError(format!(
+                    "Color space {:?} is not supported",
+                    space
+                )));
+            }
+
+            let ns_space: id = msg_send![class!(NSColorSpace), alloc];
+            let ns_space: id =
+                msg_send![ns_space, initWithCGColorSpace: cg_space];
+            CGColorSpaceRelease(cg_space);
+            if ns_space == nil {
+                return Err(ContextError::OsError(format!(
+                    "Color space {:?} is not supported",
+                    space
+                )));
+            }
+
+            let view: id = msg_send![context, view];
+            let window: id = msg_send![view, window];
+            let _: () = msg_send![window, setColorSpace: ns_space];
+            let _: () = msg_send![ns_space, release];
         }
+
         Ok(())
     }
 
@@ -235,6 +524,8 @@ impl Context {
             let context = match *self {
                 Context::WindowedContext(ref c) => *c.context,
                 Context::HeadlessContext(ref c) => *c.context,
+                #[cfg(feature = "osmesa")]
+                Context::OsMesa(ref c) => return c.is_current(),
             };
 
             let pool = NSAutoreleasePool::new(nil);
@@ -251,6 +542,13 @@ impl Context {
     }
 
     pub fn get_proc_address(&self, addr: &str) -> *const () {
+        #[cfg(feature = "osmesa")]
+        {
+            if let Context::OsMesa(ref c) = *self {
+                return c.get_proc_address(addr);
+            }
+        }
+
         let symbol_name: CFString = FromStr::from_str(addr).unwrap();
         let framework_name: CFString =
             FromStr::from_str("com.apple.opengl").unwrap();
@@ -278,6 +576,8 @@ impl Context {
                     let _: () = msg_send![pool, release];
                 }
                 Context::HeadlessContext(_) => unreachable!(),
+                #[cfg(feature = "osmesa")]
+                Context::OsMesa(_) => unreachable!(),
             }
         }
         Ok(())
@@ -288,11 +588,33 @@ impl Context {
         ::Api::OpenGl
     }
 
+    #[inline]
+    pub fn framebuffer_size(&self) -> Option<(u32, u32)> {
+        match *self {
+            #[cfg(feature = "osmesa")]
+            Context::OsMesa(ref c) => Some(c.get_dimensions()),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "osmesa")]
+    #[inline]
+    pub fn osmesa_framebuffer(&self) -> Option<(&[u32], u32)> {
+        match *self {
+            Context::OsMesa(ref c) => {
+                Some((c.get_framebuffer(), c.get_row_length()))
+            }
+            _ => None,
+        }
+    }
+
     #[inline]
     pub fn get_pixel_format(&self) -> PixelFormat {
         match *self {
             Context::WindowedContext(ref c) => c.pixel_format.clone(),
             Context::HeadlessContext(_) => unreachable!(),
+            #[cfg(feature = "osmesa")]
+            Context::OsMesa(_) => unreachable!(),
         }
     }
 
@@ -301,6 +623,20 @@ impl Context {
         match *self {
             Context::WindowedContext(ref c) => *c.context.deref() as *mut _,
             Context::HeadlessContext(ref c) => *c.context.deref() as *mut _,
+            #[cfg(feature = "osmesa")]
+            Context::OsMesa(ref c) => c.raw_handle(),
+        }
+    }
+}
+
+impl Drop for Context {
+    #[inline]
+    fn drop(&mut self) {
+        match *self {
+            // the OsMesa context unregisters itself
+            #[cfg(feature = "osmesa")]
+            Context::OsMesa(_) => (),
+            _ => registry::unregister(Backend::Cgl),
         }
     }
 }
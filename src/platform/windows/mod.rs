@@ -1,5 +1,6 @@
 #![cfg(target_os = "windows")]
 
+use std::fmt;
 use std::os::raw;
 use std::ptr;
 
@@ -17,6 +18,9 @@ use PixelFormatRequirements;
 use api::egl;
 use api::egl::Context as EglContext;
 use api::egl::EGL;
+#[cfg(feature = "osmesa")]
+use api::osmesa;
+use api::wgl;
 use api::wgl::Context as WglContext;
 use os::windows::WindowExt;
 
@@ -25,6 +29,20 @@ use os::windows::WindowExt;
 pub enum RawHandle {
     Egl(egl::ffi::EGLContext),
     Wgl(HGLRC),
+    #[cfg(feature = "osmesa")]
+    OsMesa(*mut raw::c_void),
+}
+
+#[inline]
+pub fn has_current_context() -> bool {
+    #[cfg(feature = "osmesa")]
+    {
+        if osmesa::has_current_context() {
+            return true;
+        }
+    }
+
+    wgl::has_current_context() || egl::has_current_context()
 }
 
 pub enum Context {
@@ -36,6 +54,23 @@ pub enum Context {
     HiddenWindowWgl(winit::Window, WglContext),
     /// An EGL pbuffer.
     EglPbuffer(EglContext),
+    #[cfg(feature = "osmesa")]
+    OsMesa(osmesa::OsMesaContext),
+}
+
+impl fmt::Debug for Context {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Context::Egl(ref c)
+            | Context::HiddenWindowEgl(_, ref c)
+            | Context::EglPbuffer(ref c) => c.fmt(f),
+            Context::Wgl(ref c) | Context::HiddenWindowWgl(_, ref c) => {
+                c.fmt(f)
+            }
+            #[cfg(feature = "osmesa")]
+            Context::OsMesa(ref c) => c.fmt(f),
+        }
+    }
 }
 
 unsafe impl Send for Context {}
@@ -187,11 +222,111 @@ impl Context {
         })
     }
 
+    #[inline]
+    pub unsafe fn new_raw_egl(
+        display: *const raw::c_void,
+        context: *const raw::c_void,
+        config: *const raw::c_void,
+    ) -> Result<Self, CreationError> {
+        EglContext::from_raw(display, context, config).map(Context::Egl)
+    }
+
+    #[cfg(feature = "osmesa")]
+    #[inline]
+    pub fn new_osmesa(
+        pf_reqs: &PixelFormatRequirements,
+        gl_attr: &GlAttributes<&Context>,
+    ) -> Result<Self, CreationError> {
+        let gl_attr = match gl_attr.sharing {
+            None | Some(&Context::OsMesa(_)) => {
+                gl_attr.clone().map_sharing(|ctx| match *ctx {
+                    Context::OsMesa(ref c) => c,
+                    _ => unreachable!(),
+                })
+            }
+            Some(_) => {
+                let msg =
+                    "Cannot share an OSMesa context with a non-OSMesa context";
+                return Err(CreationError::PlatformSpecific(msg.into()));
+            }
+        };
+        osmesa::OsMesaContext::new((1, 1), pf_reqs, &gl_attr)
+            .map(Context::OsMesa)
+    }
+
+    #[cfg(feature = "osmesa")]
+    pub fn resize_osmesa(
+        &mut self,
+        width: u32,
+        height: u32,
+    ) -> Result<(), ContextError> {
+        match *self {
+            Context::OsMesa(ref mut ctx) => {
+                ctx.resize(width, height);
+                Ok(())
+            }
+            _ => Err(ContextError::OsError(format!(
+                "Only OsMesa contexts can be resized this way"
+            ))),
+        }
+    }
+
+    #[cfg(feature = "osmesa")]
+    pub fn set_row_length_osmesa(
+        &mut self,
+        pixels: u32,
+    ) -> Result<(), ContextError> {
+        match *self {
+            Context::OsMesa(ref mut ctx) => ctx.set_row_length(pixels),
+            _ => Err(ContextError::OsError(format!(
+                "Only OsMesa contexts have a row length"
+            ))),
+        }
+    }
+
+    #[cfg(feature = "osmesa")]
+    pub unsafe fn make_current_raw_buffer_osmesa(
+        &self,
+        ptr: *mut u8,
+        width: u32,
+        height: u32,
+        format: osmesa::OsMesaColorFormat,
+    ) -> Result<(), ContextError> {
+        match *self {
+            Context::OsMesa(ref ctx) => {
+                ctx.make_current_raw_buffer(ptr, width, height, format)
+            }
+            _ => Err(ContextError::OsError(format!(
+                "Only OsMesa contexts can render to a raw buffer"
+            ))),
+        }
+    }
+
     #[inline]
     pub fn resize(&self, _width: u32, _height: u32) {
         // Method is for API consistency.
     }
 
+    #[inline]
+    pub fn reinitialize(&mut self) -> Result<(), ContextError> {
+        match *self {
+            Context::Egl(ref mut c)
+            | Context::HiddenWindowEgl(_, ref mut c)
+            | Context::EglPbuffer(ref mut c) => c.reinitialize(),
+            Context::Wgl(_) | Context::HiddenWindowWgl(_, _) => {
+                Err(ContextError::OsError(
+                    "Context reinitialization is only supported with EGL"
+                        .to_string(),
+                ))
+            }
+            #[cfg(feature = "osmesa")]
+            Context::OsMesa(_) => Err(ContextError::OsError(
+                "Context reinitialization is only supported with EGL"
+                    .to_string(),
+            )),
+        }
+    }
+
     #[inline]
     pub unsafe fn make_current(&self) -> Result<(), ContextError> {
         match *self {
@@ -201,6 +336,8 @@ impl Context {
             Context::Egl(ref c)
             | Context::HiddenWindowEgl(_, ref c)
             | Context::EglPbuffer(ref c) => c.make_current(),
+            #[cfg(feature = "osmesa")]
+            Context::OsMesa(ref c) => c.make_current(),
         }
     }
 
@@ -213,6 +350,8 @@ impl Context {
             Context::Egl(ref c)
             | Context::HiddenWindowEgl(_, ref c)
             | Context::EglPbuffer(ref c) => c.is_current(),
+            #[cfg(feature = "osmesa")]
+            Context::OsMesa(ref c) => c.is_current(),
         }
     }
 
@@ -225,6 +364,8 @@ impl Context {
             Context::Egl(ref c)
             | Context::HiddenWindowEgl(_, ref c)
             | Context::EglPbuffer(ref c) => c.get_proc_address(addr),
+            #[cfg(feature = "osmesa")]
+            Context::OsMesa(ref c) => c.get_proc_address(addr),
         }
     }
 
@@ -246,6 +387,31 @@ impl Context {
             Context::Egl(ref c)
             | Context::HiddenWindowEgl(_, ref c)
             | Context::EglPbuffer(ref c) => c.get_api(),
+            #[cfg(feature = "osmesa")]
+            Context::OsMesa(ref c) => c.get_api(),
+        }
+    }
+
+    #[inline]
+    pub fn framebuffer_size(&self) -> Option<(u32, u32)> {
+        match *self {
+            Context::Egl(ref c)
+            | Context::HiddenWindowEgl(_, ref c)
+            | Context::EglPbuffer(ref c) => c.surface_size(),
+            #[cfg(feature = "osmesa")]
+            Context::OsMesa(ref c) => Some(c.get_dimensions()),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "osmesa")]
+    #[inline]
+    pub fn osmesa_framebuffer(&self) -> Option<(&[u32], u32)> {
+        match *self {
+            Context::OsMesa(ref c) => {
+                Some((c.get_framebuffer(), c.get_row_length()))
+            }
+            _ => None,
         }
     }
 
@@ -267,6 +433,8 @@ impl Context {
             Context::Egl(ref c)
             | Context::HiddenWindowEgl(_, ref c)
             | Context::EglPbuffer(ref c) => RawHandle::Egl(c.raw_handle()),
+            #[cfg(feature = "osmesa")]
+            Context::OsMesa(ref c) => RawHandle::OsMesa(c.raw_handle()),
         }
     }
 
@@ -279,4 +447,24 @@ impl Context {
             _ => None,
         }
     }
+
+    #[inline]
+    pub unsafe fn get_egl_surface(&self) -> Option<*const raw::c_void> {
+        match *self {
+            Context::Egl(ref c)
+            | Context::HiddenWindowEgl(_, ref c)
+            | Context::EglPbuffer(ref c) => Some(c.get_egl_surface()),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub unsafe fn get_egl_config(&self) -> Option<*const raw::c_void> {
+        match *self {
+            Context::Egl(ref c)
+            | Context::HiddenWindowEgl(_, ref c)
+            | Context::EglPbuffer(ref c) => Some(c.get_egl_config()),
+            _ => None,
+        }
+    }
 }
@@ -0,0 +1,69 @@
+//! Bookkeeping of the contexts that are currently alive, to help tracking
+//! down context leaks.
+//!
+//! Every backend registers its contexts when they are created and
+//! unregisters them when they are dropped. This does nothing unless the
+//! `debug_contexts` feature is enabled.
+
+#[cfg(feature = "debug_contexts")]
+use std::sync::Mutex;
+
+/// The library used to create an OpenGL context.
+#[allow(dead_code)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Backend {
+    /// EGL, on Unix, Android and Windows.
+    Egl,
+    /// GLX, on X11.
+    Glx,
+    /// WGL, on Windows.
+    Wgl,
+    /// CGL, on macOS.
+    Cgl,
+    /// EAGL, on iOS.
+    Eagl,
+    /// OSMesa, on Unix.
+    OsMesa,
+    /// WebGL, on Emscripten.
+    WebGl,
+}
+
+#[cfg(feature = "debug_contexts")]
+lazy_static! {
+    static ref LIVE_CONTEXTS: Mutex<Vec<Backend>> = Mutex::new(Vec::new());
+}
+
+#[inline]
+pub(crate) fn register(backend: Backend) {
+    #[cfg(feature = "debug_contexts")]
+    LIVE_CONTEXTS.lock().unwrap().push(backend);
+    #[cfg(not(feature = "debug_contexts"))]
+    let _ = backend;
+}
+
+#[inline]
+pub(crate) fn unregister(backend: Backend) {
+    #[cfg(feature = "debug_contexts")]
+    {
+        let mut live = LIVE_CONTEXTS.lock().unwrap();
+        if let Some(pos) = live.iter().position(|b| *b == backend) {
+            live.remove(pos);
+        }
+    }
+    #[cfg(not(feature = "debug_contexts"))]
+    let _ = backend;
+}
+
+/// Returns the number of contexts that are currently alive, including
+/// headless ones and contexts created outside of glutin.
+#[cfg(feature = "debug_contexts")]
+pub fn debug_live_contexts() -> usize {
+    LIVE_CONTEXTS.lock().unwrap().len()
+}
+
+/// Returns the backend of each context that is currently alive, in the order
+/// in which they were created.
+#[cfg(feature = "debug_contexts")]
+pub fn debug_context_backends() -> Vec<Backend> {
+    LIVE_CONTEXTS.lock().unwrap().clone()
+}
@@ -40,6 +40,11 @@ impl SeparatedContext {
     /// Builds the GL context using the passed `Window`, returning the context
     /// as a `SeparatedContext`.
     ///
+    /// The window isn't rebuilt, so it can be created well before the
+    /// context, e.g. to handle input first. On Wayland, the EGL surface is
+    /// created on top of the `wl_surface` of the window, and dropping the
+    /// context leaves the window alive.
+    ///
     /// One notable limitation of the Wayland backend when it comes to shared
     /// contexts is that both contexts must use the same events loop.
     ///
@@ -54,11 +59,12 @@ impl SeparatedContext {
         el: &EventsLoop,
     ) -> Result<Self, CreationError> {
         let ContextBuilder { pf_reqs, gl_attr } = cb;
+        let sharing = gl_attr.sharing;
         let gl_attr = gl_attr.map_sharing(|ctx| &ctx.context);
 
         platform::Context::new_separated(window, el, &pf_reqs, &gl_attr).map(
             |context| SeparatedContext {
-                context: Context { context },
+                context: Context::from_platform(context, sharing),
             },
         )
     }
@@ -77,7 +83,13 @@ impl SeparatedContext {
     /// next time the screen is refreshed. However drivers can choose to
     /// override your vsync settings, which means that you can't know in
     /// advance whether `swap_buffers` will block or not.
+    ///
+    /// The context must be current on the calling thread, otherwise
+    /// `ContextError::NotCurrentOnThisThread` is returned.
     pub fn swap_buffers(&self) -> Result<(), ContextError> {
+        if !self.context.context.is_current() {
+            return Err(ContextError::NotCurrentOnThisThread);
+        }
         self.context.context.swap_buffers()
     }
 
@@ -94,10 +106,27 @@ impl SeparatedContext {
     /// The easiest way of doing this is to take every `Resized` window event
     /// that is received with a `LogicalSize` and convert it to a
     /// `PhysicalSize` and pass it into this function.
+    ///
+    /// It must be called from the thread on which the context is current,
+    /// once the window has its new size, and before rendering the next
+    /// frame. On the other platforms this does nothing, so it can always be
+    /// called.
     pub fn resize(&self, size: dpi::PhysicalSize) {
         let (width, height) = size.into();
         self.context.context.resize(width, height);
     }
+
+    /// Recreates the GL context in place. See `Context::reinitialize`.
+    pub fn reinitialize(&mut self) -> Result<(), ContextError> {
+        self.context.reinitialize()
+    }
+
+    /// Checks that the context renders, see `Context::smoke_test`, then
+    /// swaps the buffers.
+    pub unsafe fn smoke_test(&self) -> Result<(), ContextError> {
+        self.context.smoke_test()?;
+        self.swap_buffers()
+    }
 }
 
 impl ContextTrait for SeparatedContext {
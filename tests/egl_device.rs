@@ -0,0 +1,520 @@
+#![cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+
+extern crate glutin;
+
+mod support;
+
+use glutin::os::unix::{
+    ContextExt, EglDeviceContextExt, EglDisplay, EglSurfaceContextExt,
+};
+use glutin::os::ContextTraitExt;
+use glutin::{Api, ContextTrait, GlRequest};
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_void};
+use std::panic;
+use std::sync::Arc;
+use std::thread;
+use std::{mem, ptr};
+
+#[test]
+fn reinitialize_keeps_context_current() {
+    let device = match support::egl_device() {
+        Some(device) => device,
+        None => return,
+    };
+    let mut context =
+        glutin::Context::new_egl_device(support::builder(), &device).unwrap();
+    unsafe { context.make_current().unwrap() };
+
+    context.reinitialize().unwrap();
+    assert!(context.is_current());
+    assert!(context.gl_version().is_some());
+}
+
+#[test]
+fn reinitialize_after_share_partner_dropped() {
+    let device = match support::egl_device() {
+        Some(device) => device,
+        None => return,
+    };
+    let root =
+        glutin::Context::new_egl_device(support::builder(), &device).unwrap();
+    let mut context = glutin::Context::new_egl_device(
+        support::builder().with_shared_lists(&root),
+        &device,
+    )
+    .unwrap();
+    let sibling = glutin::Context::new_egl_device(
+        support::builder().with_shared_lists(&root),
+        &device,
+    )
+    .unwrap();
+
+    // the new context shares with `sibling`, which is still alive
+    drop(root);
+    context.reinitialize().unwrap();
+
+    // and with nothing once the whole group is gone
+    drop(sibling);
+    context.reinitialize().unwrap();
+    unsafe { context.make_current().unwrap() };
+}
+
+#[test]
+fn bind_unsupported_client_buffer() {
+    const EGL_OPENVG_IMAGE: u32 = 0x3096;
+    let device = match support::egl_device() {
+        Some(device) => device,
+        None => return,
+    };
+    let mut context =
+        glutin::Context::new_egl_device(support::builder(), &device).unwrap();
+
+    // Mesa doesn't implement OpenVG
+    let ret = unsafe {
+        context.bind_client_buffer(EGL_OPENVG_IMAGE, ptr::null(), None)
+    };
+    assert!(ret.is_err());
+    unsafe { context.make_current().unwrap() };
+}
+
+#[test]
+fn unbind_surface_keeps_context() {
+    let device = match support::egl_device() {
+        Some(device) => device,
+        None => return,
+    };
+    let mut context =
+        glutin::Context::new_egl_device(support::builder(), &device).unwrap();
+    unsafe {
+        context.make_current().unwrap();
+        context.unbind_surface().unwrap();
+    }
+    // Mesa supports `EGL_KHR_surfaceless_context`
+    assert!(context.is_current());
+
+    // unbinding twice does nothing
+    unsafe { context.unbind_surface().unwrap() };
+}
+
+/// Returns `GL_VERSION` of the context current on the calling thread.
+fn gl_version_string(context: &glutin::Context) -> String {
+    const GL_VERSION: u32 = 0x1F02;
+    let get_string = context.get_proc_address("glGetString");
+    assert!(!get_string.is_null());
+    unsafe {
+        let get_string: extern "system" fn(u32) -> *const c_char =
+            mem::transmute(get_string);
+        CStr::from_ptr(get_string(GL_VERSION))
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+#[test]
+fn gl_and_gles_on_one_thread() {
+    const EGL_OPENGL_ES_API: u32 = 0x30A0;
+    let device = match support::egl_device() {
+        Some(device) => device,
+        None => return,
+    };
+    let gl = glutin::Context::new_egl_device(
+        support::builder().with_gl(GlRequest::Specific(Api::OpenGl, (3, 0))),
+        &device,
+    )
+    .unwrap();
+    let gles = glutin::Context::new_egl_device(
+        support::builder()
+            .with_gl(GlRequest::Specific(Api::OpenGlEs, (2, 0))),
+        &device,
+    )
+    .unwrap();
+    assert_eq!(gl.get_api(), Api::OpenGl);
+    assert_eq!(gles.get_api(), Api::OpenGlEs);
+
+    unsafe { gl.make_current().unwrap() };
+    assert!(gl.is_current());
+    assert!(!gles.is_current());
+    assert!(!gl_version_string(&gl).starts_with("OpenGL ES"));
+
+    unsafe { gles.make_current().unwrap() };
+    assert!(gles.is_current());
+    assert!(!gl.is_current());
+    assert!(gl_version_string(&gles).starts_with("OpenGL ES"));
+
+    // querying the other context must not bind its API
+    let query_api = gles.get_proc_address("eglQueryAPI");
+    if !query_api.is_null() {
+        let query_api: extern "system" fn() -> u32 =
+            unsafe { mem::transmute(query_api) };
+        assert_eq!(query_api(), EGL_OPENGL_ES_API);
+    }
+
+    // dropping the desktop context calls `glFinish` on it, which must leave
+    // the OpenGL ES one current
+    drop(gl);
+    assert!(gles.is_current());
+    assert!(gl_version_string(&gles).starts_with("OpenGL ES"));
+    if !query_api.is_null() {
+        let query_api: extern "system" fn() -> u32 =
+            unsafe { mem::transmute(query_api) };
+        assert_eq!(query_api(), EGL_OPENGL_ES_API);
+    }
+}
+
+#[test]
+fn gl_then_gles_creates_desktop_gl() {
+    let device = match support::egl_device() {
+        Some(device) => device,
+        None => return,
+    };
+    let request = GlRequest::GlThenGles {
+        opengl_version: (3, 0),
+        opengles_version: (2, 0),
+    };
+    let context = glutin::Context::new_egl_device(
+        support::builder().with_gl(request),
+        &device,
+    )
+    .unwrap();
+    assert_eq!(context.get_api(), Api::OpenGl);
+    unsafe { context.make_current().unwrap() };
+    assert!(!gl_version_string(&context).starts_with("OpenGL ES"));
+}
+
+#[test]
+fn gl_then_gles_falls_back_to_gles() {
+    let device = match support::egl_device() {
+        Some(device) => device,
+        None => return,
+    };
+    // no driver implements this version of desktop OpenGL
+    let request = GlRequest::GlThenGles {
+        opengl_version: (9, 9),
+        opengles_version: (2, 0),
+    };
+    let context = glutin::Context::new_egl_device(
+        support::builder().with_gl(request),
+        &device,
+    )
+    .unwrap();
+    assert_eq!(context.get_api(), Api::OpenGlEs);
+    unsafe { context.make_current().unwrap() };
+    assert!(gl_version_string(&context).starts_with("OpenGL ES"));
+
+    // the config of the context, chosen again if needed, supports OpenGL ES
+    const EGL_RENDERABLE_TYPE: i32 = 0x3040;
+    const EGL_OPENGL_ES2_BIT: i32 = 0x0004;
+    let get_config_attrib = context.get_proc_address("eglGetConfigAttrib");
+    assert!(!get_config_attrib.is_null());
+    let get_config_attrib: extern "system" fn(
+        *const c_void,
+        *const c_void,
+        i32,
+        *mut i32,
+    ) -> u32 = unsafe { mem::transmute(get_config_attrib) };
+    let mut renderable = 0;
+    unsafe {
+        assert_ne!(
+            get_config_attrib(
+                context.get_egl_display().unwrap(),
+                context.get_egl_config().unwrap(),
+                EGL_RENDERABLE_TYPE,
+                &mut renderable,
+            ),
+            0
+        );
+    }
+    assert_ne!(renderable & EGL_OPENGL_ES2_BIT, 0);
+}
+
+#[test]
+fn gles_request_creates_gles() {
+    let device = match support::egl_device() {
+        Some(device) => device,
+        None => return,
+    };
+    let context = glutin::Context::new_egl_device(
+        support::builder().with_gles(3, 0),
+        &device,
+    )
+    .unwrap();
+    assert_eq!(context.get_api(), Api::OpenGlEs);
+    unsafe { context.make_current().unwrap() };
+    assert!(gl_version_string(&context).starts_with("OpenGL ES 3."));
+}
+
+#[test]
+fn unsupported_gles_request_never_falls_back() {
+    let device = match support::egl_device() {
+        Some(device) => device,
+        None => return,
+    };
+    let ret = glutin::Context::new_egl_device(
+        support::builder().with_gles(4, 0),
+        &device,
+    );
+    match ret {
+        Err(glutin::CreationError::OpenGlVersionNotSupported) => (),
+        Err(err) => panic!("unexpected error: {}", err),
+        Ok(context) => panic!("created an {:?} context", context.get_api()),
+    }
+}
+
+#[test]
+fn shared_contexts_from_many_threads() {
+    const THREADS: usize = 16;
+    let device = match support::egl_device() {
+        Some(device) => device,
+        None => return,
+    };
+    let root = Arc::new(
+        glutin::Context::new_egl_device(support::builder(), &device).unwrap(),
+    );
+
+    let workers: Vec<_> = (0..THREADS)
+        .map(|_| {
+            let root = root.clone();
+            let device = device.clone();
+            thread::spawn(move || {
+                let cb = support::builder().with_shared_lists(&root);
+                let context =
+                    glutin::Context::new_egl_device(cb, &device).unwrap();
+                unsafe { context.make_current().unwrap() };
+                assert!(context.is_current());
+                assert!(context.shares_with(&root));
+                gl_version_string(&context);
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker.join().unwrap();
+    }
+    assert!(!root.is_shared());
+}
+
+#[test]
+fn no_error_conflicts_with_debug_and_robustness() {
+    let device = match support::egl_device() {
+        Some(device) => device,
+        None => return,
+    };
+    let builders = vec![
+        support::builder().with_no_error(true).with_gl_debug_flag(true),
+        support::builder().with_no_error(true).with_gl_robustness(
+            glutin::Robustness::TryRobustLoseContextOnReset,
+        ),
+    ];
+    for cb in builders {
+        match glutin::Context::new_egl_device(cb, &device) {
+            Err(glutin::CreationError::NotSupported(_)) => (),
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(_) => panic!("created a no-error debug or robust context"),
+        }
+    }
+
+    // `Robustness::NoError` is only an optimization, so it falls back
+    let cb = support::builder()
+        .with_gl_debug_flag(true)
+        .with_gl_robustness(glutin::Robustness::NoError);
+    let context = glutin::Context::new_egl_device(cb, &device).unwrap();
+    unsafe { context.make_current().unwrap() };
+}
+
+#[test]
+fn no_error_context() {
+    let device = match support::egl_device() {
+        Some(device) => device,
+        None => return,
+    };
+    let cb = support::builder().with_no_error(true).with_gl_debug_flag(false);
+    match glutin::Context::new_egl_device(cb, &device) {
+        Ok(context) => unsafe { context.make_current().unwrap() },
+        // the driver doesn't support no-error contexts
+        Err(glutin::CreationError::NotSupported(_)) => (),
+        Err(err) => panic!("unexpected error: {}", err),
+    }
+}
+
+#[test]
+fn render_to_texture_restores_surfaces_after_panic() {
+    const EGL_DRAW: i32 = 0x3059;
+    let device = match support::egl_device() {
+        Some(device) => device,
+        None => return,
+    };
+    let context =
+        glutin::Context::new_egl_device(support::builder(), &device).unwrap();
+    unsafe { context.make_current().unwrap() };
+    let target = match context.create_render_to_texture(16, 16) {
+        Ok(target) => target,
+        Err(err) => {
+            eprintln!("the config can't be bound, skipping: {}", err);
+            return;
+        }
+    };
+
+    let current_surface = context.get_proc_address("eglGetCurrentSurface");
+    assert!(!current_surface.is_null());
+    let current_surface: extern "system" fn(i32) -> *const c_void =
+        unsafe { mem::transmute(current_surface) };
+    let previous = current_surface(EGL_DRAW);
+
+    let ret = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        target.with_current(|| {
+            assert_eq!(current_surface(EGL_DRAW), target.raw_surface());
+            panic!("rendering failed");
+        })
+    }));
+    assert!(ret.is_err());
+    assert!(context.is_current());
+    assert_eq!(current_surface(EGL_DRAW), previous);
+}
+
+#[test]
+fn damage_is_not_effective_on_pbuffers() {
+    let device = match support::egl_device() {
+        Some(device) => device,
+        None => return,
+    };
+    let context =
+        glutin::Context::new_egl_device(support::builder(), &device).unwrap();
+    unsafe { context.make_current().unwrap() };
+
+    // pbuffers are single-buffered, so the damage never reaches anything
+    assert!(!context.damage_is_effective());
+    let damage = [glutin::os::unix::Rect::from_xywh(0, 0, 1, 1)];
+    context.swap_buffers_with_damage(&damage).unwrap();
+    context.swap_buffers_with_damage_coalesced(&damage).unwrap();
+}
+
+#[test]
+fn resize_pbuffer_recreates_current_surface() {
+    const EGL_WIDTH: i32 = 0x3057;
+    const EGL_HEIGHT: i32 = 0x3056;
+    const EGL_DRAW: i32 = 0x3059;
+    let device = match support::egl_device() {
+        Some(device) => device,
+        None => return,
+    };
+    let mut context =
+        glutin::Context::new_egl_device(support::builder(), &device).unwrap();
+    unsafe { context.make_current().unwrap() };
+
+    let current_surface = context.get_proc_address("eglGetCurrentSurface");
+    let query_surface = context.get_proc_address("eglQuerySurface");
+    assert!(!current_surface.is_null() && !query_surface.is_null());
+    let current_surface: extern "system" fn(i32) -> *const c_void =
+        unsafe { mem::transmute(current_surface) };
+    let query_surface: extern "system" fn(
+        *const c_void,
+        *const c_void,
+        i32,
+        *mut i32,
+    ) -> u32 = unsafe { mem::transmute(query_surface) };
+
+    context.resize_pbuffer(64, 32).unwrap();
+    assert!(context.is_current());
+    let (display, surface) = unsafe {
+        (
+            context.get_egl_display().unwrap(),
+            context.get_egl_surface().unwrap(),
+        )
+    };
+    assert_eq!(current_surface(EGL_DRAW), surface);
+    let (mut width, mut height) = (0, 0);
+    assert_ne!(query_surface(display, surface, EGL_WIDTH, &mut width), 0);
+    assert_ne!(query_surface(display, surface, EGL_HEIGHT, &mut height), 0);
+    assert_eq!((width, height), (64, 32));
+
+    // a context without a surface has no pbuffer to resize
+    unsafe { context.unbind_surface().unwrap() };
+    assert!(context.resize_pbuffer(16, 16).is_err());
+}
+
+#[test]
+fn egl_display_outlives_context() {
+    let device = match support::egl_device() {
+        Some(device) => device,
+        None => return,
+    };
+    let context =
+        glutin::Context::new_egl_device(support::builder(), &device).unwrap();
+    let display = context.egl_display().unwrap();
+    let vendor = display.vendor();
+    assert!(vendor.is_some());
+
+    // the display holds the reference of the context, so it isn't terminated
+    drop(context);
+    assert!(display.is_initialized());
+    assert_eq!(display.vendor(), vendor);
+}
+
+#[test]
+fn client_extensions_are_queried_without_a_display() {
+    if support::egl_device().is_none() {
+        return;
+    }
+    // device displays need `EGL_EXT_platform_device`, a client extension
+    let client = EglDisplay::client_extensions();
+    assert!(client.iter().any(|e| e == "EGL_EXT_platform_device"));
+}
+
+#[test]
+fn oversized_color_bits_fail_without_overflowing() {
+    let device = match support::egl_device() {
+        Some(device) => device,
+        None => return,
+    };
+    let cb = support::builder().with_color_bits(200, 200, 200, 8);
+    match glutin::Context::new_egl_device(cb, &device) {
+        Err(glutin::CreationError::NoMatchingConfig { .. }) => {}
+        other => panic!("unexpected result: {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn flush_and_finish_require_a_current_context() {
+    let device = match support::egl_device() {
+        Some(device) => device,
+        None => return,
+    };
+    let context =
+        glutin::Context::new_egl_device(support::builder(), &device).unwrap();
+    match context.flush() {
+        Err(glutin::ContextError::NotCurrentOnThisThread) => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    unsafe { context.make_current().unwrap() };
+    // the second calls use the functions loaded by the first ones
+    for _ in 0..2 {
+        context.flush().unwrap();
+        context.finish().unwrap();
+    }
+}
+
+#[test]
+fn oversized_pbuffers_are_rejected() {
+    let device = match support::egl_device() {
+        Some(device) => device,
+        None => return,
+    };
+    let mut context =
+        glutin::Context::new_egl_device(support::builder(), &device).unwrap();
+    let (max_width, max_height) = context.max_buffer_size().unwrap();
+
+    match context.resize_pbuffer(max_width + 1, 1) {
+        Err(glutin::ContextError::OsError(ref msg))
+            if msg.contains("maximum") => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+    // the context keeps its pbuffer
+    unsafe { context.make_current().unwrap() };
+}
@@ -0,0 +1,14 @@
+extern crate glutin;
+
+use glutin::CreationError;
+use std::error::Error;
+use std::io;
+
+#[test]
+fn display_leaves_the_source_to_source() {
+    let source = io::Error::new(io::ErrorKind::NotFound, "libEGL.so.1");
+    let err = CreationError::NoBackendAvailable(Box::new(source));
+
+    assert_eq!(err.to_string(), "No backend is available");
+    assert_eq!(err.source().unwrap().to_string(), "libEGL.so.1");
+}
@@ -0,0 +1,76 @@
+#![cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+
+extern crate glutin;
+
+use glutin::os::unix::OsMesaContextExt;
+use glutin::{ContextTrait, CreationError};
+use std::mem;
+
+const GL_COLOR_BUFFER_BIT: u32 = 0x4000;
+
+/// Returns an OsMesa context, or `None` if the library isn't installed, in
+/// which case the tests that need it do nothing.
+fn osmesa_context() -> Option<glutin::Context> {
+    match glutin::Context::new_osmesa(glutin::ContextBuilder::new()) {
+        Ok(context) => Some(context),
+        Err(CreationError::NoBackendAvailable(_)) => {
+            eprintln!("OsMesa isn't available, skipping");
+            None
+        }
+        Err(err) => panic!("{}", err),
+    }
+}
+
+/// Clears the framebuffer of the current context to `color`.
+fn clear(context: &glutin::Context, color: [f32; 4]) {
+    unsafe {
+        let clear_color: extern "system" fn(f32, f32, f32, f32) =
+            mem::transmute(context.get_proc_address("glClearColor"));
+        let clear: extern "system" fn(u32) =
+            mem::transmute(context.get_proc_address("glClear"));
+        clear_color(color[0], color[1], color[2], color[3]);
+        clear(GL_COLOR_BUFFER_BIT);
+    }
+}
+
+/// Checks that the whole framebuffer is `width` by `height` and `rgba`.
+fn assert_framebuffer(
+    context: &glutin::Context,
+    width: u32,
+    height: u32,
+    rgba: [u8; 4],
+) {
+    let image = context.capture_framebuffer().unwrap();
+    assert_eq!((image.width, image.height), (width, height));
+    assert_eq!(image.data.len(), (width * height * 4) as usize);
+    assert!(image.data.chunks(4).all(|pixel| pixel == &rgba[..]));
+}
+
+#[test]
+fn resize_shrink_then_regrow() {
+    let mut context = match osmesa_context() {
+        Some(context) => context,
+        None => return,
+    };
+    unsafe { context.make_current().unwrap() };
+
+    context.resize_osmesa(64, 64).unwrap();
+    clear(&context, [1.0, 0.0, 0.0, 1.0]);
+    assert_framebuffer(&context, 64, 64, [255, 0, 0, 255]);
+
+    // the buffer is reused, and rendering only covers the new size
+    context.resize_osmesa(16, 8).unwrap();
+    clear(&context, [0.0, 1.0, 0.0, 1.0]);
+    assert_framebuffer(&context, 16, 8, [0, 255, 0, 255]);
+
+    // the buffer is reallocated, and rendering covers all of it
+    context.resize_osmesa(128, 32).unwrap();
+    clear(&context, [0.0, 0.0, 1.0, 1.0]);
+    assert_framebuffer(&context, 128, 32, [0, 0, 255, 255]);
+}
@@ -0,0 +1,23 @@
+#![allow(dead_code)]
+
+use glutin::os::unix::EglDevice;
+use glutin::ContextBuilder;
+
+/// Returns the first EGL device, or `None` if EGL or
+/// `EGL_EXT_device_enumeration` isn't available, in which case the tests
+/// that need it do nothing.
+///
+/// Mesa exposes its software renderer as a device, so the tests also run on
+/// machines without a GPU.
+pub fn egl_device() -> Option<EglDevice> {
+    let device = EglDevice::query_all().into_iter().next();
+    if device.is_none() {
+        eprintln!("no EGL device available, skipping");
+    }
+    device
+}
+
+/// A builder that accepts software renderers.
+pub fn builder<'a>() -> ContextBuilder<'a> {
+    ContextBuilder::new().with_hardware_acceleration(None)
+}